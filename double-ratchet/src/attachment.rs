@@ -0,0 +1,275 @@
+//! Defines the encryption of attachments which are too large to send as messages.
+//! 
+//! An attachment is encrypted under a fresh random key and the resulting [Descriptor] is
+//! sent to the partner Client as an ordinary message, for example with
+//! [Client::lock_value](crate::client::Client::lock_value).
+//! The ciphertext is split into chunks which are authenticated individually with
+//! AES-256-GCM; the nonce of each chunk encodes its index and whether it is the final
+//! chunk so chunks cannot be reordered, dropped or truncated without detection.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use clear_on_drop::ClearOnDrop;
+use ring::{aead, digest, constant_time,};
+use rand::{RngCore, CryptoRng,};
+use std::io::{self, Read, Write,};
+
+mod serde;
+
+/// The length of the plaintext of a chunk.
+pub const CHUNK_LENGTH: usize = 64 * 1024;
+/// The length of an authentication tag.
+const TAG_LENGTH: usize = 16;
+
+/// The information needed to decrypt and verify an attachment.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct Descriptor {
+  /// The key the attachment was encrypted with.
+  key: [u8; 32],
+  /// The SHA-256 digest of the ciphertext.
+  digest: [u8; 32],
+  /// The length of the plaintext.
+  size: u64,
+  /// The media type of the plaintext.
+  content_type: String,
+}
+
+impl Descriptor {
+  /// The SHA-256 digest of the ciphertext.
+  #[inline]
+  pub fn digest(&self,) -> &[u8; 32] { &self.digest }
+  /// The length of the plaintext.
+  #[inline]
+  pub fn size(&self,) -> u64 { self.size }
+  /// The media type of the plaintext.
+  #[inline]
+  pub fn content_type(&self,) -> &str { &self.content_type }
+}
+
+impl Drop for Descriptor {
+  #[inline]
+  fn drop(&mut self,) { ClearOnDrop::new(self.key.as_mut(),); }
+}
+
+/// Calculates the nonce of a chunk.
+/// 
+/// # Params
+/// 
+/// index --- The index of the chunk.  
+/// last --- Whether this is the final chunk.  
+fn nonce(index: u64, last: bool,) -> aead::Nonce {
+  let mut nonce = [0; 12];
+
+  nonce[..8].copy_from_slice(&index.to_be_bytes(),);
+  nonce[11] = last as u8;
+
+  aead::Nonce::assume_unique_for_key(nonce,)
+}
+
+/// Reads until `buffer` is full or the end of the input is reached.
+/// 
+/// # Params
+/// 
+/// input --- The input to read from.  
+/// buffer --- The buffer to read into.  
+fn read_full<I,>(input: &mut I, buffer: &mut [u8],) -> io::Result<usize>
+  where I: Read, {
+  let mut len = 0;
+
+  while len < buffer.len() {
+    match input.read(&mut buffer[len..],) {
+      Ok(0) => break,
+      Ok(read) => len += read,
+      Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+      Err(e) => return Err(e),
+    }
+  }
+
+  Ok(len)
+}
+
+/// Encrypts an attachment under a fresh key.
+/// 
+/// # Params
+/// 
+/// input --- The plaintext of the attachment.  
+/// output --- The output to write the ciphertext too.  
+/// content_type --- The media type of the plaintext.  
+#[inline]
+pub fn encrypt<I, O,>(input: I, output: O, content_type: &str,) -> Result<Descriptor, Error>
+  where I: Read, O: Write, {
+  encrypt_with_rng(input, output, content_type, &mut rand::thread_rng(),)
+}
+
+/// Encrypts an attachment under a fresh key using the passed source of randomness.
+/// 
+/// # Params
+/// 
+/// input --- The plaintext of the attachment.  
+/// output --- The output to write the ciphertext too.  
+/// content_type --- The media type of the plaintext.  
+/// rand --- The source of randomness used to generate the key.  
+pub fn encrypt_with_rng<I, O, Rand,>(mut input: I, mut output: O, content_type: &str, rand: &mut Rand,) -> Result<Descriptor, Error>
+  where I: Read, O: Write, Rand: RngCore + CryptoRng, {
+  let mut key = [0; 32];
+  rand.fill_bytes(&mut key,);
+
+  let sealing_key = aead::SealingKey::new(&aead::AES_256_GCM, &key,)
+    .map_err(|_,| Error::Encryption,)?;
+  let mut context = digest::Context::new(&digest::SHA256,);
+  let mut chunk = ClearOnDrop::new(vec![0; CHUNK_LENGTH + TAG_LENGTH],);
+  let mut next = ClearOnDrop::new(vec![0; CHUNK_LENGTH],);
+  let mut len = read_full(&mut input, &mut chunk[..CHUNK_LENGTH],)?;
+  let mut size = 0;
+
+  for index in 0.. {
+    //Read ahead to know if this is the last chunk.
+    let next_len = if len == CHUNK_LENGTH { read_full(&mut input, &mut next,)? } else { 0 };
+    let last = next_len == 0;
+    let sealed = aead::seal_in_place(&sealing_key, nonce(index, last,), aead::Aad::empty(), &mut chunk[..len + TAG_LENGTH], TAG_LENGTH,)
+      .map_err(|_,| Error::Encryption,)?;
+
+    context.update(&chunk[..sealed],);
+    output.write_all(&chunk[..sealed],)?;
+    size += len as u64;
+
+    if last { break }
+
+    chunk[..next_len].copy_from_slice(&next[..next_len],);
+    len = next_len;
+  }
+
+  output.flush()?;
+
+  let mut digest = [0; 32];
+  digest.copy_from_slice(context.finish().as_ref(),);
+
+  Ok(Descriptor { key, digest, size, content_type: content_type.to_owned(), })
+}
+
+/// Decrypts and verifies an attachment.
+/// 
+/// The plaintext is written as it is decrypted; if an error is returned the output must
+/// be discarded.
+/// 
+/// # Params
+/// 
+/// input --- The ciphertext of the attachment.  
+/// output --- The output to write the plaintext too.  
+/// descriptor --- The descriptor of the attachment.  
+pub fn decrypt<I, O,>(mut input: I, mut output: O, descriptor: &Descriptor,) -> Result<(), Error>
+  where I: Read, O: Write, {
+  let opening_key = aead::OpeningKey::new(&aead::AES_256_GCM, &descriptor.key,)
+    .map_err(|_,| Error::Decryption,)?;
+  let mut context = digest::Context::new(&digest::SHA256,);
+  let mut chunk = ClearOnDrop::new(vec![0; CHUNK_LENGTH + TAG_LENGTH],);
+  let mut next = vec![0; CHUNK_LENGTH + TAG_LENGTH];
+  let mut len = read_full(&mut input, &mut chunk,)?;
+  let mut size = 0;
+
+  for index in 0.. {
+    //Read ahead to know if this is the last chunk.
+    let next_len = if len == chunk.len() { read_full(&mut input, &mut next,)? } else { 0 };
+    let last = next_len == 0;
+
+    context.update(&chunk[..len],);
+
+    let plaintext = aead::open_in_place(&opening_key, nonce(index, last,), aead::Aad::empty(), 0, &mut chunk[..len],)
+      .map_err(|_,| Error::Decryption,)?;
+
+    size += plaintext.len() as u64;
+    if size > descriptor.size { return Err(Error::Size) }
+    output.write_all(plaintext,)?;
+
+    if last { break }
+
+    chunk[..next_len].copy_from_slice(&next[..next_len],);
+    len = next_len;
+  }
+
+  output.flush()?;
+
+  constant_time::verify_slices_are_equal(context.finish().as_ref(), &descriptor.digest,)
+    .map_err(|_,| Error::Digest,)?;
+  if size != descriptor.size { return Err(Error::Size) }
+
+  Ok(())
+}
+
+/// An error returned from encrypting or decrypting an attachment.
+#[derive(Debug,)]
+pub enum Error {
+  /// There was an error interacting with the IO.
+  Io(io::Error,),
+  /// A chunk could not be encrypted.
+  Encryption,
+  /// A chunk failed authentication or the attachment was truncated.
+  Decryption,
+  /// The digest of the ciphertext does not match the descriptor.
+  Digest,
+  /// The length of the plaintext does not match the descriptor.
+  Size,
+}
+
+impl From<io::Error> for Error {
+  #[inline]
+  fn from(from: io::Error,) -> Self { Error::Io(from,) }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_attachment() {
+    for &len in &[0, 10, CHUNK_LENGTH, CHUNK_LENGTH * 2 + 5,] {
+      let plaintext = (0..len).map(|i,| i as u8,).collect::<Vec<_>>();
+      let mut ciphertext = Vec::new();
+      let descriptor = encrypt(plaintext.as_slice(), &mut ciphertext, "image/png",)
+        .expect("Error encrypting attachment");
+
+      assert_eq!(descriptor.size(), len as u64, "Bad descriptor size",);
+      assert_eq!(descriptor.content_type(), "image/png", "Bad content type",);
+      let chunks = ((len + CHUNK_LENGTH - 1) / CHUNK_LENGTH).max(1,);
+      assert_eq!(ciphertext.len(), len + chunks * TAG_LENGTH, "Bad ciphertext length",);
+
+      let mut other = Vec::new();
+      decrypt(ciphertext.as_slice(), &mut other, &descriptor,).expect("Error decrypting attachment");
+      assert_eq!(plaintext, other, "Attachment corrupted",);
+    }
+  }
+  #[test]
+  fn test_attachment_tampering() {
+    let plaintext = vec![1; CHUNK_LENGTH * 2 + 5];
+    let mut ciphertext = Vec::new();
+    let descriptor = encrypt(plaintext.as_slice(), &mut ciphertext, "video/mp4",)
+      .expect("Error encrypting attachment");
+
+    //Test a modified chunk.
+    let mut modified = ciphertext.clone();
+    modified[10] ^= 1;
+    match decrypt(modified.as_slice(), &mut Vec::new(), &descriptor,) {
+      Err(Error::Decryption) => {},
+      _ => panic!("Decrypted a modified attachment"),
+    }
+
+    //Test a truncated attachment.
+    let truncated = &ciphertext[..(CHUNK_LENGTH + TAG_LENGTH) * 2];
+    match decrypt(truncated, &mut Vec::new(), &descriptor,) {
+      Err(Error::Decryption) => {},
+      _ => panic!("Decrypted a truncated attachment"),
+    }
+
+    //Test a different attachment.
+    let mut other = Vec::new();
+    let other_descriptor = encrypt(plaintext.as_slice(), &mut other, "video/mp4",)
+      .expect("Error encrypting attachment");
+    let mut mixed = descriptor.clone();
+    mixed.digest = *other_descriptor.digest();
+    match decrypt(ciphertext.as_slice(), &mut Vec::new(), &mixed,) {
+      Err(Error::Digest) => {},
+      _ => panic!("Decrypted an attachment with the wrong digest"),
+    }
+  }
+}
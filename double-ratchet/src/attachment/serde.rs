@@ -0,0 +1,80 @@
+//! Defines serde for the Descriptor type.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+
+static FIELDS: &[&str] = &[
+  "key",
+  "digest",
+  "size",
+  "content_type",
+];
+
+impl Serialize for Descriptor {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(Descriptor,), FIELDS.len(),)?;
+
+    serializer.serialize_field(&self.key,)?;
+    serializer.serialize_field(&self.digest,)?;
+    serializer.serialize_field(&self.size,)?;
+    serializer.serialize_field(&self.content_type,)?;
+    serializer.end()
+  }
+}
+
+impl<'de,> Deserialize<'de> for Descriptor {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct DescriptorVisitor;
+
+    impl<'de,> Visitor<'de> for DescriptorVisitor {
+      type Value = Descriptor;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let key = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+        let digest = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+        let size = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[2],),)?;
+        let content_type = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[3],),)?;
+
+        Ok(Descriptor { key, digest, size, content_type, })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(Descriptor,), FIELDS.len(), DescriptorVisitor,)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_descriptor_serde() {
+    let descriptor = encrypt([1; 100].as_ref(), io::sink(), "text/plain",)
+      .expect("Error encrypting attachment");
+    let serialised = serde_cbor::ser::to_vec_packed(&descriptor,)
+      .expect("Error serialising the Descriptor");
+    let other = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the Descriptor");
+
+    assert_eq!(descriptor, other, "Descriptor deserialised incorrectly",);
+  }
+}
@@ -1,27 +1,180 @@
 //! Defines the double ratchet clients [LocalClient] and [RemoteClient].
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-21
+//! Last Moddified --- 2026-10-18
 
 use crate::{
   ratchet::Ratchet,
-  message::Message,
+  message::{Message, Header, Extensions, Registry, ChainFingerprint,},
+  padding::Padding,
+  franking::Opening,
+  clock::{Clock, SystemClock,},
+  observer::{Observer, Event,},
+  transcript::Checkpoint,
   typenum::consts::{self, U32,},
   generic_array::{ArrayLength, GenericArray,},
 };
 use clear_on_drop::ClearOnDrop;
+use ring::{digest, hmac, constant_time,};
 use rand::{RngCore, CryptoRng,};
 use x25519_dalek::{PublicKey, StaticSecret,};
-use std::collections::HashMap;
+use ::serde::{Serialize, de::DeserializeOwned,};
+use std::{collections::{HashMap, HashSet,}, sync::Arc, time::Duration,};
 
 pub mod aead;
 mod open_data;
 mod lock;
 mod open;
+mod pending;
+mod delegate;
+mod info;
+mod split;
 mod serde;
 
-use self::{aead::{Algorithm, Aes256Gcm,}, open_data::OpenData, lock::*, open::*,};
-use crate::framed::Framed;
+use self::{aead::{Algorithm, Aes256Gcm,}, open_data::OpenData, lock::*, open::*, pending::*, info::fingerprint,};
+pub use self::{delegate::DelegatedKey, info::SessionInfo, split::{LockHalf, OpenHalf,},};
+use crate::{framed::Framed, fingerprint::SafetyNumber,};
+
+/// Defines the methods which read and configure the state of a Client.
+/// 
+/// The type must define `inner` and `inner_mut` returning its [InnerClient] and `is_local`
+/// returning whether it took the initiating role.
+macro_rules! state_accessors {
+  () => {
+    /// The identity public key of the remote Client.
+    #[inline]
+    pub fn remote_identity(&self,) -> $crate::x25519_dalek::PublicKey { self.inner().remote_identity() }
+    /// The [Padding](crate::padding::Padding) policy applied to messages.
+    #[inline]
+    pub fn padding(&self,) -> $crate::padding::Padding { self.inner().padding() }
+    /// Sets the [Padding](crate::padding::Padding) policy applied to messages.
+    /// 
+    /// Both Clients must use the same policy; messages are padded when they are locked
+    /// and the padding is checked and removed when they are opened.
+    /// 
+    /// # Params
+    /// 
+    /// padding --- The new padding policy.  
+    #[inline]
+    pub fn set_padding(&mut self, padding: $crate::padding::Padding,) { self.inner_mut().set_padding(padding,) }
+    /// Indicates whether locked messages are [franked](crate::franking).
+    #[inline]
+    pub fn franking(&self,) -> bool { self.inner().franking() }
+    /// Sets whether locked messages are [franked](crate::franking).
+    /// 
+    /// A franked message commits to its data so the remote Client can report it to a
+    /// [Moderator](crate::franking::Moderator); the remote Client checks the commitment
+    /// of every franked message it opens.
+    /// 
+    /// # Params
+    /// 
+    /// franking --- `true` if locked messages should be franked.  
+    #[inline]
+    pub fn set_franking(&mut self, franking: bool,) { self.inner_mut().set_franking(franking,) }
+    /// The franking [Opening](crate::franking::Opening) of the last message opened or `None` if it was not franked.
+    /// 
+    /// The opening and message data form a [Report](crate::franking::Report) of the message.
+    #[inline]
+    pub fn opening(&self,) -> Option<&$crate::franking::Opening> { self.inner().opening() }
+    /// The time after which locked messages expire.
+    #[inline]
+    pub fn expiry(&self,) -> Option<::std::time::Duration> { self.inner().expiry() }
+    /// Sets the time after which locked messages expire.
+    /// 
+    /// The expiry of a message is authenticated and the remote Client refuses to open
+    /// the message once it has passed, destroying its key.
+    /// 
+    /// # Params
+    /// 
+    /// expiry --- The lifetime of locked messages or `None` if they never expire.  
+    #[inline]
+    pub fn set_expiry(&mut self, expiry: Option<::std::time::Duration>,) { self.inner_mut().set_expiry(expiry,) }
+    /// The time keys of previous steps are retained for.
+    #[inline]
+    pub fn retention(&self,) -> Option<::std::time::Duration> { self.inner().retention() }
+    /// Sets the time keys of previous steps are retained for.
+    /// 
+    /// Once a ratchet step has been replaced for longer than the retention window the
+    /// keys of its skipped messages are destroyed.
+    /// 
+    /// # Params
+    /// 
+    /// retention --- The retention window or `None` if keys are kept until used.  
+    #[inline]
+    pub fn set_retention(&mut self, retention: Option<::std::time::Duration>,) { self.inner_mut().set_retention(retention,) }
+    /// Sets the [Clock](crate::clock::Clock) used to expire messages and keys.
+    /// 
+    /// # Params
+    /// 
+    /// clock --- The source of the current time.  
+    #[inline]
+    pub fn set_clock<C,>(&mut self, clock: C,)
+      where C: 'static + $crate::clock::Clock + Send + Sync, { self.inner_mut().set_clock(clock,) }
+    /// Sets the [Observer](crate::observer::Observer) notified of ratchet events.
+    /// 
+    /// Events never contain any secrets so an observer can safely log them.
+    /// 
+    /// # Params
+    /// 
+    /// observer --- The observer of ratchet events.  
+    #[inline]
+    pub fn set_observer<O,>(&mut self, observer: O,)
+      where O: 'static + $crate::observer::Observer + Send + Sync, { self.inner_mut().set_observer(observer,) }
+    /// The extension types understood by this Client.
+    #[inline]
+    pub fn registry(&self,) -> &$crate::message::Registry { self.inner().registry() }
+    /// The extension types understood by this Client.
+    /// 
+    /// Messages carrying a critical extension which is not registered are refused.
+    #[inline]
+    pub fn registry_mut(&mut self,) -> &mut $crate::message::Registry { self.inner_mut().registry_mut() }
+    /// A [Checkpoint](crate::transcript::Checkpoint) of the messages this Client has sent and received.
+    /// 
+    /// The checkpoint should be sent to the remote Client, for instance using `lock_value`,
+    /// to be compared with `verify_checkpoint`.
+    #[inline]
+    pub fn checkpoint(&self,) -> $crate::transcript::Checkpoint { self.inner().checkpoint() }
+    /// A [SessionInfo](crate::client::SessionInfo) snapshot of the state of this Client.
+    /// 
+    /// The snapshot never contains any secrets and is cheap enough to take periodically
+    /// when monitoring many Clients.
+    #[inline]
+    pub fn info(&self,) -> $crate::client::SessionInfo { self.inner().info(self.is_local(),) }
+    /// Compares the transcript of this Client with a [Checkpoint](crate::transcript::Checkpoint) from the remote Client.
+    /// 
+    /// # Params
+    /// 
+    /// remote --- The latest checkpoint of the remote Client.  
+    #[inline]
+    pub fn verify_checkpoint(&self, remote: &$crate::transcript::Checkpoint,) -> Result<(), $crate::transcript::Error> {
+      self.inner().checkpoint().verify(remote,)
+    }
+    /// The [SafetyNumber](crate::fingerprint::SafetyNumber) of the identity keys this Client was established with.
+    /// 
+    /// Users compare the displayed `SafetyNumber` of both Clients to verify each others
+    /// identity.
+    #[inline]
+    pub fn safety_number(&self,) -> $crate::fingerprint::SafetyNumber { self.inner().safety_number() }
+    /// Derives application keying material from the session.
+    /// 
+    /// The output is independent of the message keys and differs for every `label` and
+    /// `context`.
+    /// The epoch of the exporter secret is returned with the output; both Clients derive the
+    /// same output only while they report the same epoch.
+    /// The epoch advances when the initiating Client takes a ratchet step and again when the
+    /// remote Client opens the first message of that step.
+    /// 
+    /// # Params
+    /// 
+    /// label --- Identifies the purpose of the keying material.  
+    /// context --- Application data bound to the keying material.  
+    /// len --- The number of bytes to derive.  
+    #[inline]
+    pub fn export_keying_material(&self, label: &[u8], context: &[u8], len: usize,) -> Result<([u8; 8], Vec<u8>,), $crate::client::Error> {
+      self.inner().export_keying_material(label, context, len,)
+    }
+  };
+}
 
 /// The initiating end of a Double-Ratchet comunication.
 /// 
@@ -45,20 +198,218 @@ impl<D, S, A, R, L,> LocalClient<D, S, A, R, L,>
   /// 
   /// remote --- The public key of the remote Client.  
   /// private_key --- The private key to connect using.  
+  #[inline]
   pub fn connect(remote: &PublicKey, private_key: &StaticSecret,) -> Self {
-    let mut client = Box::<InnerClient<D, S, A, R, L,>>::default();
-    let mut ratchet = Ratchet::from(private_key.diffie_hellman(remote,).as_bytes().clone().as_mut(),);
+    Self::connect_with_rng(remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Initiates communication with a remote Client using the passed source of randomness.
+  /// 
+  /// The function preceeds a call to `accept_with_rng`.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  #[inline]
+  pub fn connect_with_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    Self::connect_with_psk_and_rng(remote, private_key, None, rand,)
+  }
+  /// Initiates communication with a remote Client mixing a pre-shared key into the
+  /// session.
+  /// 
+  /// The function preceeds a call to `accept_with_psk` with the same `psk`.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  #[inline]
+  pub fn connect_with_psk(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>,) -> Self {
+    Self::connect_with_psk_and_rng(remote, private_key, psk, &mut rand::thread_rng(),)
+  }
+  /// Initiates communication with a remote Client mixing a pre-shared key into the
+  /// session using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn connect_with_psk_and_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    let mut seed = establishment_seed(remote, private_key, psk,);
+
+    LocalClient(InnerClient::establish(seed.as_mut_slice(), remote, &PublicKey::from(private_key,), true, rand,),)
+  }
+  /// Constructs a Client from a secret already agreed with the remote Client.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The secret shared with the remote Client, it will be cleared after use.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub(crate) fn from_seed_with_rng<Rand,>(seed: &mut [u8], remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    LocalClient(InnerClient::establish(seed, remote, &PublicKey::from(private_key,), true, rand,),)
+  }
+  /// Initiates communication with a remote Client which may also have initiated
+  /// communication.
+  /// 
+  /// The Client keeps the seed it was established from until it commits its first ratchet
+  /// step so that it can be demoted using `demote_with_rng`; it cannot be serialised until
+  /// then because the seed derives every chain of the session.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub(crate) fn contend_with_psk_and_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    let mut seed = establishment_seed(remote, private_key, psk,);
+    let standby = seed.clone();
+    let mut client = InnerClient::establish(seed.as_mut_slice(), remote, &PublicKey::from(private_key,), true, rand,);
+
+    client.standby = Some(standby);
+    LocalClient(client,)
+  }
+  /// Returns `true` if `message` was locked by a remote Client which also initiated
+  /// communication and this Client can be demoted to open it.
+  /// 
+  /// A remote Client in the partner role steps to a new key as soon as it is established
+  /// so only an initiating Client locks messages under the first key drawn from the seed,
+  /// which is the same for both initiating Clients.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message received from the remote Client.  
+  pub(crate) fn contended(&self, message: &Message,) -> bool {
+    self.0.standby.is_some() && message.header.public_key == self.0.lock.next_header.public_key
+  }
+  /// Re-establishes this Client in the partner role and opens the first message of a
+  /// remote Client which also initiated communication.
+  /// 
+  /// The returned Client takes the settings and observer of this Client and has a
+  /// pending open of `message`.
+  /// Messages locked by this Client cannot be opened by the remote Client.
+  /// If `message` does not open in the partner role this Client is unchanged.
+  /// 
+  /// # Params
+  /// 
+  /// message --- A message for which `contended` returned `true`.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub(crate) fn demote_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<(RemoteClient<D, S, A, R, L,>, &'a mut [u8],), (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    let inner = &mut self.0;
+    //Only a single open can be pending.
+    if inner.pending.is_some() { return Err((message, Error::Pending,)) }
 
-    client.private_key.copy_from_slice(&StaticSecret::new(&mut rand::thread_rng(),).to_bytes().as_ref(),);
+    let mut seed = match &inner.standby {
+      Some(seed) => seed.clone(),
+      None => return Err((message, Error::Decryption,)),
+    };
+    let mut client = InnerClient::establish(
+      seed.as_mut_slice(),
+      &PublicKey::from(inner.remote_identity,),
+      &PublicKey::from(inner.local_identity,),
+      false,
+      rand,
+    );
 
-    client.lock.ratchet.reseed(&mut ratchet,);
-    client.lock.next_header.public_key.copy_from_slice(PublicKey::from(private_key,).as_bytes().as_ref(),);
+    client.padding = inner.padding;
+    client.franking = inner.franking;
+    client.expiry = inner.expiry;
+    client.retention = inner.retention;
+    client.clock = inner.clock.clone();
+    client.registry = inner.registry.clone();
 
-    client.open.ratchet.reseed(&mut ratchet,);
-    client.open.current_public_key.copy_from_slice(remote.as_bytes().as_ref(),);
+    let data = client.open_pending(message, buffer, false, rand,)?;
 
-    LocalClient(client,)
+    client.observer = inner.observer.take();
+    inner.standby = None;
+
+    Ok((RemoteClient(client,), data,))
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client.
+  /// 
+  /// The key is only handed out once the ciphertext and extensions of the message
+  /// authenticate; the plaintext is discarded. Use `delegate_header` when only the header
+  /// is available.
+  /// The returned [DelegatedKey] opens only `message`; this Client will refuse the
+  /// message with [Error::Delegated].
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  #[inline]
+  pub fn delegate(&mut self, message: &Message,) -> Result<DelegatedKey<A, L,>, Error> {
+    self.delegate_with_rng(message, &mut rand::thread_rng(),)
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client
+  /// using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  #[inline]
+  pub fn delegate_with_rng<Rand,>(&mut self, message: &Message, rand: &mut Rand,) -> Result<DelegatedKey<A, L,>, Error>
+    where Rand: RngCore + CryptoRng, {
+    self.0.delegate(message, true, rand,)
   }
+  /// Extracts the key of a message from its header without authenticating the message.
+  /// 
+  /// The header is not authenticated so it never takes a ratchet step:
+  /// 
+  /// * a header of the current step derives the keys of the step up to its index; the keys
+  ///   are the keys the real messages are locked with so a forged index only derives them
+  ///   early.
+  /// * a header of a retained previous step hands out its skipped key.
+  /// * a header of any other step is refused with [Error::NoKey]; the whole message must be
+  ///   opened or passed to `delegate` to take the step.
+  /// 
+  /// The expiry of the message is checked when the [DelegatedKey] opens it.
+  /// The header is recorded in the transcript as if the message was opened so a forged
+  /// header is revealed when checkpoints are compared.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message to delegate.  
+  #[inline]
+  pub fn delegate_header(&mut self, header: &Header,) -> Result<DelegatedKey<A, L,>, Error> {
+    self.0.delegate_header(header,)
+  }
+  /// Splits this Client into a [LockHalf] and an [OpenHalf] which can be used from
+  /// different threads.
+  /// 
+  /// Any pending open is aborted; ratchet steps taken by the open half are handed to the
+  /// lock half once they are committed.
+  #[inline]
+  pub fn split(self,) -> (LockHalf<D, S, A, R, L,>, OpenHalf<D, S, A, R, L,>,) { split::split(self.0, true,) }
+}
+
+impl<D, S, A, R, L,> LocalClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// The state of this Client.
+  #[inline]
+  pub(crate) fn inner(&self,) -> &InnerClient<D, S, A, R, L,> { &self.0 }
+  /// The state of this Client.
+  #[inline]
+  pub(crate) fn inner_mut(&mut self,) -> &mut InnerClient<D, S, A, R, L,> { &mut self.0 }
+  /// Returns `true` as this end took the initiating role.
+  #[inline]
+  pub(crate) fn is_local(&self,) -> bool { true }
+
+  state_accessors!();
 }
 
 impl<D, S, A, R, L,> Client for LocalClient<D, S, A, R, L,>
@@ -67,12 +418,18 @@ impl<D, S, A, R, L,> Client for LocalClient<D, S, A, R, L,>
     L: ArrayLength<u8>,
     Ratchet<D, S, R,>: RngCore + CryptoRng, {
   #[inline]
-  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
-    self.0.open(message, buffer, true,)
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    self.0.open_pending(message, buffer, true, rand,)
   }
   #[inline]
-  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
-    self.0.lock(message,)
+  fn commit(&mut self,) { self.0.commit() }
+  #[inline]
+  fn abort(&mut self,) { self.0.abort() }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    self.0.lock(message, extensions, rand,)
   }
 }
 
@@ -98,20 +455,127 @@ impl<D, S, A, R, L,> RemoteClient<D, S, A, R, L,>
   /// 
   /// remote --- The public key of the remote Client.  
   /// private_key --- The private key to connect using.  
+  #[inline]
   pub fn accept(remote: &PublicKey, private_key: &StaticSecret,) -> Self {
-    let mut client = Box::<InnerClient<D, S, A, R, L,>>::default();
-    let mut ratchet = Ratchet::from(private_key.diffie_hellman(remote,).as_bytes().clone().as_mut(),);
-
-    client.private_key.copy_from_slice(StaticSecret::new(&mut rand::thread_rng(),).to_bytes().as_ref(),);
+    Self::accept_with_rng(remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Accepts communication from a remote Client using the passed source of randomness.
+  /// 
+  /// The function follows a call to `connect_with_rng`.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  #[inline]
+  pub fn accept_with_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    Self::accept_with_psk_and_rng(remote, private_key, None, rand,)
+  }
+  /// Accepts communication from a remote Client mixing a pre-shared key into the
+  /// session.
+  /// 
+  /// The function follows a call to `connect_with_psk` with the same `psk`.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  #[inline]
+  pub fn accept_with_psk(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>,) -> Self {
+    Self::accept_with_psk_and_rng(remote, private_key, psk, &mut rand::thread_rng(),)
+  }
+  /// Accepts communication from a remote Client mixing a pre-shared key into the
+  /// session using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn accept_with_psk_and_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    let mut seed = establishment_seed(remote, private_key, psk,);
 
-    client.open.ratchet.reseed(&mut ratchet,);
-    client.open.current_public_key.copy_from_slice(remote.as_bytes().as_ref(),);
+    RemoteClient(InnerClient::establish(seed.as_mut_slice(), remote, &PublicKey::from(private_key,), false, rand,),)
+  }
+  /// Constructs a Client from a secret already agreed with the remote Client.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The secret shared with the remote Client, it will be cleared after use.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub(crate) fn from_seed_with_rng<Rand,>(seed: &mut [u8], remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    RemoteClient(InnerClient::establish(seed, remote, &PublicKey::from(private_key,), false, rand,),)
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client.
+  /// 
+  /// The key is only handed out once the ciphertext and extensions of the message
+  /// authenticate; the plaintext is discarded. Use `delegate_header` when only the header
+  /// is available.
+  /// The returned [DelegatedKey] opens only `message`; this Client will refuse the
+  /// message with [Error::Delegated].
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  #[inline]
+  pub fn delegate(&mut self, message: &Message,) -> Result<DelegatedKey<A, L,>, Error> {
+    self.delegate_with_rng(message, &mut rand::thread_rng(),)
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client
+  /// using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  #[inline]
+  pub fn delegate_with_rng<Rand,>(&mut self, message: &Message, rand: &mut Rand,) -> Result<DelegatedKey<A, L,>, Error>
+    where Rand: RngCore + CryptoRng, {
+    self.0.delegate(message, false, rand,)
+  }
+  /// Extracts the key of a message from its header without authenticating the message.
+  /// 
+  /// The header cannot take a ratchet step, see [LocalClient::delegate_header].
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message to delegate.  
+  #[inline]
+  pub fn delegate_header(&mut self, header: &Header,) -> Result<DelegatedKey<A, L,>, Error> {
+    self.0.delegate_header(header,)
+  }
+  /// Splits this Client into a [LockHalf] and an [OpenHalf] which can be used from
+  /// different threads.
+  /// 
+  /// Any pending open is aborted; ratchet steps taken by the open half are handed to the
+  /// lock half once they are committed.
+  #[inline]
+  pub fn split(self,) -> (LockHalf<D, S, A, R, L,>, OpenHalf<D, S, A, R, L,>,) { split::split(self.0, false,) }
+}
 
-    client.lock.ratchet.reseed(&mut ratchet,);
-    client.lock.next_header.public_key.copy_from_slice(PublicKey::from(private_key,).as_bytes().as_ref(),);
+impl<D, S, A, R, L,> RemoteClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// The state of this Client.
+  #[inline]
+  pub(crate) fn inner(&self,) -> &InnerClient<D, S, A, R, L,> { &self.0 }
+  /// The state of this Client.
+  #[inline]
+  pub(crate) fn inner_mut(&mut self,) -> &mut InnerClient<D, S, A, R, L,> { &mut self.0 }
+  /// Returns `false` as this end took the partner role.
+  #[inline]
+  pub(crate) fn is_local(&self,) -> bool { false }
 
-    RemoteClient(client,)
-  }
+  state_accessors!();
 }
 
 impl<D, S, A, R, L,> Client for RemoteClient<D, S, A, R, L,>
@@ -120,15 +584,58 @@ impl<D, S, A, R, L,> Client for RemoteClient<D, S, A, R, L,>
     L: ArrayLength<u8>,
     Ratchet<D, S, R,>: RngCore + CryptoRng, {
   #[inline]
-  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
-    self.0.open(message, buffer, false,)
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    self.0.open_pending(message, buffer, false, rand,)
   }
   #[inline]
-  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
-    self.0.lock(message,)
+  fn commit(&mut self,) { self.0.commit() }
+  #[inline]
+  fn abort(&mut self,) { self.0.abort() }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    self.0.lock(message, extensions, rand,)
   }
 }
 
+/// The HKDF info prefix of exported keying material.
+const EXPORT_INFO: &[u8] = b"DoubleRatchetExporter";
+/// The maximum length of exported keying material.
+const EXPORT_MAX_LENGTH: usize = 255 * 32;
+/// The HKDF info used to mix a pre-shared key into a session.
+const PSK_INFO: &[u8] = b"DoubleRatchetPSK";
+/// The HKDF info used to derive the chains of a ratchet step.
+const STEP_INFO: &[u8] = b"DoubleRatchetStep";
+/// The HMAC input used to derive the epoch of the exporter secret.
+const EPOCH_INFO: &[u8] = b"DoubleRatchetEpoch";
+
+/// Derives the seed of a new session.
+/// 
+/// Without a pre-shared key the seed is the shared secret of the identity keys;
+/// otherwise the pre-shared key is mixed in as the salt of HKDF-SHA256.
+/// 
+/// # Params
+/// 
+/// remote --- The public key of the remote Client.  
+/// private_key --- The private key to connect using.  
+/// psk --- The secret shared with the remote Client out of band.  
+fn establishment_seed(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>,) -> ClearOnDrop<GenericArray<u8, U32>> {
+  use ring::{digest, hmac, hkdf,};
+
+  let mut seed = ClearOnDrop::new(GenericArray::default(),);
+
+  seed.copy_from_slice(private_key.diffie_hellman(remote,).as_bytes(),);
+  if let Some(psk) = psk {
+    let salt = hmac::SigningKey::new(&digest::SHA256, psk,);
+    let secret = seed.clone();
+
+    hkdf::extract_and_expand(&salt, &secret, PSK_INFO, &mut seed,);
+  }
+
+  seed
+}
+
 /// A double ratchet Client connected to a partner Client.
 /// 
 /// Bare in mind that Both Clients must be constructed with the same ADT parameters if
@@ -143,6 +650,42 @@ pub(crate) struct InnerClient<Digest, State, Algorithm, Rounds, AadLength,>
   open: OpenClient<Digest, State, Algorithm, Rounds, AadLength,>,
   /// The private key needed to decrypt messages received from the remote Client in the next ratchet step.
   private_key: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The root key the chains of every ratchet step are derived from.
+  root: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The identity public key of this Client.
+  local_identity: [u8; 32],
+  /// The identity public key of the remote Client.
+  remote_identity: [u8; 32],
+  /// The secret application keying material is exported from.
+  exporter: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The padding policy applied to locked and opened messages.
+  padding: Padding,
+  /// Indicates whether locked messages are franked.
+  franking: bool,
+  /// The franking opening of the last message opened.
+  opening: Option<Opening>,
+  /// The time after which locked messages expire.
+  expiry: Option<Duration>,
+  /// The time keys of previous steps are retained for.
+  retention: Option<Duration>,
+  /// The source of the current time.
+  clock: Arc<dyn Clock + Send + Sync>,
+  /// The observer of ratchet events.
+  observer: Option<Box<dyn Observer + Send + Sync>>,
+  /// The extension types understood by this Client.
+  registry: Registry,
+  /// The transcript of the messages sent and received.
+  transcript: Checkpoint,
+  /// The public keys and indices of the messages of the current step whose keys were
+  /// delegated.
+  delegated: HashSet<([u8; 32], u32,)>,
+  /// The state needed to rollback an open which has not been committed.
+  pending: Option<Pending<Digest, State, Algorithm, Rounds, AadLength,>>,
+  /// The seed needed to re-establish an initiating Client in the partner role if the
+  /// remote Client also initiated communication.
+  /// 
+  /// It is dropped as soon as a ratchet step is committed and is never serialised.
+  standby: Option<ClearOnDrop<GenericArray<u8, U32>>>,
 }
 
 impl<D, S, A, R, L,> InnerClient<D, S, A, R, L,>
@@ -150,151 +693,753 @@ impl<D, S, A, R, L,> InnerClient<D, S, A, R, L,>
     A: Algorithm,
     L: ArrayLength<u8>,
     Ratchet<D, S, R,>: RngCore + CryptoRng, {
-  /// Receives a message from the connected Client.
+  /// Constructs a new `InnerClient` from shared secret material.
   /// 
-  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// `seed` will be cleared after use.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The secret shared with the remote Client.  
+  /// remote --- The public key of the remote Client.  
+  /// identity --- The public key of this Client.  
+  /// local --- Indicates whether this Client is the initiator of the communication.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub(crate) fn establish<Rand,>(seed: &mut [u8], remote: &PublicKey, identity: &PublicKey, local: bool, rand: &mut Rand,) -> Box<Self>
+    where Rand: RngCore + CryptoRng, {
+    let mut client = Box::<Self>::default();
+    let mut ratchet = Ratchet::from(seed,);
+
+    client.reseed(&mut ratchet, local,);
+    //The root key is drawn after the chains so both Clients derive the same value.
+    ratchet.fill_bytes(&mut client.root,);
+
+    //The first step of the remote Client is taken against a key drawn from the seed so
+    //that the identity key of the initiating Client is never kept.
+    let first_key = StaticSecret::new(&mut ratchet,);
+
+    if local {
+      client.private_key.copy_from_slice(ClearOnDrop::new(&mut first_key.to_bytes(),).as_ref(),);
+      client.lock.next_header.public_key.copy_from_slice(PublicKey::from(&first_key,).as_bytes().as_ref(),);
+      client.open.current_public_key.copy_from_slice(remote.as_bytes().as_ref(),);
+    } else {
+      client.open.current_public_key.copy_from_slice(PublicKey::from(&first_key,).as_bytes().as_ref(),);
+    }
+
+    client.local_identity = *identity.as_bytes();
+    client.remote_identity = *remote.as_bytes();
+
+    //The remote Client steps immediately so that the initiating Client steps as soon as
+    //it receives a reply.
+    if !local { client.step_lock(local, rand,); }
+
+    client
+  }
+  /// Reseeds both chains from a shared seed and derives the fingerprint of the new step.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The Ratchet shared with the remote Client.  
+  /// local --- Indicates whether this Client is the initiator of the communication.  
+  fn reseed(&mut self, seed: &mut Ratchet<D, S, R,>, local: bool,) {
+    if local {
+      self.lock.ratchet.reseed(seed,);
+      self.open.ratchet.reseed(seed,);
+    } else {
+      self.open.ratchet.reseed(seed,);
+      self.lock.ratchet.reseed(seed,);
+    }
+
+    //The fingerprint is drawn after both chains so both Clients derive the same value.
+    let mut chain_fingerprint = [0; 8];
+    seed.fill_bytes(&mut chain_fingerprint,);
+    self.lock.chain_fingerprint = Some(chain_fingerprint);
+    self.open.chain_fingerprint = Some(chain_fingerprint);
+
+    self.mix_exporter(seed,);
+  }
+  /// Reseeds the locking chain from the seed of a new ratchet step.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The Ratchet derived for the step.  
+  /// local --- Indicates whether this Client is the initiator of the communication.  
+  fn reseed_lock(&mut self, seed: &mut Ratchet<D, S, R,>, local: bool,) {
+    self.lock.ratchet.reseed(seed,);
+
+    let mut chain_fingerprint = [0; 8];
+    seed.fill_bytes(&mut chain_fingerprint,);
+    self.lock.chain_fingerprint = Some(chain_fingerprint);
+
+    //Only the steps of the initiating Client are mixed into the exporter so that both
+    //Clients mix the same steps in the same order.
+    if local { self.mix_exporter(seed,) }
+  }
+  /// Reseeds the opening chain from the seed of a new ratchet step.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The Ratchet derived for the step.  
+  /// local --- Indicates whether this Client is the initiator of the communication.  
+  fn reseed_open(&mut self, seed: &mut Ratchet<D, S, R,>, local: bool,) {
+    self.open.ratchet.reseed(seed,);
+
+    let mut chain_fingerprint = [0; 8];
+    seed.fill_bytes(&mut chain_fingerprint,);
+    self.open.chain_fingerprint = Some(chain_fingerprint);
+
+    if !local { self.mix_exporter(seed,) }
+  }
+  /// Mixes the next exporter secret into the previous secret.
+  /// 
+  /// # Params
+  /// 
+  /// seed --- The Ratchet the next exporter secret is drawn from.  
+  fn mix_exporter(&mut self, seed: &mut Ratchet<D, S, R,>,) {
+    let mut step_secret = ClearOnDrop::new(GenericArray::<u8, U32>::default(),);
+    seed.fill_bytes(&mut step_secret,);
+    let key = hmac::SigningKey::new(&digest::SHA256, &self.exporter,);
+    self.exporter.copy_from_slice(hmac::sign(&key, &step_secret,).as_ref(),);
+  }
+  /// Derives the seed of the next chain from the root key and the shared secret of the
+  /// private key and the current public key of the remote Client.
+  /// 
+  /// The root key is advanced.
+  fn next_chain(&mut self,) -> Ratchet<D, S, R,> {
+    use ring::hkdf;
+    use std::mem;
+
+    let mut secret = ClearOnDrop::new(GenericArray::<u8, U32>::default(),);
+    secret.copy_from_slice(unsafe {
+      //These calls are safe because we can only initialise these fields with these
+      //types or deserialisation.
+      let private = mem::transmute::<_, &StaticSecret>(&self.private_key,);
+      let public = mem::transmute::<_, &PublicKey>(&self.open.current_public_key,);
+
+      private.diffie_hellman(public,)
+    }.as_bytes(),);
+
+    let salt = hmac::SigningKey::new(&digest::SHA256, &self.root,);
+    let mut output = ClearOnDrop::new(GenericArray::<u8, consts::U64>::default(),);
+    hkdf::extract_and_expand(&salt, &secret, STEP_INFO, &mut output,);
+    self.root.copy_from_slice(&output[..32],);
+
+    Ratchet::from(&mut output[32..],)
+  }
+  /// Steps the locking chain to a new private key and returns the replaced locking state.
+  /// 
+  /// # Params
+  /// 
+  /// local --- Indicates whether this Client is the initiator of the communication.  
+  /// rand --- The source of randomness used to generate the new private key.  
+  fn step_lock<Rand,>(&mut self, local: bool, rand: &mut Rand,) -> LockClient<D, S, A, R, L,>
+    where Rand: RngCore + CryptoRng, {
+    use std::mem;
+
+    //Update the private key.
+    let next_private_key = StaticSecret::new(rand,);
+    self.private_key.copy_from_slice(
+      ClearOnDrop::new(
+        &mut next_private_key.to_bytes(),
+      ).as_ref(),
+    );
+
+    //Update the lock state.
+    let previous_step = self.lock.next_header.message_index;
+    let lock = mem::replace(&mut self.lock, LockClient::default(),);
+    self.lock.next_header.public_key.copy_from_slice(PublicKey::from(&next_private_key,).as_bytes().as_ref(),);
+    self.lock.next_header.previous_step = previous_step;
+
+    let mut seed = self.next_chain();
+    self.reseed_lock(&mut seed, local,);
+
+    lock
+  }
+  /// Decrypts a message from the connected Client without consuming its key.
+  /// 
+  /// If the message is decrypted successfully the message data is appended to `buffer`
+  /// and the open remains pending until it is passed to `commit` or `abort`.
   /// 
   /// # Params
   /// 
   /// message --- The Message to decrypt.  
   /// buffer --- The buffer to write the decrypted message too.  
   /// local --- Indicates whether this Client is the initiator of the communication for ratchet steps.  
-  pub fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, local: bool,) -> Result<&'a mut [u8], (Message, Error,)> {
-    use std::{mem, hint,};
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn open_pending<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, local: bool, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    //Only a single open can be pending.
+    if self.pending.is_some() { return Err((message, Error::Pending,)) }
+    //Messages with critical extensions which are not understood are refused.
+    if let Some(kind) = self.registry.unknown_critical(&message.extensions,) {
+      return Err((message, Error::UnknownExtension(kind,),))
+    }
+
+    let header = message.header;
+    let now = self.clock.now();
+
+    //Destroy the keys of steps older than the retention window.
+    if let Some(retention) = self.retention {
+      for (key, count,) in self.open.purge(now.saturating_sub(retention.as_secs(),), now,) {
+        self.observe(Event::KeyEvicted { remote_key: fingerprint(&key,), count, },);
+      }
+    }
+
+    let mut pending = match self.prepare(header, now, local, rand,) {
+      Ok(v) => v,
+      Err(error) => {
+        self.observe(Event::DecryptFailed { remote_key: fingerprint(&header.public_key,), message_index: header.message_index, error, },);
+
+        return Err((message, error,))
+      },
+    };
+
+    match self.open.open_pending(message, buffer, self.padding, now,) {
+      Ok((data, open_data, opening,)) => {
+        pending.open_data = Some(open_data);
+        self.pending = Some(pending);
+        self.opening = opening;
+
+        Ok(data)
+      },
+      //The key of an expired message is destroyed so the open is committed.
+      Err((message, Error::Expired,)) => {
+        self.settle(&header,);
+        self.observe(Event::DecryptFailed { remote_key: fingerprint(&header.public_key,), message_index: header.message_index, error: Error::Expired, },);
+
+        Err((message, Error::Expired,))
+      },
+      //Rollback if there was an error.
+      Err((message, error,)) => {
+        //The keys generated for the message are needed to check for a divergence.
+        let error = if error == Error::Decryption && self.desynced(&message,) { Error::Desync }
+          else { error };
+
+        self.rollback(pending,);
+
+        //Report messages whose key was handed out.
+        let error = if error == Error::NoKey
+          && self.delegated.contains(&(header.public_key, header.message_index,),) { Error::Delegated }
+          else { error };
+        self.observe(Event::DecryptFailed { remote_key: fingerprint(&header.public_key,), message_index: header.message_index, error, },);
+
+        Err((message, error,))
+      },
+    }
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client.
+  /// 
+  /// The message is opened and its key is consumed as if the message was opened; later
+  /// attempts to open the message will return [Error::Delegated].
+  /// A message which fails to open is refused and any ratchet step is rolled back so a
+  /// forged header cannot advance the ratchet.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  /// local --- Indicates whether this Client is the initiator of the communication for ratchet steps.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn delegate<Rand,>(&mut self, message: &Message, local: bool, rand: &mut Rand,) -> Result<DelegatedKey<A, L,>, Error>
+    where Rand: RngCore + CryptoRng, {
+    let header = message.header;
+    let mut buffer = Vec::new();
+    //Authenticate the message before its key is handed out.
+    let opened = self.open_pending(message.clone(), &mut buffer, local, rand,)
+      .map(|_,| (),).map_err(|(_, error,),| error,);
+
+    //The plaintext is not handed out.
+    ClearOnDrop::new(buffer.as_mut_slice(),);
+    opened?;
+
+    let open_data = self.pending.take()
+      .and_then(|mut pending,| pending.open_data.take(),)
+      .ok_or(Error::NoKey,)?;
+
+    self.opening = None;
+    self.settle(&header,);
+    self.delegated.insert((header.public_key, header.message_index,),);
+
+    Ok(DelegatedKey::new(header, open_data, self.padding,))
+  }
+  /// Extracts the key of a message from its header without authenticating the message.
+  /// 
+  /// See [LocalClient::delegate_header] for the rules on ratchet steps.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message to delegate.  
+  pub fn delegate_header(&mut self, header: &Header,) -> Result<DelegatedKey<A, L,>, Error> {
+    //The keys generated by a pending open are removed if it is aborted.
+    if self.pending.is_some() { return Err(Error::Pending) }
+
+    //Generate the keys of the current step up to the message.
+    if self.open.current_public_key.as_ref() == header.public_key.as_ref()
+      && self.open.sent_count <= header.message_index {
+      let generated = self.open.sent_count..header.message_index + 1;
+
+      for index in generated.clone() {
+        self.open.current_keys.insert(index, OpenData::new(&mut self.open.ratchet,),);
+      }
+      self.open.sent_count = generated.end;
+
+      if generated.start < header.message_index {
+        self.observe(Event::KeysSkipped { remote_key: fingerprint(&header.public_key,), start: generated.start, end: header.message_index, },);
+      }
+    }
+
+    let open_data = match self.open.take_key(header,) {
+      Some(open_data) => open_data,
+      None if self.delegated.contains(&(header.public_key, header.message_index,),) => return Err(Error::Delegated),
+      None => return Err(Error::NoKey),
+    };
+
+    self.settle(header,);
+    self.delegated.insert((header.public_key, header.message_index,),);
+
+    Ok(DelegatedKey::new(*header, open_data, self.padding,))
+  }
+  /// Takes any ratchet step and generates any keys needed to open a message.
+  /// 
+  /// The returned state can rollback the changes.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message to open.  
+  /// now --- The current time in seconds since the UNIX epoch.  
+  /// local --- Indicates whether this Client is the initiator of the communication for ratchet steps.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  fn prepare<Rand,>(&mut self, header: Header, now: u64, local: bool, rand: &mut Rand,) -> Result<Pending<D, S, A, R, L,>, Error>
+    where Rand: RngCore + CryptoRng, {
+    use std::mem;
 
     //Remember the ratchet state.
-    let mut ratchet = self.open.ratchet.clone();
-    let mut sent_count = self.open.sent_count;
-    let sent_count = ClearOnDrop::new(&mut sent_count,);
+    let mut pending = Pending {
+      header,
+      open_data: None,
+      ratchet: self.open.ratchet.clone(),
+      sent_count: self.open.sent_count,
+      generated: 0..0,
+      step: None,
+    };
     //Check if the message is part of the current ratchet step.
-    let current_step = self.open.current_public_key.as_ref() == message.header.public_key.as_ref();
+    let current_step = self.open.current_public_key.as_ref() == header.public_key.as_ref();
     //Check if the message is part of a new ratchet step.
     let new_step = !current_step
       //Check if it is part of a previous step.
-      && self.open.previous_keys.keys().all(|key,| key.as_ref() != message.header.public_key.as_ref(),);
-    //Ensure that a result is created.
-    let res;
+      && self.open.previous_keys.keys().all(|key,| key.as_ref() != header.public_key.as_ref(),);
 
     //If the message is part of the next step advance the step.
     if new_step {
       //Update the sent count.
-      self.open.sent_count = 0;
+      let sent_count = mem::replace(&mut self.open.sent_count, 0,);
 
       //Update the current public key.
       let current_public_key = self.open.current_public_key.clone();
-      self.open.current_public_key.copy_from_slice(&message.header.public_key,);
-
-      //Update the private key.
-      let private_key = self.private_key.clone();
-      self.private_key.copy_from_slice(
-        ClearOnDrop::new(
-          &mut StaticSecret::new(&mut rand::thread_rng(),).to_bytes(),
-        ).as_ref(),
-      );
+      self.open.current_public_key.copy_from_slice(&header.public_key,);
 
       //Generate any skipped keys.
-      if *sent_count <= message.header.previous_step {
-        //Generate the skipped keys.
-        for index in *sent_count..message.header.previous_step {
-          self.open.current_keys.insert(index, OpenData::new(&mut self.open.ratchet,),);
-        }
+      let skipped = sent_count..u32::max(sent_count, header.previous_step,);
+      for index in skipped.clone() {
+        self.open.current_keys.insert(index, OpenData::new(&mut self.open.ratchet,),);
       }
 
       //Move the current keys into the previous keys.
+      self.open.retired.insert(current_public_key.clone(), now,);
       self.open.previous_keys.insert(
         current_public_key.clone(),
         //Update the current keys.
         mem::replace(
           &mut self.open.current_keys,
-          HashMap::with_capacity(message.header.message_index as usize + 1,),
+          HashMap::with_capacity(header.message_index as usize + 1,),
         ),
       );
 
-      //Update the lock state.
-      let mut lock_client = mem::replace(&mut self.lock, LockClient::default(),);
-      self.lock.next_header.public_key.copy_from_slice(&self.private_key,);
-
-      //Reseed the chains.
-      let mut step_seed = Ratchet::from(unsafe {
-        //These calls are safe because we can only initialise these fields with these
-        //types or deserialisation.
-        let private = mem::transmute::<_, &StaticSecret>(&self.private_key,);
-        let public = mem::transmute::<_, &PublicKey>(&self.open.current_public_key,);
-
-        private.diffie_hellman(public,).as_bytes().clone()
-      }.as_mut(),);
-      if local {
-        self.lock.ratchet.reseed(&mut step_seed,);
-        self.open.ratchet.reseed(&mut step_seed,);
-      } else {
-        self.open.ratchet.reseed(&mut step_seed,);
-        self.lock.ratchet.reseed(&mut step_seed,);
-      }
+      let private_key = self.private_key.clone();
+      let root = self.root.clone();
+      let chain_fingerprint = self.open.chain_fingerprint;
+      let exporter = self.exporter.clone();
 
-      let previous_step = message.header.previous_step;
+      //Reseed the opening chain from the new public key of the remote Client.
+      let mut open_seed = self.next_chain();
+      self.reseed_open(&mut open_seed, local,);
+      //Reseed the locking chain from a new private key.
+      let lock = self.step_lock(local, rand,);
 
-      res = self.open(message, buffer, local,);
+      if skipped.start < skipped.end {
+        self.observe(Event::KeysSkipped { remote_key: fingerprint(&current_public_key,), start: skipped.start, end: skipped.end, },);
+      }
+      self.observe(Event::StepAdvanced {
+        remote_key: fingerprint(&header.public_key,),
+        local_key: fingerprint(&self.lock.next_header.public_key,),
+        previous_step: header.previous_step,
+      },);
 
-      //Rollback if there was an error.
-      if res.is_err() {
-        //Rollback the lock state.
-        self.lock = mem::replace(&mut lock_client, LockClient::default(),);
-
-        //Rollback the current keys.
-        match self.open.previous_keys.remove(&current_public_key,) {
-          Some(current_keys) => self.open.current_keys = current_keys,
-          //This should always be safe because we are rolling back a previous change.
-          None => unsafe { hint::unreachable_unchecked() },
-        }
+      pending.step = Some(Step { lock, private_key, root, current_public_key, chain_fingerprint, exporter, skipped, },);
+    }
 
-        //Delete generated keys.
-        if *sent_count <= previous_step {
-          for index in *sent_count..previous_step {
-            self.open.current_keys.remove(&index,);
-          }
-        }
+    //If the message is part of the current step make sure we have generated the key for it.
+    if (current_step || new_step) && self.open.sent_count <= header.message_index {
+      //Update the sent count.
+      pending.generated = self.open.sent_count..header.message_index + 1;
+      self.open.sent_count = header.message_index + 1;
 
-        //Rollback the private key.
-        self.private_key = private_key;
-        //Rollback the current public key.
-        self.open.current_public_key = current_public_key;
+      //Generate the skipped keys.
+      for index in pending.generated.clone() {
+        self.open.current_keys.insert(index, OpenData::new(&mut self.open.ratchet,),);
       }
-    //The message is part of an existing ratchet step.
-    } else {
-      //If the message is part of the current step make sure we have generated the key for it.
-      if current_step {
-        //Update the sent count.
-        self.open.sent_count = message.header.message_index + 1;
-
-        //Generate any skipped keys.
-        if *sent_count <= message.header.message_index {
-          //Generate the skipped keys.
-          for index in *sent_count..self.open.sent_count {
-            self.open.current_keys.insert(index, OpenData::new(&mut self.open.ratchet,),);
-          }
-        }
+
+      if pending.generated.start < header.message_index {
+        self.observe(Event::KeysSkipped { remote_key: fingerprint(&header.public_key,), start: pending.generated.start, end: header.message_index, },);
       }
+    }
 
-      res = self.open.open(message, buffer,);
+    Ok(pending)
+  }
+  /// Indicates whether a message which failed to authenticate was locked with a chain
+  /// which has diverged from the chain of this Client.
+  /// 
+  /// The chain fingerprint of a message is not trusted until the message authenticates.
+  /// A message of the current step whose fingerprint differs from the expected fingerprint
+  /// has only diverged if it still fails to authenticate once the expected fingerprint is
+  /// restored; otherwise its fingerprint was altered in transit.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message which failed to authenticate.  
+  fn desynced(&self, message: &Message,) -> bool {
+    //Only the fingerprint of the current step is known.
+    if self.open.current_public_key.as_ref() != message.header.public_key.as_ref() { return false }
 
-      //Rollback if there was an error.
-      if res.is_err() {
-        //Delete the generated keys.
-        for index in *sent_count..self.open.sent_count {
-          self.open.current_keys.remove(&index,);
-        }
+    let expected = match (message.chain_fingerprint(), self.open.chain_fingerprint,) {
+      (Some(fingerprint), Some(expected),)
+        if constant_time::verify_slices_are_equal(&fingerprint, &expected,).is_err() => expected,
+      _ => return false,
+    };
+    let mut message = message.clone();
+    message.extensions.set(&ChainFingerprint(expected,),);
+
+    !self.open.authenticates(&message, self.padding,)
+  }
+  /// Completes a pending open consuming the key of the opened message.
+  #[inline]
+  pub fn commit(&mut self,) {
+    if let Some(pending) = self.pending.take() { self.settle(&pending.header,) }
+  }
+  /// Records that the key of a message was consumed and any ratchet step taken to open it
+  /// was committed.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message whose key was consumed.  
+  fn settle(&mut self, header: &Header,) {
+    //The remote Client took the partner role so this Client can no longer be demoted.
+    self.standby = None;
+    self.transcript.received.push(header,);
+    self.forget_delegated();
+  }
+  /// Forgets the delegated messages of retired steps.
+  /// 
+  /// Only the delegated messages of the current step are reported with
+  /// [Error::Delegated] so that the record does not grow with every step.
+  fn forget_delegated(&mut self,) {
+    let current = &self.open.current_public_key;
+
+    self.delegated.retain(|(key, _,),| current.as_ref() == key.as_ref(),);
+  }
+  /// Reports an event to the observer.
+  /// 
+  /// # Params
+  /// 
+  /// event --- The event which occurred.  
+  fn observe(&self, event: Event,) {
+    #[cfg(feature = "tracing",)]
+    event.trace();
+
+    if let Some(observer) = &self.observer { observer.observe(&event,) }
+  }
+  /// Cancels a pending open restoring the key of the opened message.
+  #[inline]
+  pub fn abort(&mut self,) {
+    if let Some(pending) = self.pending.take() {
+      self.opening = None;
+      self.rollback(pending,)
+    }
+  }
+  /// Reverts the changes made while opening a message.
+  /// 
+  /// # Params
+  /// 
+  /// pending --- The state replaced opening the message.  
+  fn rollback(&mut self, pending: Pending<D, S, A, R, L,>,) {
+    use std::hint;
+
+    let Pending { header, open_data, ratchet, sent_count, generated, step, } = pending;
+
+    self.observe(Event::RolledBack { remote_key: fingerprint(&header.public_key,), message_index: header.message_index, step: step.is_some(), },);
+
+    //Restore the key of the message.
+    if let Some(open_data) = open_data { self.open.restore_key(&header, open_data,); }
+
+    //Delete the generated keys.
+    for index in generated { self.open.current_keys.remove(&index,); }
+
+    //Rollback the ratchet step.
+    if let Some(Step { lock, private_key, root, current_public_key, chain_fingerprint, exporter, skipped, }) = step {
+      //Rollback the current keys.
+      self.open.retired.remove(&current_public_key,);
+      match self.open.previous_keys.remove(&current_public_key,) {
+        Some(current_keys) => self.open.current_keys = current_keys,
+        //This should always be safe because we are rolling back a previous change.
+        None => unsafe { hint::unreachable_unchecked() },
       }
+
+      //Delete the skipped keys.
+      for index in skipped { self.open.current_keys.remove(&index,); }
+
+      //Rollback the lock state.
+      self.lock = lock;
+      //Rollback the private key.
+      self.private_key = private_key;
+      //Rollback the root key.
+      self.root = root;
+      //Rollback the current public key.
+      self.open.current_public_key = current_public_key;
+      //Rollback the chain fingerprint.
+      self.open.chain_fingerprint = chain_fingerprint;
+      //Rollback the exporter secret.
+      self.exporter = exporter;
+    }
+
+    //Rollback the sent count.
+    self.open.sent_count = sent_count;
+    //Rollback the ratchet.
+    self.open.ratchet = ratchet;
+  }
+  /// Encrypts the passed message.
+  /// 
+  /// The buffer will be cleared if the message is encrypted successfully.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to encrypt.  
+  /// extensions --- The extensions to send with the message.  
+  /// rand --- The source of randomness used to generate franking keys.  
+  pub fn lock<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    let expires = self.expiry.map(|expiry,| self.clock.now().saturating_add(expiry.as_secs(),),);
+    let message = match &mut self.pending {
+      //A pending ratchet step may be rolled back so messages are locked with the state
+      //from before the step and the step continues from the last of them.
+      Some(Pending { step: Some(step), .. }) => {
+        let message = step.lock.lock_franked(message, self.padding, expires, extensions, self.franking, rand,)?;
+
+        self.lock.next_header.previous_step = step.lock.next_header.message_index;
+        message
+      },
+      _ => self.lock.lock_franked(message, self.padding, expires, extensions, self.franking, rand,)?,
+    };
+
+    self.transcript.sent.push(&message.header,);
+    Ok(message)
+  }
+}
+
+impl<D, S, A, R, L,> InnerClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// The identity public key of the remote Client.
+  #[inline]
+  pub fn remote_identity(&self,) -> PublicKey { self.remote_identity.into() }
+  /// The padding policy applied to messages.
+  #[inline]
+  pub fn padding(&self,) -> Padding { self.padding }
+  /// Sets the padding policy applied to messages.
+  #[inline]
+  pub fn set_padding(&mut self, padding: Padding,) { self.padding = padding }
+  /// Indicates whether locked messages are franked.
+  #[inline]
+  pub fn franking(&self,) -> bool { self.franking }
+  /// Sets whether locked messages are franked.
+  #[inline]
+  pub fn set_franking(&mut self, franking: bool,) { self.franking = franking }
+  /// The franking opening of the last message opened.
+  #[inline]
+  pub fn opening(&self,) -> Option<&Opening> { self.opening.as_ref() }
+  /// The time after which locked messages expire.
+  #[inline]
+  pub fn expiry(&self,) -> Option<Duration> { self.expiry }
+  /// Sets the time after which locked messages expire.
+  #[inline]
+  pub fn set_expiry(&mut self, expiry: Option<Duration>,) { self.expiry = expiry }
+  /// The time keys of previous steps are retained for.
+  #[inline]
+  pub fn retention(&self,) -> Option<Duration> { self.retention }
+  /// Sets the time keys of previous steps are retained for.
+  #[inline]
+  pub fn set_retention(&mut self, retention: Option<Duration>,) { self.retention = retention }
+  /// Sets the source of the current time.
+  #[inline]
+  pub fn set_clock<C,>(&mut self, clock: C,)
+    where C: 'static + Clock + Send + Sync, { self.clock = Arc::new(clock,) }
+  /// Sets the observer of ratchet events.
+  #[inline]
+  pub fn set_observer<O,>(&mut self, observer: O,)
+    where O: 'static + Observer + Send + Sync, { self.observer = Some(Box::new(observer,)) }
+  /// The extension types understood by this Client.
+  #[inline]
+  pub fn registry(&self,) -> &Registry { &self.registry }
+  /// The extension types understood by this Client.
+  #[inline]
+  pub fn registry_mut(&mut self,) -> &mut Registry { &mut self.registry }
+  /// The transcript of the messages sent and received.
+  #[inline]
+  pub fn checkpoint(&self,) -> Checkpoint { self.transcript }
+  /// A snapshot of the state of this Client.
+  /// 
+  /// # Params
+  /// 
+  /// local --- Indicates whether this Client is the initiator of the communication.  
+  #[inline]
+  pub fn info(&self, local: bool,) -> SessionInfo { SessionInfo::new(self, local,) }
+  /// The [SafetyNumber] of the identity keys used to establish this Client.
+  #[inline]
+  pub fn safety_number(&self,) -> SafetyNumber {
+    SafetyNumber::new(&self.local_identity.into(), &self.remote_identity.into(),)
+  }
+  /// Derives application keying material from the session.
+  /// 
+  /// The output is independent of the message keys and differs for every `label` and
+  /// `context`.
+  /// The epoch of the exporter secret is returned with the output; both Clients derive the
+  /// same output only while they report the same epoch.
+  /// The epoch advances when the initiating Client takes a ratchet step and again when the
+  /// remote Client opens the first message of that step.
+  /// 
+  /// # Params
+  /// 
+  /// label --- Identifies the purpose of the keying material.  
+  /// context --- Application data bound to the keying material.  
+  /// len --- The number of bytes to derive.  
+  pub fn export_keying_material(&self, label: &[u8], context: &[u8], len: usize,) -> Result<([u8; 8], Vec<u8>,), Error> {
+    use ring::{digest, hmac, hkdf,};
+
+    if len > EXPORT_MAX_LENGTH { return Err(Error::ExportLength) }
+
+    let mut info = Vec::with_capacity(EXPORT_INFO.len() + label.len() + context.len() + 16,);
+    info.extend_from_slice(EXPORT_INFO,);
+    for data in &[label, context,] {
+      info.extend_from_slice(&(data.len() as u64).to_le_bytes(),);
+      info.extend_from_slice(data,);
     }
 
-    if res.is_err() {
-      //Rollback the sent count.
-      self.open.sent_count = *sent_count;
-      //Rollback the ratchet.
-      self.open.ratchet = mem::replace(&mut ratchet, Ratchet::default(),);
+    let key = hmac::SigningKey::new(&digest::SHA256, &self.exporter,);
+    let mut output = vec![0; len];
+    hkdf::expand(&key, &info, &mut output,);
+
+    let mut epoch = [0; 8];
+    epoch.copy_from_slice(&hmac::sign(&key, EPOCH_INFO,).as_ref()[..8],);
+
+    Ok((epoch, output,))
+  }
+}
+
+impl<D, S, A, R, L,> Default for InnerClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  #[inline]
+  fn default() -> Self {
+    Self {
+      lock: LockClient::default(),
+      open: OpenClient::default(),
+      private_key: ClearOnDrop::new(GenericArray::default(),),
+      root: ClearOnDrop::new(GenericArray::default(),),
+      local_identity: [0; 32],
+      remote_identity: [0; 32],
+      exporter: ClearOnDrop::new(GenericArray::default(),),
+      padding: Padding::None,
+      franking: false,
+      opening: None,
+      expiry: None,
+      retention: None,
+      clock: Arc::new(SystemClock,),
+      observer: None,
+      registry: Registry::new(),
+      transcript: Checkpoint::default(),
+      delegated: HashSet::new(),
+      pending: None,
+      standby: None,
     }
+  }
+}
 
-    res
+/// Defines functionality of a Double-Ratchet `Client`.
+pub trait Client: Sized {
+  /// Receives a message from the connected `Client`.
+  /// 
+  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  #[inline]
+  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
+    self.open_with_rng(message, buffer, &mut rand::thread_rng(),)
+  }
+  /// Receives a message from the connected `Client` using the passed source of randomness.
+  /// 
+  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  fn open_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    let data = self.open_pending_with_rng(message, buffer, rand,)?;
+
+    self.commit(); Ok(data)
+  }
+  /// Receives a message from the connected `Client` without consuming its key.
+  /// 
+  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// Until the open is passed to `commit` or `abort` no other message can be opened;
+  /// `abort` restores the `Client` as if the message was never received so it can be
+  /// opened again once the application has finished processing it.
+  /// Messages can still be locked while the open is pending; they are locked with the
+  /// ratchet state from before the open so they are kept whether it is committed or
+  /// aborted.
+  /// A `Client` serialised while an open is pending is still pending once deserialised.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  #[inline]
+  fn open_pending<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
+    self.open_pending_with_rng(message, buffer, &mut rand::thread_rng(),)
   }
+  /// Receives a message from the connected `Client` without consuming its key using the
+  /// passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng;
+  /// Completes a pending open; the key of the opened message is deleted.
+  /// 
+  /// Does nothing if there is no pending open.
+  fn commit(&mut self,);
+  /// Cancels a pending open; the `Client` is restored to its state before the message
+  /// was opened.
+  /// 
+  /// Does nothing if there is no pending open.
+  fn abort(&mut self,);
   /// Encrypts the passed message.
   /// 
   /// The buffer will be cleared if the message is encrypted successfully.
@@ -302,44 +1447,97 @@ impl<D, S, A, R, L,> InnerClient<D, S, A, R, L,>
   /// # Params
   /// 
   /// message --- The Message to encrypt.  
-  pub fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
-    self.lock.lock(message,)
+  #[inline]
+  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
+    self.lock_with_extensions(message, Extensions::new(),)
   }
-}
-
-impl<D, S, A, R, L,> Default for InnerClient<D, S, A, R, L,>
-  where S: ArrayLength<u8>,
-    A: Algorithm,
-    L: ArrayLength<u8>, {
+  /// Encrypts the passed message sending the extensions with it.
+  /// 
+  /// The buffer will be cleared if the message is encrypted successfully.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to encrypt.  
+  /// extensions --- The extensions to authenticate and send with the message.  
   #[inline]
-  fn default() -> Self {
-    Self {
-      lock: LockClient::default(),
-      open: OpenClient::default(),
-      private_key: ClearOnDrop::new(GenericArray::default(),),
-    }
+  fn lock_with_extensions(&mut self, message: &mut [u8], extensions: Extensions,) -> Result<Message, Error> {
+    self.lock_with_extensions_and_rng(message, extensions, &mut rand::thread_rng(),)
   }
-}
+  /// Encrypts the passed message sending the extensions with it using the passed source
+  /// of randomness.
+  /// 
+  /// The buffer will be cleared if the message is encrypted successfully.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to encrypt.  
+  /// extensions --- The extensions to authenticate and send with the message.  
+  /// rand --- The source of randomness used to generate franking keys.  
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng;
+  /// Serialises and encrypts the passed value.
+  /// 
+  /// The serialised plaintext is cleared once it is encrypted.
+  /// 
+  /// # Params
+  /// 
+  /// value --- The value to encrypt.  
+  fn lock_value<T,>(&mut self, value: &T,) -> Result<Message, Error>
+    where T: ?Sized + Serialize, {
+    let mut buffer = Vec::new();
+    let res = serde_cbor::ser::to_writer_packed(&mut buffer, value,)
+      .map_err(|_,| Error::Serialise,)
+      .and_then(|_,| self.lock(&mut buffer,),);
 
-/// Defines functionality of a Double-Ratchet `Client`.
-pub trait Client: Sized {
-  /// Receives a message from the connected `Client`.
+    //Clear the plaintext.
+    ClearOnDrop::new(buffer.as_mut_slice(),);
+
+    res
+  }
+  /// Receives a value from the connected `Client`.
   /// 
-  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// If the message could not be opened it is returned with the error.
+  /// If the message was opened but its data could not be deserialised the open is aborted
+  /// and [Error::Deserialise] is returned without the message; the `Client` is left as if
+  /// the message was never received.
   /// 
   /// # Params
   /// 
   /// message --- The Message to decrypt.  
-  /// buffer --- The buffer to write the decrypted message too.  
-  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)>;
-  /// Encrypts the passed message.
+  #[inline]
+  fn open_value<T,>(&mut self, message: Message,) -> Result<T, (Option<Message>, Error,)>
+    where T: DeserializeOwned, {
+    self.open_value_with_rng(message, &mut rand::thread_rng(),)
+  }
+  /// Receives a value from the connected `Client` using the passed source of randomness.
   /// 
-  /// The buffer will be cleared if the message is encrypted successfully.
+  /// If the message could not be opened it is returned with the error.
+  /// If the message was opened but its data could not be deserialised the open is aborted
+  /// and [Error::Deserialise] is returned without the message; the `Client` is left as if
+  /// the message was never received.
   /// 
   /// # Params
   /// 
-  /// message --- The Message to encrypt.  
-  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error>;
+  /// message --- The Message to decrypt.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  fn open_value_with_rng<T, Rand,>(&mut self, message: Message, rand: &mut Rand,) -> Result<T, (Option<Message>, Error,)>
+    where T: DeserializeOwned,
+      Rand: RngCore + CryptoRng, {
+    let mut buffer = Vec::new();
+    let res = match self.open_pending_with_rng(message, &mut buffer, rand,) {
+      //The key of the message is only consumed if its data is deserialised.
+      Ok(data) => match serde_cbor::from_slice(data,) {
+        Ok(value) => { self.commit(); Ok(value) },
+        Err(_) => { self.abort(); Err((None, Error::Deserialise,)) },
+      },
+      Err((message, error,)) => Err((Some(message), error,)),
+    };
+
+    //Clear the plaintext.
+    ClearOnDrop::new(buffer.as_mut_slice(),);
+
+    res
+  }
   /// Returns a [Framed] around this `Client`.
   /// 
   /// # Params
@@ -352,24 +1550,36 @@ pub trait Client: Sized {
 impl<'t, T,> Client for &'t mut T
   where T: Client {
   #[inline]
-  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
-    T::open(self, message, buffer,)
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    T::open_pending_with_rng(self, message, buffer, rand,)
   }
   #[inline]
-  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
-    T::lock(self, message,)
+  fn commit(&mut self,) { T::commit(self,) }
+  #[inline]
+  fn abort(&mut self,) { T::abort(self,) }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    T::lock_with_extensions_and_rng(self, message, extensions, rand,)
   }
 }
 
 impl<T,> Client for Box<T>
   where T: Client {
   #[inline]
-  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
-    T::open(self, message, buffer,)
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    T::open_pending_with_rng(self, message, buffer, rand,)
   }
   #[inline]
-  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
-    T::lock(self, message,)
+  fn commit(&mut self,) { T::commit(self,) }
+  #[inline]
+  fn abort(&mut self,) { T::abort(self,) }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    T::lock_with_extensions_and_rng(self, message, extensions, rand,)
   }
 }
 
@@ -379,12 +1589,18 @@ impl<D, S, A, R, L,> Client for (bool, Box<InnerClient<D, S, A, R, L,>>,)
     L: ArrayLength<u8>,
     Ratchet<D, S, R,>: RngCore + CryptoRng, {
   #[inline]
-  fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
-    self.1.open(message, buffer, self.0,)
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    self.1.open_pending(message, buffer, self.0, rand,)
   }
   #[inline]
-  fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
-    self.1.lock(message,)
+  fn commit(&mut self,) { self.1.commit() }
+  #[inline]
+  fn abort(&mut self,) { self.1.abort() }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    self.1.lock(message, extensions, rand,)
   }
 }
 
@@ -405,11 +1621,45 @@ pub enum Error {
   /// once it is possible for this error to be returned instead of `NoKey` if the client
   /// no longer remembers the public key of the message's header.
   Decryption,
+  /// A value could not be serialised.
+  Serialise,
+  /// The plaintext of a message could not be deserialised as a value; the open was
+  /// aborted so the key of the message was kept.
+  Deserialise,
+  /// A previously opened message has not been committed or aborted.
+  Pending,
+  /// The message was locked by a Client whose ratchet state has diverged from this
+  /// Client; the session must be reset using [crate::reset].
+  /// 
+  /// Only messages which fail to authenticate are reported as diverged so a message
+  /// whose chain fingerprint was altered in transit is reported as `Decryption`.
+  Desync,
+  /// Too much keying material was requested.
+  ExportLength,
+  /// The padding of an opened message was invalid.
+  Padding,
+  /// The message expired before it was opened; its key has been destroyed.
+  Expired,
+  /// The message carried a critical extension of the contained type which is not registered.
+  UnknownExtension(u16,),
+  /// The franking commitment of an opened message did not match its data.
+  Franking,
+  /// The key of the message was handed out with `delegate`.
+  /// 
+  /// Only reported for messages of the current ratchet step; a delegated message of a
+  /// retired step is reported as `NoKey`.
+  Delegated,
+  /// A [Session](crate::session::Session) was established with its own identity key.
+  SameIdentity,
+  /// A thread panicked while using a [SharedClient](crate::shared::SharedClient) so its
+  /// state may be inconsistent; the session must be reset using [crate::reset].
+  Poisoned,
 }
 
 #[cfg(test,)]
 mod tests {
   use super::*;
+  use crate::transcript;
   use sha1::Sha1;
 
   #[test]
@@ -451,6 +1701,40 @@ mod tests {
     lock.open(message, &mut Vec::new(),).expect_err("Opened a message twice");
   }
   #[test]
+  fn test_client_step_public_key() {
+    use rand::{SeedableRng, rngs::StdRng,};
+
+    let mut rand = StdRng::seed_from_u64(1,);
+    let open_sec = StaticSecret::from([1; 32],);
+    let lock_sec = StaticSecret::from([2; 32],);
+    let mut open = LocalClient::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U10,>::connect_with_rng(&(&lock_sec).into(), &open_sec, &mut rand,);
+    let mut lock = RemoteClient::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U10,>::accept(&(&open_sec).into(), &lock_sec,);
+    //The private key the Client will step to.
+    let next_sec = StaticSecret::new(&mut rand.clone(),);
+    //The remote Client stepped to a new public key when it was established.
+    let message = lock.lock(&mut [1; 10],).expect("Error locking message");
+    assert_ne!(message.header.public_key, *PublicKey::from(&lock_sec,).as_bytes(), "Remote Client did not step",);
+    let mut buffer = Vec::new();
+
+    open.open_with_rng(message, &mut buffer, &mut rand,).expect("Error opening message from a new step");
+    assert_eq!(buffer, [1; 10], "Message corrupted",);
+    //The Client must advertise the public key of its new private key and never the private key itself.
+    assert_eq!(open.0.lock.next_header.public_key, *PublicKey::from(&next_sec,).as_bytes(), "Bad public key after a step",);
+    assert_ne!(open.0.lock.next_header.public_key, next_sec.to_bytes(), "Private key sent in the header",);
+  }
+  #[test]
+  fn test_client_identity_not_kept() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let serialised = serde_cbor::to_vec(&local,).expect("Error serialising Client");
+
+    //The first step is taken against a key drawn from the seed rather than the identity key.
+    assert_ne!(&local.0.lock.next_header.public_key, PublicKey::from(&local_sec,).as_bytes(), "Identity key advertised",);
+    assert_ne!(local.0.private_key.as_slice(), &local_sec.to_bytes()[..], "Identity key kept",);
+    assert!(!serialised.windows(32,).any(|window,| window == &local_sec.to_bytes()[..],), "Identity key serialised",);
+  }
+  #[test]
   fn test_client_recovery() {
     let open_sec = StaticSecret::from([1; 32],);
     let lock_sec = StaticSecret::from([2; 32],);
@@ -468,7 +1752,7 @@ mod tests {
     
     //Test corrupted message.
     buffer.clear();
-    lock.open(Message { data: Box::new([1; 100]), ..message }, &mut buffer,)
+    lock.open(Message { data: Box::new([1; 100]), ..message.clone() }, &mut buffer,)
       .expect_err("Opened corrupted message");
 
     //Test corrupted recovery.
@@ -476,4 +1760,756 @@ mod tests {
     open.open(message, &mut buffer,).expect("Error opening message");
     assert_eq!(buffer, msg.as_ref(), "Received message corrupted",);
   }
+  #[test]
+  fn test_client_psk() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let connect = |psk: Option<&[u8]>,| LocalClient::<Sha1, consts::U64,>::connect_with_psk(&(&remote_sec).into(), &local_sec, psk,);
+    let accept = |psk: Option<&[u8]>,| RemoteClient::<Sha1, consts::U64,>::accept_with_psk(&(&local_sec).into(), &remote_sec, psk,);
+    let msg = [1; 10];
+
+    //Test matching keys.
+    let mut local = connect(Some(b"psk"),);
+    let mut remote = accept(Some(b"psk"),);
+    let message = local.lock(&mut msg.clone(),).expect("Error locking message");
+    let mut buffer = Vec::new();
+    remote.open(message, &mut buffer,).expect("Error opening message");
+    assert_eq!(buffer, msg.as_ref(), "Message corrupted",);
+
+    //Test mismatched keys.
+    for (local_psk, remote_psk,) in vec![
+      (Some(b"psk".as_ref()), Some(b"other".as_ref()),),
+      (Some(b"psk".as_ref()), None,),
+      (None, Some(b"psk".as_ref()),),
+    ] {
+      let message = connect(local_psk,).lock(&mut msg.clone(),).expect("Error locking message");
+      let mut remote = accept(remote_psk,);
+
+      match remote.open(message.clone(), &mut Vec::new(),) {
+        Err((_, Error::Desync,)) => {},
+        _ => panic!("Opened a message with a mismatched psk"),
+      }
+      //The failed message must leave the Client usable.
+      match remote.open(message, &mut Vec::new(),) {
+        Err((_, Error::Desync,)) => {},
+        _ => panic!("Failed open changed the Client"),
+      }
+    }
+  }
+  #[test]
+  fn test_client_export() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let exported = local.export_keying_material(b"call", b"1", 48,).expect("Error exporting");
+
+    assert_eq!(exported.1.len(), 48, "Bad exported length",);
+    assert_eq!(exported, remote.export_keying_material(b"call", b"1", 48,).expect("Error exporting"),
+      "Clients exported different material",
+    );
+    assert_ne!(exported, local.export_keying_material(b"file", b"1", 48,).expect("Error exporting"),
+      "Labels not separated",
+    );
+    assert_ne!(exported, local.export_keying_material(b"call", b"2", 48,).expect("Error exporting"),
+      "Contexts not separated",
+    );
+    assert_eq!(local.export_keying_material(b"call", b"1", 255 * 32 + 1,), Err(Error::ExportLength),
+      "Exported too much material",
+    );
+
+    //The local Client steps opening the reply.
+    let message = remote.lock(&mut [1; 10],).expect("Error locking reply");
+    local.open(message, &mut Vec::new(),).expect("Error opening reply");
+    let stepped = local.export_keying_material(b"call", b"1", 48,).expect("Error exporting");
+    let other = remote.export_keying_material(b"call", b"1", 48,).expect("Error exporting");
+    assert_ne!(stepped.0, exported.0, "Epoch did not advance",);
+    assert_ne!(stepped.0, other.0, "Diverged Clients report the same epoch",);
+    assert_ne!(stepped.1, other.1, "Diverged Clients exported the same material",);
+
+    //The remote Client follows the step opening the next message.
+    let message = local.lock(&mut [2; 10],).expect("Error locking message");
+    remote.open(message, &mut Vec::new(),).expect("Error opening message");
+    assert_eq!(stepped, remote.export_keying_material(b"call", b"1", 48,).expect("Error exporting"),
+      "Clients exported different material after a step",
+    );
+    assert_ne!(stepped.1, exported.1, "Exported material did not change after a step",);
+  }
+  #[test]
+  fn test_client_desync() {
+    use crate::message::ExtensionType;
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let other_sec = StaticSecret::from([3; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    //The remote Client was established with a different state to the local Client.
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&other_sec).into(), &remote_sec,);
+    let message = local.lock(&mut [1; 10],).expect("Error locking message");
+
+    assert!(message.chain_fingerprint().is_some(), "Message has no chain fingerprint",);
+    match remote.open(message, &mut Vec::new(),) {
+      Err((_, Error::Desync,)) => {},
+      _ => panic!("Desync not detected"),
+    }
+
+    //The chain fingerprint is authenticated with the message.
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let message = local.lock(&mut [2; 10],).expect("Error locking message");
+    let mut stripped = message.clone();
+    stripped.extensions.remove(ChainFingerprint::KIND,);
+    match remote.open(stripped, &mut Vec::new(),) {
+      Err((_, Error::Decryption,)) => {},
+      _ => panic!("Opened a message with a stripped chain fingerprint"),
+    }
+    remote.open(message, &mut Vec::new(),).expect("Error opening message");
+
+    //An altered chain fingerprint is not mistaken for a desync.
+    let message = local.lock(&mut [3; 10],).expect("Error locking message");
+    let mut altered = message.clone();
+    let mut chain_fingerprint = message.chain_fingerprint().expect("Message has no chain fingerprint");
+    chain_fingerprint[0] ^= 1;
+    altered.extensions.set(&ChainFingerprint(chain_fingerprint,),);
+    match remote.open(altered, &mut Vec::new(),) {
+      Err((_, Error::Decryption,)) => {},
+      _ => panic!("Altered chain fingerprint not refused as a decryption error"),
+    }
+    remote.open(message, &mut Vec::new(),).expect("Error opening message");
+  }
+  #[test]
+  fn test_client_expiry() {
+    use std::sync::{Arc, atomic::{AtomicU64, Ordering,},};
+
+    struct TestClock(Arc<AtomicU64>,);
+
+    impl Clock for TestClock {
+      fn now(&self,) -> u64 { self.0.load(Ordering::SeqCst,) }
+    }
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let time = Arc::new(AtomicU64::new(100,),);
+
+    local.set_clock(TestClock(time.clone(),),);
+    remote.set_clock(TestClock(time.clone(),),);
+    local.set_expiry(Some(Duration::from_secs(60,),),);
+
+    let message = local.lock(&mut [1; 10],).expect("Error locking first message");
+    let other = local.lock(&mut [2; 10],).expect("Error locking second message");
+
+    assert_eq!(message.expires(), Some(160), "Bad message expiry",);
+    remote.open(message, &mut Vec::new(),).expect("Error opening unexpired message");
+
+    time.store(160, Ordering::SeqCst,);
+    match remote.open(other.clone(), &mut Vec::new(),) {
+      Err((_, Error::Expired,)) => {},
+      _ => panic!("Opened an expired message"),
+    }
+    match remote.open(other, &mut Vec::new(),) {
+      Err((_, Error::NoKey,)) => {},
+      _ => panic!("Expired message key was kept"),
+    }
+
+    //Messages locked without an expiry never expire.
+    local.set_expiry(None,);
+    let message = local.lock(&mut [3; 10],).expect("Error locking third message");
+
+    time.store(std::u64::MAX, Ordering::SeqCst,);
+    remote.open(message, &mut Vec::new(),).expect("Error opening message without expiry");
+  }
+  #[test]
+  fn test_client_extensions() {
+    use crate::message::Extension;
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let mut extensions = Extensions::new();
+
+    extensions.insert(Extension { kind: 1, critical: false, value: vec![1, 2,].into(), },);
+
+    //Unknown non-critical extensions are ignored.
+    let message = local.lock_with_extensions(&mut [1; 10], extensions.clone(),)
+      .expect("Error locking message");
+    assert_eq!(message.extensions.get(1,), extensions.get(1,), "Extensions not sent",);
+    remote.open(message, &mut Vec::new(),).expect("Error opening message");
+
+    //Extensions are authenticated.
+    let mut message = local.lock_with_extensions(&mut [2; 10], extensions.clone(),)
+      .expect("Error locking message");
+    message.extensions.insert(Extension { kind: 1, critical: false, value: vec![3,].into(), },);
+    match remote.open(message, &mut Vec::new(),) {
+      Err((_, Error::Decryption,)) => {},
+      _ => panic!("Opened a message with modified extensions"),
+    }
+
+    //Unknown critical extensions are refused.
+    extensions.insert(Extension { kind: 2, critical: true, value: Box::new([],), },);
+    let message = local.lock_with_extensions(&mut [3; 10], extensions,)
+      .expect("Error locking message");
+    match remote.open(message.clone(), &mut Vec::new(),) {
+      Err((_, Error::UnknownExtension(2,),)) => {},
+      _ => panic!("Opened a message with an unknown critical extension"),
+    }
+
+    remote.registry_mut().register_kind(2,);
+    remote.open(message, &mut Vec::new(),).expect("Error opening message with a registered extension");
+  }
+  #[test]
+  fn test_client_transcript() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let messages = (0..3).map(|index,| local.lock(&mut [index; 10],).expect("Error locking message"),)
+      .collect::<Vec<_>>();
+
+    //The transport dropped the last message.
+    for message in messages[..2].iter().rev() {
+      remote.open(message.clone(), &mut Vec::new(),).expect("Error opening message");
+    }
+    assert_eq!(remote.verify_checkpoint(&local.checkpoint(),), Err(transcript::Error::Incomplete(1)),
+      "Dropped message not detected",
+    );
+    assert_eq!(local.verify_checkpoint(&remote.checkpoint(),), Ok(()), "Local transcript rejected",);
+
+    //Aborted opens are not part of the transcript.
+    remote.open_pending(messages[2].clone(), &mut Vec::new(),).expect("Error opening pending message");
+    remote.abort();
+    assert_eq!(remote.checkpoint().received.count(), 2, "Aborted open was recorded",);
+
+    remote.open(messages[2].clone(), &mut Vec::new(),).expect("Error opening message");
+    assert_eq!(remote.verify_checkpoint(&local.checkpoint(),), Ok(()), "Complete transcript rejected",);
+  }
+  #[test]
+  fn test_client_delegate() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let first = local.lock(&mut [1; 10],).expect("Error locking first message");
+    let second = local.lock(&mut [2; 10],).expect("Error locking second message");
+
+    //Hand the key of the second message to another process.
+    //A forged header is refused without advancing the ratchet.
+    let mut forged = second.clone();
+    forged.header.public_key = [9; 32];
+    assert!(remote.delegate(&forged,).is_err(), "Delegated a forged step",);
+    let mut forged = second.clone();
+    forged.header.message_index = 100;
+    assert!(remote.delegate(&forged,).is_err(), "Delegated a forged index",);
+    assert!(!remote.info().pending, "Forged delegation left an open pending",);
+
+    let key = remote.delegate(&second,).expect("Error delegating key");
+    assert_eq!(key.header(), &second.header, "Delegated the wrong key",);
+    let key = serde_cbor::to_vec(&key,).expect("Error serialising DelegatedKey");
+    assert_eq!(remote.delegate(&second,).map(|_,| (),), Err(Error::Delegated), "Delegated a key twice",);
+
+    //The key only opens its own message.
+    let other = serde_cbor::from_slice::<DelegatedKey<Aes256Gcm, consts::U0,>>(&key,)
+      .expect("Error deserialising DelegatedKey");
+    match other.open(first.clone(), &mut Vec::new(),) {
+      Err((_, Error::NoKey,)) => {},
+      _ => panic!("Opened the wrong message with a DelegatedKey"),
+    }
+
+    let key = serde_cbor::from_slice::<DelegatedKey<Aes256Gcm, consts::U0,>>(&key,)
+      .expect("Error deserialising DelegatedKey");
+    let mut buffer = Vec::new();
+    assert_eq!(key.open(second.clone(), &mut buffer,).expect("Error opening delegated message"), &[2; 10],
+      "Delegated message corrupted",
+    );
+
+    //The Client refuses the delegated message but opens the others.
+    match remote.open(second, &mut Vec::new(),) {
+      Err((_, Error::Delegated,)) => {},
+      _ => panic!("Opened a delegated message"),
+    }
+    remote.open(first, &mut Vec::new(),).expect("Error opening first message");
+    assert_eq!(remote.checkpoint().received.count(), 2, "Delegated message not recorded",);
+
+    //Delegated messages are forgotten once their step is retired.
+    let reply = remote.lock(&mut [3; 10],).expect("Error locking reply");
+    local.open(reply, &mut Vec::new(),).expect("Error opening reply");
+    let third = local.lock(&mut [4; 10],).expect("Error locking third message");
+    assert_eq!(remote.info().delegated, 1, "Delegated message forgotten",);
+    remote.open(third, &mut Vec::new(),).expect("Error opening third message");
+    assert_eq!(remote.info().delegated, 0, "Delegated message of a retired step kept",);
+  }
+  #[test]
+  fn test_client_delegate_header() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let first = local.lock(&mut [1; 10],).expect("Error locking first message");
+    let second = local.lock(&mut [2; 10],).expect("Error locking second message");
+
+    //The key of a message in the current step is derived from its header alone.
+    let key = remote.delegate_header(&second.header,).expect("Error delegating key");
+    let mut buffer = Vec::new();
+    assert_eq!(key.open(second.clone(), &mut buffer,).expect("Error opening delegated message"), &[2; 10],
+      "Delegated message corrupted",
+    );
+    assert_eq!(remote.delegate_header(&second.header,).map(|_,| (),), Err(Error::Delegated), "Delegated a key twice",);
+    match remote.open(second, &mut Vec::new(),) {
+      Err((_, Error::Delegated,)) => {},
+      _ => panic!("Opened a delegated message"),
+    }
+    //The skipped key was kept.
+    remote.open(first, &mut Vec::new(),).expect("Error opening first message");
+
+    //A header cannot take a ratchet step.
+    let reply = remote.lock(&mut [3; 10],).expect("Error locking reply");
+    let current_public_key = local.0.open.current_public_key.clone();
+    assert_eq!(local.delegate_header(&reply.header,).map(|_,| (),), Err(Error::NoKey), "Delegated a new step",);
+    assert_eq!(local.0.open.current_public_key[..], current_public_key[..], "Header took a ratchet step",);
+    local.open(reply, &mut Vec::new(),).expect("Error opening reply");
+  }
+  #[test]
+  fn test_client_franking() {
+    use crate::{franking::Moderator, message::Franking,};
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let moderator = Moderator::new(&[3; 32],);
+
+    local.set_franking(true,);
+    let message = local.lock(&mut [4; 10],).expect("Error locking franked message");
+    let commitment = message.franking().expect("Message was not franked");
+    //The server stamps the commitment while relaying the message.
+    let stamp = moderator.stamp(&commitment, b"local to remote",);
+
+    //A commitment which does not match the data is rejected.
+    let mut replaced = message.clone();
+    replaced.extensions.set(&Franking([0; 32],),);
+    match remote.open(replaced, &mut Vec::new(),) {
+      Err((_, Error::Decryption,)) => {},
+      _ => panic!("Opened a message with a replaced commitment"),
+    }
+
+    let mut buffer = Vec::new();
+    assert_eq!(remote.open(message, &mut buffer,).expect("Error opening franked message"), &[4; 10],
+      "Franked message corrupted",
+    );
+
+    let report = remote.opening().expect("No franking opening").report(&buffer,);
+    assert_eq!(moderator.verify(&report, b"local to remote", &stamp,), Ok(()), "Report rejected",);
+
+    //Unfranked messages have no opening.
+    local.set_franking(false,);
+    let message = local.lock(&mut [5; 10],).expect("Error locking message");
+    assert_eq!(message.franking(), None, "Unfranked message has a commitment",);
+    remote.open(message, &mut Vec::new(),).expect("Error opening message");
+    assert_eq!(remote.opening(), None, "Unfranked message has an opening",);
+  }
+  #[test]
+  fn test_client_info() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let messages = (0..4).map(|index,| local.lock(&mut [index; 10],).expect("Error locking message"),)
+      .collect::<Vec<_>>();
+
+    //Skip the first two messages.
+    remote.open(messages[2].clone(), &mut Vec::new(),).expect("Error opening message");
+
+    let info = local.info();
+    assert!(info.local, "Bad local role",);
+    assert_eq!(info.message_index, 4, "Bad message index",);
+    assert_eq!(info.sent, 4, "Bad sent count",);
+
+    let info = remote.info();
+    assert!(!info.local, "Bad remote role",);
+    assert_eq!(info.remote_key, local.info().local_key, "Key fingerprints differ",);
+    assert_eq!(info.chain_fingerprint, local.info().chain_fingerprint, "Chain fingerprints differ",);
+    assert_eq!(info.received_index, 3, "Bad received index",);
+    assert_eq!(info.current_keys, 2, "Bad skipped key count",);
+    assert_eq!(info.received, 1, "Bad received count",);
+    assert!(info.memory > 0, "Bad memory estimate",);
+
+    remote.open_pending(messages[0].clone(), &mut Vec::new(),).expect("Error opening pending message");
+    assert!(remote.info().pending, "Pending open not reported",);
+    remote.commit();
+    assert_eq!(remote.info().current_keys, 1, "Used key still reported",);
+  }
+  #[test]
+  fn test_client_observer() {
+    use crate::client::info::fingerprint;
+    use std::sync::{Arc, Mutex,};
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let events = Arc::new(Mutex::new(Vec::new(),),);
+    let messages = (0..3).map(|index,| local.lock(&mut [index; 10],).expect("Error locking message"),)
+      .collect::<Vec<_>>();
+
+    remote.set_observer({
+      let events = events.clone();
+
+      move |event: &Event,| events.lock().expect("Error locking events").push(*event,)
+    },);
+
+    remote.open(messages[2].clone(), &mut Vec::new(),).expect("Error opening message");
+    remote.open_pending(messages[0].clone(), &mut Vec::new(),).expect("Error opening pending message");
+    remote.abort();
+    remote.open(messages[2].clone(), &mut Vec::new(),).expect_err("Opened a message twice");
+
+    let remote_key = fingerprint(&messages[0].header.public_key,);
+    assert_eq!(*events.lock().expect("Error locking events"), vec![
+      Event::KeysSkipped { remote_key, start: 0, end: 2, },
+      Event::RolledBack { remote_key, message_index: 0, step: false, },
+      Event::RolledBack { remote_key, message_index: 2, step: false, },
+      Event::DecryptFailed { remote_key, message_index: 2, error: Error::NoKey, },
+    ], "Bad events",);
+  }
+  #[test]
+  fn test_client_split() {
+    use std::{thread, sync::mpsc,};
+
+    const MESSAGES: u8 = 50;
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let (mut local_lock, mut local_open,) = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,).split();
+    let (mut remote_lock, mut remote_open,) = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,).split();
+    let (to_remote, from_local,) = mpsc::channel();
+    let (to_local, from_remote,) = mpsc::channel();
+    //Send and receive on both ends at once.
+    let threads = vec![
+      thread::spawn(move || for index in 0..MESSAGES {
+        to_remote.send(local_lock.lock(&mut [index; 10],).expect("Error locking local message"),)
+          .expect("Error sending local message");
+      },),
+      thread::spawn(move || for index in 0..MESSAGES {
+        to_local.send(remote_lock.lock(&mut [index; 10],).expect("Error locking remote message"),)
+          .expect("Error sending remote message");
+      },),
+      thread::spawn(move || for (index, message,) in from_local.iter().enumerate() {
+        let mut buffer = Vec::new();
+
+        assert_eq!(remote_open.open(message, &mut buffer,).expect("Error opening local message"), &[index as u8; 10],
+          "Local message corrupted",
+        );
+      },),
+      thread::spawn(move || for (index, message,) in from_remote.iter().enumerate() {
+        let mut buffer = Vec::new();
+
+        assert_eq!(local_open.open(message, &mut buffer,).expect("Error opening remote message"), &[index as u8; 10],
+          "Remote message corrupted",
+        );
+      },),
+    ];
+
+    for thread in threads { thread.join().expect("Error joining thread"); }
+  }
+  #[test]
+  fn test_client_split_steps() {
+    use std::{thread, sync::mpsc,};
+
+    const ROUNDS: u8 = 10;
+    const BURST: u8 = 3;
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let (mut local_lock, mut local_open,) = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,).split();
+    let (mut remote_lock, mut remote_open,) = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,).split();
+    let (to_remote, from_local,) = mpsc::channel();
+    let (to_local, from_remote,) = mpsc::channel();
+    //Each end replies once its open half has received a burst so both ends keep stepping.
+    let (local_turn, local_wait,) = mpsc::channel();
+    let (remote_turn, remote_wait,) = mpsc::channel();
+    let local_sender = thread::spawn(move || {
+      let mut keys = Vec::new();
+
+      for round in 0..ROUNDS {
+        if round > 0 { local_wait.recv().expect("Error waiting for remote burst"); }
+
+        for index in 0..BURST {
+          let message = local_lock.lock(&mut [round * BURST + index; 10],).expect("Error locking local message");
+
+          keys.push(message.header.public_key,);
+          to_remote.send(message,).expect("Error sending local message");
+        }
+      }
+
+      keys
+    },);
+    let remote_sender = thread::spawn(move || {
+      let mut keys = Vec::new();
+
+      for round in 0..ROUNDS {
+        remote_wait.recv().expect("Error waiting for local burst");
+
+        for index in 0..BURST {
+          let message = remote_lock.lock(&mut [round * BURST + index; 10],).expect("Error locking remote message");
+
+          keys.push(message.header.public_key,);
+          to_local.send(message,).expect("Error sending remote message");
+        }
+      }
+
+      keys
+    },);
+    let receivers = vec![
+      thread::spawn(move || for (index, message,) in from_local.iter().enumerate() {
+        let mut buffer = Vec::new();
+
+        assert_eq!(remote_open.open(message, &mut buffer,).expect("Error opening local message"), &[index as u8; 10],
+          "Local message corrupted",
+        );
+        //The sender may have finished.
+        if index as u8 % BURST == BURST - 1 { let _ = remote_turn.send((),); }
+      },),
+      thread::spawn(move || for (index, message,) in from_remote.iter().enumerate() {
+        let mut buffer = Vec::new();
+
+        assert_eq!(local_open.open(message, &mut buffer,).expect("Error opening remote message"), &[index as u8; 10],
+          "Remote message corrupted",
+        );
+        //The sender may have finished.
+        if index as u8 % BURST == BURST - 1 { let _ = local_turn.send((),); }
+      },),
+    ];
+    let local_keys = local_sender.join().expect("Error joining local sender");
+    let remote_keys = remote_sender.join().expect("Error joining remote sender");
+
+    for thread in receivers { thread.join().expect("Error joining receiver"); }
+
+    //Every burst is locked with the step taken opening the previous burst from the peer.
+    for keys in vec![local_keys, remote_keys] {
+      assert_eq!(keys.len(), (ROUNDS * BURST) as usize, "Bad message count",);
+      assert!(keys.chunks(BURST as usize,).all(|burst,| burst.iter().all(|key,| key == &burst[0],),),
+        "Stepped within a burst",
+      );
+      let steps = keys.chunks(BURST as usize,).map(|burst,| burst[0],).collect::<Vec<_>>();
+      assert!(steps.windows(2,).all(|pair,| pair[0] != pair[1],), "Lock half did not take the new step",);
+    }
+  }
+  #[test]
+  fn test_client_padding() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+
+    local.set_padding(Padding::Block(32,),);
+    remote.set_padding(Padding::Block(32,),);
+
+    let short = local.lock(&mut [1; 3],).expect("Error locking short message");
+    let long = local.lock(&mut [2; 30],).expect("Error locking long message");
+
+    assert_eq!(short.data.len(), long.data.len(), "Message lengths not hidden",);
+
+    let mut buffer = Vec::new();
+    assert_eq!(remote.open(short, &mut buffer,).expect("Error opening short message"), &[1; 3],
+      "Short message corrupted",
+    );
+    buffer.clear();
+    assert_eq!(remote.open(long, &mut buffer,).expect("Error opening long message"), &[2; 30],
+      "Long message corrupted",
+    );
+
+    //Messages which do not fit a fixed length cannot be locked.
+    local.set_padding(Padding::Fixed(16,),);
+    assert_eq!(local.lock(&mut [3; 16],), Err(Error::MessageLength), "Locked an oversized message",);
+
+    //Unpadded messages are rejected by a padding Client.
+    local.set_padding(Padding::None,);
+    let message = local.lock(&mut [4; 10],).expect("Error locking unpadded message");
+    match remote.open(message.clone(), &mut Vec::new(),) {
+      Err((_, Error::Padding,)) => {},
+      _ => panic!("Invalid padding accepted"),
+    }
+
+    //The key is kept so the message can be opened with the correct policy.
+    remote.set_padding(Padding::None,);
+    remote.open(message, &mut Vec::new(),).expect("Error opening unpadded message");
+  }
+  #[test]
+  fn test_client_pending() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let msg = [1; 20];
+    let message = local.lock(&mut msg.clone(),).expect("Error locking first message");
+    let other = local.lock(&mut msg.clone(),).expect("Error locking second message");
+    let mut buffer = Vec::new();
+
+    //Test aborting a pending open.
+    remote.open_pending(message.clone(), &mut buffer,).expect("Error opening pending message");
+    assert_eq!(buffer, msg.as_ref(), "Pending message corrupted",);
+    let during = remote.lock(&mut msg.clone(),).expect("Error locking while pending");
+    match remote.open(other.clone(), &mut Vec::new(),) {
+      Err((_, Error::Pending,)) => {},
+      _ => panic!("Opened while pending"),
+    }
+    remote.abort();
+
+    //Test commiting a pending open.
+    buffer.clear();
+    remote.open_pending(message.clone(), &mut buffer,).expect("Error reopening aborted message");
+    assert_eq!(buffer, msg.as_ref(), "Reopened message corrupted",);
+    remote.commit();
+    match remote.open(message, &mut Vec::new(),) {
+      Err((_, Error::NoKey,)) => {},
+      _ => panic!("Opened a commited message twice"),
+    }
+
+    buffer.clear();
+    remote.open(other, &mut buffer,).expect("Error opening second message");
+    assert_eq!(buffer, msg.as_ref(), "Second message corrupted",);
+
+    //Test aborting a ratchet step.
+    let message = remote.lock(&mut msg.clone(),).expect("Error locking reply");
+    assert_ne!(message.header.public_key[..], local.0.open.current_public_key[..], "Reply is not from a new step",);
+    let lock = local.0.lock.next_header;
+    let private_key = local.0.private_key.clone();
+    let root = local.0.root.clone();
+    let current_public_key = local.0.open.current_public_key.clone();
+    let current_keys = local.0.open.current_keys.keys().copied().collect::<HashSet<_>>();
+    let chain_fingerprint = local.0.open.chain_fingerprint;
+    let exported = local.export_keying_material(b"pending", b"", 32,).expect("Error exporting");
+    buffer.clear();
+    local.open_pending(message.clone(), &mut buffer,).expect("Error opening pending reply");
+    assert!(local.0.pending.as_ref().map_or(false, |pending,| pending.step.is_some(),), "No ratchet step taken",);
+
+    //The pending open is persisted with the Client.
+    let serialised = serde_cbor::to_vec(&local,).expect("Error serialising pending Client");
+    let restored = serde_cbor::from_slice::<LocalClient<Sha1, consts::U64,>>(&serialised,)
+      .expect("Error deserialising pending Client");
+    assert!(restored.info().pending, "Pending open not persisted",);
+
+    for mut client in vec![local, restored,] {
+      client.abort();
+      assert_eq!(client.0.lock.next_header, lock, "Lock state not restored",);
+      assert_eq!(client.0.private_key[..], private_key[..], "Private key not restored",);
+      assert_eq!(client.0.root[..], root[..], "Root key not restored",);
+      assert_eq!(client.0.open.current_public_key[..], current_public_key[..], "Public key not restored",);
+      assert_eq!(client.0.open.current_keys.keys().copied().collect::<HashSet<_>>(), current_keys, "Keys not restored",);
+      assert!(client.0.open.previous_keys.is_empty(), "Previous step not restored",);
+      assert_eq!(client.0.open.chain_fingerprint, chain_fingerprint, "Chain fingerprint not restored",);
+      assert_eq!(client.export_keying_material(b"pending", b"", 32,), Ok(exported.clone()), "Exporter not restored",);
+
+      buffer.clear();
+      client.open(message.clone(), &mut buffer,).expect("Error opening aborted reply");
+      assert_eq!(buffer, msg.as_ref(), "Reply corrupted",);
+      buffer.clear();
+      client.open(during.clone(), &mut buffer,).expect("Error opening message locked while pending");
+      assert_eq!(buffer, msg.as_ref(), "Message locked while pending corrupted",);
+    }
+  }
+  #[test]
+  fn test_client_lock_pending() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let msg = [1; 20];
+    let reply = remote.lock(&mut msg.clone(),).expect("Error locking reply");
+    let public_key = local.0.lock.next_header.public_key;
+    let mut buffer = Vec::new();
+
+    //Messages locked while a ratchet step is pending use the state from before the step.
+    local.open_pending(reply, &mut buffer,).expect("Error opening pending reply");
+    let first = local.lock(&mut msg.clone(),).expect("Error locking while pending");
+    assert_eq!(first.header.public_key, public_key, "Locked with the pending step",);
+
+    let serialised = serde_cbor::to_vec(&local,).expect("Error serialising pending Client");
+    let mut aborted = serde_cbor::from_slice::<LocalClient<Sha1, consts::U64,>>(&serialised,)
+      .expect("Error deserialising pending Client");
+
+    //The committed step follows the messages locked while pending.
+    local.commit();
+    let second = local.lock(&mut msg.clone(),).expect("Error locking message");
+    assert_eq!(second.header.previous_step, 1, "Step does not follow the pending messages",);
+    remote.open(second, &mut Vec::new(),).expect("Error opening message from the new step");
+    buffer.clear();
+    remote.open(first, &mut buffer,).expect("Error opening message locked while pending");
+    assert_eq!(buffer, msg.as_ref(), "Message locked while pending corrupted",);
+
+    //An aborted step keeps the messages locked while pending.
+    aborted.abort();
+    let second = aborted.lock(&mut msg.clone(),).expect("Error locking after abort");
+    assert_eq!(second.header.message_index, 1, "Message locked while pending was lost",);
+  }
+  #[test]
+  fn test_client_value() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let value = (String::from("value",), vec![1u32, 2, 3,], Some(4u64),);
+
+    let message = local.lock_value(&value,).expect("Error locking value");
+    let other: (String, Vec<u32>, Option<u64>,) = remote.open_value(message.clone(),)
+      .expect("Error opening value");
+    assert_eq!(value, other, "Value corrupted",);
+
+    match remote.open_value::<u32,>(message,) {
+      Err((_, Error::NoKey,)) => {},
+      _ => panic!("Opened a value twice"),
+    }
+
+    let message = local.lock(&mut [0xFF; 3],).expect("Error locking bytes");
+    match remote.open_value::<String,>(message.clone(),) {
+      Err((None, Error::Deserialise,)) => {},
+      _ => panic!("Deserialised an invalid value"),
+    }
+    //The key was kept when the invalid value was refused.
+    remote.open(message, &mut Vec::new(),).expect("Error opening invalid value as bytes");
+  }
+  #[test]
+  fn test_client_safety_number() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+
+    assert!(local.safety_number().matches(&remote.safety_number(),), "SafetyNumbers differ",);
+    assert_eq!(local.remote_identity().as_bytes(), PublicKey::from(&remote_sec,).as_bytes(), "Bad remote identity",);
+  }
+  #[test]
+  fn test_client_seeded() {
+    use rand::{SeedableRng, rngs::StdRng,};
+    use serde_cbor::ser;
+
+    /// Runs a conversation between two seeded Clients and returns the transcript.
+    fn transcript(seed: u64,) -> Vec<Vec<u8>> {
+      let mut rand = StdRng::seed_from_u64(seed,);
+      let open_sec = StaticSecret::from([1; 32],);
+      let lock_sec = StaticSecret::from([2; 32],);
+      let mut lock = LocalClient::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U10,>::connect_with_rng(&(&open_sec).into(), &lock_sec, &mut rand,);
+      let mut open = RemoteClient::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U10,>::accept_with_rng(&(&lock_sec).into(), &open_sec, &mut rand,);
+      let mut transcript = Vec::new();
+      let mut buffer = Vec::new();
+
+      for index in 0..3 {
+        let message = lock.lock(&mut [index; 20],).expect("Error locking message");
+        transcript.push(ser::to_vec_packed(&message,).expect("Error serialising message"),);
+        open.open_with_rng(message, &mut buffer, &mut rand,).expect("Error opening message");
+
+        let message = open.lock(&mut [index; 20],).expect("Error locking reply");
+        transcript.push(ser::to_vec_packed(&message,).expect("Error serialising reply"),);
+        lock.open_with_rng(message, &mut buffer, &mut rand,).expect("Error opening reply");
+      }
+
+      transcript.push(ser::to_vec_packed(&lock,).expect("Error serialising LocalClient"),);
+      transcript.push(ser::to_vec_packed(&open,).expect("Error serialising RemoteClient"),);
+
+      transcript
+    }
+
+    assert_eq!(transcript(1,), transcript(1,), "Seeded transcripts differ",);
+    assert_ne!(transcript(1,), transcript(2,), "Differently seeded transcripts match",);
+  }
 }
@@ -0,0 +1,83 @@
+//! Defines the [DelegatedKey] used to open a single message outside of its [Client].
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::{aead::Algorithm, OpenData, Error, open::decrypt,};
+use crate::{
+  message::{Message, Header,},
+  padding::Padding,
+  clock::{Clock, SystemClock,},
+  generic_array::ArrayLength,
+};
+
+mod serde;
+
+/// A single use token which opens exactly one message.
+/// 
+/// The token is extracted from a Client with `delegate` and can be serialised and sent
+/// to another process, such as a push notification handler, which opens the message
+/// without owning the Client.
+/// The Client records that the key was handed out and will refuse the message with
+/// [Error::Delegated].
+pub struct DelegatedKey<Algorithm, AadLength,>
+  where Algorithm: super::aead::Algorithm,
+    AadLength: ArrayLength<u8>, {
+  /// The header of the message the key opens.
+  header: Header,
+  /// The key of the message.
+  open_data: OpenData<Algorithm, AadLength,>,
+  /// The padding policy the message was locked with.
+  padding: Padding,
+}
+
+impl<A, L,> DelegatedKey<A, L,>
+  where A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// Constructs a new `DelegatedKey`.
+  #[inline]
+  pub(crate) fn new(header: Header, open_data: OpenData<A, L,>, padding: Padding,) -> Self {
+    Self { header, open_data, padding, }
+  }
+  /// The header of the message the key opens.
+  #[inline]
+  pub fn header(&self,) -> &Header { &self.header }
+  /// Opens the message consuming the key.
+  /// 
+  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  #[inline]
+  pub fn open<'a,>(self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
+    self.open_with_clock(message, buffer, &SystemClock,)
+  }
+  /// Opens the message consuming the key using the passed [Clock] to check its expiry.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  /// clock --- The source of the current time.  
+  pub fn open_with_clock<'a,>(self, message: Message, buffer: &'a mut Vec<u8>, clock: &dyn Clock,) -> Result<&'a mut [u8], (Message, Error,)> {
+    use clear_on_drop::clear::Clear;
+
+    //The key only opens the message it was delegated for.
+    if message.header != self.header { return Err((message, Error::NoKey,)) }
+
+    //Expired messages are authenticated before the key is discarded.
+    if message.expires().map_or(false, |expires,| clock.now() >= expires,) {
+      return match decrypt(&self.open_data, &message, &mut Vec::new(), self.padding,) {
+        Ok((data, _,)) => { data.clear(); Err((message, Error::Expired,)) },
+        Err(e) => Err((message, e,)),
+      }
+    }
+
+    match decrypt(&self.open_data, &message, buffer, self.padding,) {
+      Ok((data, _,)) => Ok(data),
+      Err(e) => Err((message, e,)),
+    }
+  }
+}
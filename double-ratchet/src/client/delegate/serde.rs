@@ -0,0 +1,66 @@
+//! Defines serde for DelegatedKey.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+use std::marker::PhantomData;
+
+static FIELDS: &[&str] = &[
+  "header",
+  "open_data",
+  "padding",
+];
+
+impl<A, L,> Serialize for DelegatedKey<A, L,>
+  where A: Algorithm,
+    L: ArrayLength<u8>, {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(DelegatedKey,), FIELDS.len(),)?;
+
+    serializer.serialize_field(&self.header,)?;
+    serializer.serialize_field(&self.open_data,)?;
+    serializer.serialize_field(&self.padding,)?;
+    serializer.end()
+  }
+}
+
+impl<'de, A, L,> Deserialize<'de> for DelegatedKey<A, L,>
+  where A: Algorithm,
+    L: ArrayLength<u8>, {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct KeyVisitor<A, L,>(PhantomData<(A, L,)>,);
+
+    impl<'de, A, L,> Visitor<'de> for KeyVisitor<A, L,>
+      where A: Algorithm,
+        L: ArrayLength<u8>, {
+      type Value = DelegatedKey<A, L,>;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let header = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+        let open_data = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+        let padding = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[2],),)?;
+
+        Ok(DelegatedKey { header, open_data, padding, })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(DelegatedKey,), FIELDS.len(), KeyVisitor(PhantomData,),)
+  }
+}
@@ -0,0 +1,126 @@
+//! Defines the [SessionInfo] snapshot used to monitor a [Client].
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::{aead::Algorithm, InnerClient,};
+use crate::generic_array::ArrayLength;
+use ring::digest;
+use std::{
+  mem,
+  hash::Hash,
+  collections::{HashMap, HashSet,},
+};
+
+/// A read only snapshot of the state of a Client.
+/// 
+/// Keys are never exposed; public keys are identified by a short fingerprint which can be
+/// compared between snapshots and the headers of messages.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub struct SessionInfo {
+  /// Indicates whether the Client is the initiator of the communication.
+  pub local: bool,
+  /// The fingerprint of the public key messages are currently locked under.
+  pub local_key: [u8; 8],
+  /// The fingerprint of the public key of the remote Client in the current step.
+  pub remote_key: [u8; 8],
+  /// The fingerprint of the current ratchet step.
+  pub chain_fingerprint: Option<[u8; 8]>,
+  /// The index of the next message locked.
+  pub message_index: u32,
+  /// The number of messages locked in the previous step.
+  pub previous_step: u32,
+  /// The number of messages received from the remote Client in the current step.
+  pub received_index: u32,
+  /// The number of unused keys in the current step.
+  pub current_keys: usize,
+  /// The number of unused keys in the retained previous steps.
+  pub previous_keys: usize,
+  /// The number of previous steps retained.
+  pub retained_steps: usize,
+  /// The number of message keys delegated.
+  pub delegated: usize,
+  /// Indicates whether an open is pending.
+  pub pending: bool,
+  /// The number of messages sent.
+  pub sent: u64,
+  /// The number of messages received.
+  pub received: u64,
+  /// The approximate number of bytes of memory used by the Client.
+  pub memory: usize,
+}
+
+impl SessionInfo {
+  /// Takes a snapshot of a Client.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The Client to inspect.  
+  /// local --- Indicates whether the Client is the initiator of the communication.  
+  pub(crate) fn new<D, S, A, R, L,>(client: &InnerClient<D, S, A, R, L,>, local: bool,) -> Self
+    where S: ArrayLength<u8>,
+      A: Algorithm,
+      L: ArrayLength<u8>, {
+    let open = &client.open;
+    let memory = mem::size_of_val(client,)
+      + mem::size_of_val(&*client.clock,)
+      + map_memory(&open.current_keys,)
+      + map_memory(&open.previous_keys,)
+      + open.previous_keys.values().map(map_memory,).sum::<usize>()
+      + map_memory(&open.retired,)
+      + set_memory(&client.delegated,)
+      + client.registry.iter().count() * mem::size_of::<u16>()
+      + client.pending.as_ref().map_or(0, |pending,| mem::size_of_val(pending,),);
+
+    Self {
+      local,
+      local_key: fingerprint(&client.lock.next_header.public_key,),
+      remote_key: fingerprint(&open.current_public_key,),
+      chain_fingerprint: open.chain_fingerprint,
+      message_index: client.lock.next_header.message_index,
+      previous_step: client.lock.next_header.previous_step,
+      received_index: open.sent_count,
+      current_keys: open.current_keys.len(),
+      previous_keys: open.previous_keys.values().map(HashMap::len,).sum(),
+      retained_steps: open.previous_keys.len(),
+      delegated: client.delegated.len(),
+      pending: client.pending.is_some(),
+      sent: client.transcript.sent.count(),
+      received: client.transcript.received.count(),
+      memory,
+    }
+  }
+}
+
+/// Derives the redacted fingerprint of a public key.
+/// 
+/// # Params
+/// 
+/// key --- The public key to fingerprint.  
+pub(crate) fn fingerprint(key: &[u8],) -> [u8; 8] {
+  let mut fingerprint = [0; 8];
+
+  fingerprint.copy_from_slice(&digest::digest(&digest::SHA256, key,).as_ref()[..8],);
+  fingerprint
+}
+
+/// The approximate number of bytes allocated by a map excluding the allocations of its values.
+/// 
+/// # Params
+/// 
+/// map --- The map to measure.  
+fn map_memory<K, V,>(map: &HashMap<K, V,>,) -> usize
+  where K: Eq + Hash, {
+  //Every bucket stores a control byte alongside its entry.
+  map.capacity() * (mem::size_of::<(K, V,)>() + 1)
+}
+
+/// The approximate number of bytes allocated by a set.
+/// 
+/// # Params
+/// 
+/// set --- The set to measure.  
+fn set_memory<T,>(set: &HashSet<T,>,) -> usize
+  where T: Eq + Hash, {
+  set.capacity() * (mem::size_of::<T>() + 1)
+}
@@ -1,12 +1,14 @@
 //! Defines the locking half of a [Client].
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-15
+//! Last Moddified --- 2026-10-18
 
 use super::{aead::Algorithm, OpenData, Error,};
 use crate::{
   ratchet::Ratchet,
-  message::{Message, Header,},
+  message::{Message, Header, Extensions, Expires, ChainFingerprint, Franking,},
+  padding::Padding,
+  franking::{self, KEY_LENGTH,},
   generic_array::ArrayLength,
   typenum::Unsigned,
 };
@@ -24,6 +26,8 @@ pub(crate) struct LockClient<Digest, State, Algorithm, Rounds, AadLength,>
   pub ratchet: Ratchet<Digest, State, Rounds,>,
   /// The Header for the next message encrypted.
   pub next_header: Header,
+  /// The fingerprint of the chain sent with every message.
+  pub chain_fingerprint: Option<[u8; 8]>,
   pub _data: PhantomData<(Algorithm, AadLength,)>,
 }
 
@@ -52,11 +56,20 @@ impl<D, S, A, R, L,> LockClient<D, S, A, R, L,>
   /// # Params
   /// 
   /// buffer --- The message data to encrypt.  
-  pub fn lock(&mut self, buffer: &mut [u8],) -> Result<Message, Error> {
+  /// padding --- The padding policy to apply to the message data.  
+  /// expires --- The time in seconds since the UNIX epoch after which the message cannot be opened.  
+  /// extensions --- The extensions to send with the message.  
+  /// franking --- The franking key to commit to the message data with.  
+  pub fn lock(&mut self, buffer: &mut [u8], padding: Padding, expires: Option<u64>, mut extensions: Extensions, franking: Option<&[u8; KEY_LENGTH]>,) -> Result<Message, Error> {
     use std::{mem, iter, hint,};
     
+    //The franking key is encrypted after the message data.
+    let key = franking.map_or(&[][..], |key,| &key[..],);
+    //Calculate the length of the padded message.
+    let padded_length = padding.padded_length(buffer.len() + key.len(),)
+      .ok_or(Error::MessageLength,)?;
     //Check the message length is valid.
-    if buffer.len() > self.max_message_length() { return Err(Error::MessageLength) };
+    if padded_length > self.max_message_length() { return Err(Error::MessageLength) };
 
     //The encryption algorithm.
     let algorithm = A::algorithm();
@@ -71,9 +84,20 @@ impl<D, S, A, R, L,> LockClient<D, S, A, R, L,>
       //Replace the cached header.
       mem::replace(&mut self.next_header, header,)
     };
-    //Pad the message data to fit the encrypted output and auth tag.
+    //The end of message marker.
+    let marker = padding.marker();
+    //The number of zero bytes padding the message.
+    let zeros = padded_length - buffer.len() - key.len() - marker.iter().count();
+    //The fields added to the protocol are sent as extensions.
+    if let Some(expires) = expires { extensions.set(&Expires(expires,),); }
+    if let Some(chain_fingerprint) = self.chain_fingerprint { extensions.set(&ChainFingerprint(chain_fingerprint,),); }
+    //Commit to the message data.
+    if let Some(key) = franking { extensions.set(&Franking(franking::commit(key, buffer,),),); }
+    //Pad the message data to fit the padding, encrypted output and auth tag.
     let mut data = ClearOnDrop::new(buffer.iter().copied()
-      .chain(iter::repeat(0,).take(A::TagLength::USIZE,),)
+      .chain(key.iter().copied(),)
+      .chain(marker,)
+      .chain(iter::repeat(0,).take(zeros + A::TagLength::USIZE,),)
       .collect::<Box<[u8]>>(),
     );
     //Calculate the sealing data.
@@ -97,7 +121,10 @@ impl<D, S, A, R, L,> LockClient<D, S, A, R, L,>
         _ => unsafe { hint::unreachable_unchecked() }
       };
       //Get the authentication data.
-      let aad = Aad::from(&sealing_data.aad,);
+      let aad = sealing_data.aad.iter().copied()
+        .chain(extensions.authenticated_data(),)
+        .collect::<Vec<_>>();
+      let aad = Aad::from(&aad,);
       //Seal the message and get the length of the encrypted data.
       let length = aead::seal_in_place(sealing_key, nonce, aad, data.as_mut(), A::TagLength::USIZE,).ok()
         .ok_or(Error::Encryption,)?;
@@ -106,11 +133,42 @@ impl<D, S, A, R, L,> LockClient<D, S, A, R, L,>
 
       buffer.clear();
 
-      Ok(Message { header, data, })
+      Ok(Message { header, data, extensions, })
     },)
   }
 }
 
+impl<D, S, A, R, L,> LockClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  /// Encrypts the passed data committing to it with a random franking key if `franking`
+  /// is `true`.
+  /// 
+  /// # Params
+  /// 
+  /// buffer --- The message data to encrypt.  
+  /// padding --- The padding policy to apply to the message data.  
+  /// expires --- The time in seconds since the UNIX epoch after which the message cannot be opened.  
+  /// extensions --- The extensions to send with the message.  
+  /// franking --- Indicates whether the message is franked.  
+  /// rand --- The source of randomness used to generate the franking key.  
+  pub fn lock_franked<Rand,>(&mut self, buffer: &mut [u8], padding: Padding, expires: Option<u64>, extensions: Extensions, franking: bool, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    use clear_on_drop::clear::Clear;
+
+    //Franked messages commit to their data with a random franking key.
+    let mut key = [0; KEY_LENGTH];
+    let franking = if franking { rand.fill_bytes(&mut key,); Some(&key) } else { None };
+
+    let message = self.lock(buffer, padding, expires, extensions, franking,);
+    key[..].clear();
+
+    message
+  }
+}
+
 impl<D, S, A, R, L,> Default for LockClient<D, S, A, R, L,>
   where S: ArrayLength<u8>,
     A: Algorithm,
@@ -120,6 +178,7 @@ impl<D, S, A, R, L,> Default for LockClient<D, S, A, R, L,>
     Self {
       ratchet: Ratchet::default(),
       next_header: Header::default(),
+      chain_fingerprint: None,
       _data: PhantomData,
     }
   }
@@ -146,14 +205,20 @@ mod tests {
         public_key: [1; 32].into(),
         ..Header::default()
       },
+      chain_fingerprint: None,
       _data: PhantomData,
     };
     let mut open = OpenClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,> {
       current_public_key: ClearOnDrop::new([1; 32].into(),),
       sent_count: 1,
-      current_keys: vec![(0, OpenData::new(&mut ratchet,),),].into_iter().collect(),
+      current_keys: vec![
+        (0, OpenData::new(&mut ratchet,),),
+        (1, OpenData::new(&mut ratchet,),),
+      ].into_iter().collect(),
       ratchet,
       previous_keys: Default::default(),
+      chain_fingerprint: None,
+      retired: Default::default(),
     };
     let msg_length = (std::usize::MAX - <Aes256Gcm as Algorithm>::TagLength::USIZE)
       / <Aes256Gcm as Algorithm>::BlockSize::USIZE
@@ -162,13 +227,25 @@ mod tests {
     assert_eq!(lock.max_message_length(), msg_length, "Bad max message length",);
 
     let msg = [1; 20];
-    let other = lock.lock(&mut msg.clone(),)
+    let other = lock.lock(&mut msg.clone(), Padding::None, None, Extensions::new(), None,)
       .expect("Error encrpyting message");
     let mut buffer = Vec::new();
-    let other = open.open(other, &mut buffer,)
+    let other = open.open(other, &mut buffer, Padding::None, 0,)
       .expect("Error decrypting message");
     
     assert_eq!(lock.next_header.message_index, 1, "Sent count failed to update",);
     assert_eq!(msg.as_ref(), other, "Message does not match",);
+
+    let other = lock.lock(&mut msg.clone(), Padding::Block(64,), None, Extensions::new(), None,)
+      .expect("Error encrpyting padded message");
+
+    assert_eq!(other.data.len(), 64 + <Aes256Gcm as Algorithm>::TagLength::USIZE, "Bad padded length",);
+
+    let mut buffer = Vec::new();
+    let other = open.open(other, &mut buffer, Padding::Block(64,), 0,)
+      .expect("Error decrypting padded message");
+
+    assert_eq!(msg.as_ref(), other, "Padded message does not match",);
+    lock.lock(&mut [0; 20], Padding::Fixed(20,), None, Extensions::new(), None,).expect_err("Locked an oversized message");
   }
 }
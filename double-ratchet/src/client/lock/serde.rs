@@ -12,6 +12,7 @@ use ::serde::{
 static FIELDS: &[&str] = &[
   "ratchet",
   "next_header",
+  "chain_fingerprint",
 ];
 
 impl<D, S, A, R, L,> Serialize for LockClient<D, S, A, R, L,>
@@ -22,6 +23,7 @@ impl<D, S, A, R, L,> Serialize for LockClient<D, S, A, R, L,>
 
     serializer.serialize_field(&self.ratchet,)?;
     serializer.serialize_field(&self.next_header,)?;
+    serializer.serialize_field(&self.chain_fingerprint,)?;
     serializer.end()
   }
 }
@@ -48,8 +50,10 @@ impl<'de, D, S: 'de, A, R, L,> Deserialize<'de> for LockClient<D, S, A, R, L,>
           .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
         let next_header = seq.next_element()?
           .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+        //Clients from older versions sent the fingerprint in the header.
+        let chain_fingerprint = seq.next_element()?.unwrap_or_default();
         
-        Ok(LockClient { ratchet, next_header, _data: PhantomData, })
+        Ok(LockClient { ratchet, next_header, chain_fingerprint, _data: PhantomData, })
       }
     }
 
@@ -73,6 +77,7 @@ mod tests {
         public_key,
         ..Header::default()
       },
+      chain_fingerprint: Some([2; 8]),
       ..LockClient::default()
     };
     let mut serialised = [0; 1024];
@@ -1,11 +1,13 @@
 //! Defines the opening half of a [Client].
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-15
+//! Last Moddified --- 2026-10-18
 
 use super::{aead::Algorithm, OpenData, Error,};
 use crate::{
-  message::Message,
+  message::{Message, Header,},
+  padding::Padding,
+  franking::{Opening, KEY_LENGTH,},
   generic_array::{ArrayLength, GenericArray,},
   typenum::consts::U32,
 };
@@ -30,12 +32,134 @@ pub(crate) struct OpenClient<Digest, State, Algorithm, Rounds, AadLength,>
   pub current_keys: HashMap<u32, OpenData<Algorithm, AadLength,>>,
   /// The OpenData under the previous PublicKeys.
   pub previous_keys: HashMap<ClearOnDrop<GenericArray<u8, U32>>, HashMap<u32, OpenData<Algorithm, AadLength,>>>,
+  /// The fingerprint of the current ratchet step.
+  pub chain_fingerprint: Option<[u8; 8]>,
+  /// The time in seconds since the UNIX epoch that each previous PublicKey was replaced.
+  pub retired: HashMap<ClearOnDrop<GenericArray<u8, U32>>, u64>,
 }
 
 impl<D, S, A, R, L,> OpenClient<D, S, A, R, L,>
   where S: ArrayLength<u8>,
     A: Algorithm,
     L: ArrayLength<u8>, {
+  /// Removes the OpenData for the passed message header.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message to get the OpenData for.  
+  pub fn take_key(&mut self, header: &Header,) -> Option<OpenData<A, L,>> {
+    use std::collections::hash_map::Entry;
+
+    //The message is part of the current step.
+    if self.current_public_key.as_ref() == header.public_key.as_ref() {
+      //Get the opening data for the message from the current step.
+      self.current_keys.remove(&header.message_index,)
+    //The message must be part of a previous step.
+    } else {
+      match self.previous_keys.entry(ClearOnDrop::new(header.public_key.into(),),) {
+        //There are previous keys for this public key.
+        Entry::Occupied(mut entry) => {
+          let keys = entry.get_mut();
+          //Remove the key.
+          let open_data = keys.remove(&header.message_index,);
+
+          //Clear the entry once there are no more keys.
+          if keys.is_empty() { entry.remove(); }
+
+          open_data
+        },
+        //There are no keys for this public key.
+        Entry::Vacant(_) => None,
+      }
+    }
+  }
+  /// Stores the OpenData for the passed message header.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message the OpenData belongs too.  
+  /// open_data --- The OpenData to store.  
+  pub fn restore_key(&mut self, header: &Header, open_data: OpenData<A, L,>,) {
+    //The message is part of the current step.
+    if self.current_public_key.as_ref() == header.public_key.as_ref() {
+      self.current_keys.insert(header.message_index, open_data,);
+    //The message is part of a previous step.
+    } else {
+      self.previous_keys.entry(ClearOnDrop::new(header.public_key.into(),),)
+        .or_default()
+        .insert(header.message_index, open_data,);
+    }
+  }
+  /// Opens the passed message and appends the data to `buffer` returning the OpenData
+  /// which was used and the franking [Opening] of the message.
+  /// 
+  /// If the message cannot be opened it is returned and the OpenData is kept.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to open.  
+  /// buffer --- The buffer to append the decrypted message too.  
+  /// padding --- The padding policy the message was locked with.  
+  /// now --- The current time in seconds since the UNIX epoch.  
+  pub fn open_pending<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, padding: Padding, now: u64,) -> Result<(&'a mut [u8], OpenData<A, L,>, Option<Opening>,), (Message, Error,)> {
+    let open_data = match self.take_key(&message.header,) {
+      Some(v) => v,
+      None => return Err((message, Error::NoKey,)),
+    };
+
+    //Expired messages are authenticated before their key is destroyed.
+    if message.expires().map_or(false, |expires,| now >= expires,) {
+      return match decrypt(&open_data, &message, &mut Vec::new(), padding,) {
+        Ok((data, _,)) => { data.clear(); Err((message, Error::Expired,)) },
+        Err(e) => {
+          //Store the key for a later attempt.
+          self.restore_key(&message.header, open_data,);
+
+          Err((message, e,))
+        },
+      }
+    }
+
+    match decrypt(&open_data, &message, buffer, padding,) {
+      Ok((data, opening,)) => Ok((data, open_data, opening,)),
+      Err(e) => {
+        //Store the key for a later attempt.
+        self.restore_key(&message.header, open_data,);
+
+        Err((message, e,))
+      },
+    }
+  }
+  /// Indicates whether the passed message authenticates under its stored OpenData.
+  /// 
+  /// The key of the message is not consumed and the decrypted data is cleared.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to authenticate.  
+  /// padding --- The padding policy the message was locked with.  
+  pub fn authenticates(&self, message: &Message, padding: Padding,) -> bool {
+    let header = &message.header;
+    let open_data = if self.current_public_key.as_ref() == header.public_key.as_ref() {
+      self.current_keys.get(&header.message_index,)
+    } else {
+      self.previous_keys.get(&ClearOnDrop::new(header.public_key.into(),),)
+        .and_then(|keys,| keys.get(&header.message_index,),)
+    };
+    let open_data = match open_data {
+      Some(v) => v,
+      None => return false,
+    };
+    let mut buffer = Vec::new();
+    //Padding and franking are only checked once the message has authenticated.
+    let authenticated = match decrypt(open_data, message, &mut buffer, padding,) {
+      Err(Error::Decryption) => false,
+      _ => true,
+    };
+
+    ClearOnDrop::new(buffer.as_mut_slice(),);
+    authenticated
+  }
   /// Opens the passed message and appends the data to `buffer`.
   /// 
   /// If the message cannot be opened it is returned.
@@ -44,83 +168,118 @@ impl<D, S, A, R, L,> OpenClient<D, S, A, R, L,>
   /// 
   /// message --- The message to open.  
   /// buffer --- The buffer to append the decrypted message too.  
-  pub fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
-    use ring::aead::{self, OpeningKey, Nonce, Aad,};
-    use std::{hint, collections::hash_map::Entry,};
-    
-    clear_on_drop::clear_stack_on_return_fnonce(1, move || {
-      //The message is part of the current step.
-      let open_data = if self.current_public_key.as_ref() == message.header.public_key.as_ref() {
-        //Get the opening data for the message from the current step.
-        match self.current_keys.remove(&message.header.message_index,) {
-          Some(v) => v,
-          None => return Err((message, Error::NoKey,)),
-        }
-      //The message must be part of a previous step.
-      } else {
-        match self.previous_keys.entry(ClearOnDrop::new(message.header.public_key.into(),),) {
-          //There are previous keys for this public key.
-          Entry::Occupied(mut entry) => {
-            let keys = entry.get_mut();
-            //Remove the key.
-            let open_data = match keys.remove(&message.header.message_index,) {
-              Some(v) => v,
-              //There is no key.
-              None => return Err((message, Error::NoKey,)),
-            };
-
-            //Clear the entry once there are no more keys.
-            if keys.is_empty() { entry.remove(); }
-
-            open_data
-          },
-          //There are no keys for this public key.
-          Entry::Vacant(_) => return Err((message, Error::NoKey,)),
-        }
-      };
-      let key = match OpeningKey::new(A::algorithm(), &open_data.key,) {
-        Ok(v) => v,
-        //This is safe because we get the value from OpenData.
-        _ => unsafe { hint::unreachable_unchecked() },
-      };
-      let nonce = match Nonce::try_assume_unique_for_key(&open_data.nonce,) {
-        Ok(v) => v,
-        //This is safe because we get the value from OpenData.
-        _ => unsafe { hint::unreachable_unchecked() },
-      };
-      let aad = Aad::from(open_data.aad.as_ref(),);
-      //The original length of the buffer before the message data is appended.
-      let buffer_len = buffer.len();
-      //The message data to open.
-      let data = {
-        //The decryption is done in place so we copy the data into the buffer for decryption.
-        buffer.extend(message.data.iter().copied(),);
-
-        &mut buffer[buffer_len..]
-      };
-      //Open the message.
-      let data_len = aead::open_in_place(&key, nonce, aad, 0, data,).ok()
-        //Get the length of the unencrypted data.
-        .map(|data,| data.len(),)
-        .ok_or_else(move || {
-          //Store the key for a later attempt.
-          self.current_keys.insert(message.header.message_index, open_data,); 
-        
-          (message, Error::Decryption,)
-        },)?;
-      //The length of buffer which is used.
-      let len = buffer_len + data_len;
-
-      //Clear the unused data.
-      buffer[len..].clear();
-      //Remove the unused data.
-      buffer.truncate(len,);
-
-      Ok(&mut buffer[buffer_len..])
-    },)
+  /// padding --- The padding policy the message was locked with.  
+  /// now --- The current time in seconds since the UNIX epoch.  
+  #[inline]
+  pub fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, padding: Padding, now: u64,) -> Result<&'a mut [u8], (Message, Error,)> {
+    self.open_pending(message, buffer, padding, now,).map(|(data, _, _,)| data,)
+  }
+  /// Destroys the keys of previous steps which were replaced before `before` returning
+  /// the public key and number of keys destroyed for each step.
+  /// 
+  /// # Params
+  /// 
+  /// before --- The time in seconds since the UNIX epoch before which keys are destroyed.  
+  /// now --- The current time in seconds since the UNIX epoch.  
+  pub fn purge(&mut self, before: u64, now: u64,) -> Vec<(ClearOnDrop<GenericArray<u8, U32>>, usize,)> {
+    let (previous_keys, retired,) = (&mut self.previous_keys, &mut self.retired,);
+    let mut evicted = Vec::new();
+
+    //Steps from older versions were never given a retirement time; they are retained for
+    //the full window from the first time they are seen.
+    for key in previous_keys.keys() {
+      retired.entry(key.clone(),).or_insert(now,);
+    }
+
+    previous_keys.retain(|key, keys,| {
+      let retain = retired.get(key,).map_or(false, |&time,| time >= before,);
+
+      if !retain { evicted.push((key.clone(), keys.len(),),); }
+
+      retain
+    },);
+    //Forget steps which were purged or whose keys have all been used.
+    retired.retain(|key, &mut time,| time >= before && previous_keys.contains_key(key,),);
+
+    evicted
   }
 }
 
+/// Decrypts the passed message and appends the data to `buffer` with the padding removed.
+/// 
+/// If the message is franked the commitment is checked and the franking key is removed
+/// from the data and returned as an [Opening].
+/// 
+/// # Params
+/// 
+/// open_data --- The OpenData to decrypt the message with.  
+/// message --- The message to decrypt.  
+/// buffer --- The buffer to append the decrypted message too.  
+/// padding --- The padding policy the message was locked with.  
+pub(crate) fn decrypt<'a, A, L,>(open_data: &OpenData<A, L,>, message: &Message, buffer: &'a mut Vec<u8>, padding: Padding,) -> Result<(&'a mut [u8], Option<Opening>,), Error>
+  where A: Algorithm,
+    L: ArrayLength<u8>, {
+  use ring::aead::{self, OpeningKey, Nonce, Aad,};
+  use std::hint;
+
+  clear_on_drop::clear_stack_on_return_fnonce(1, move || {
+    let key = match OpeningKey::new(A::algorithm(), &open_data.key,) {
+      Ok(v) => v,
+      //This is safe because we get the value from OpenData.
+      _ => unsafe { hint::unreachable_unchecked() },
+    };
+    let nonce = match Nonce::try_assume_unique_for_key(&open_data.nonce,) {
+      Ok(v) => v,
+      //This is safe because we get the value from OpenData.
+      _ => unsafe { hint::unreachable_unchecked() },
+    };
+    let aad = open_data.aad.iter().copied()
+      .chain(message.authenticated_data(),)
+      .collect::<Vec<_>>();
+    let aad = Aad::from(&aad,);
+    //The original length of the buffer before the message data is appended.
+    let buffer_len = buffer.len();
+    //The message data to open.
+    let data = {
+      //The decryption is done in place so we copy the data into the buffer for decryption.
+      buffer.extend(message.data.iter().copied(),);
+
+      &mut buffer[buffer_len..]
+    };
+    //Open the message and get the length of the unpadded data.
+    let data_len = match aead::open_in_place(&key, nonce, aad, 0, data,) {
+      Ok(data) => padding.unpad(data,).ok_or(Error::Padding,),
+      Err(_) => Err(Error::Decryption),
+    };
+    //Split the franking key from the message data and check the commitment.
+    let (data_len, opening,) = match (data_len, message.franking(),) {
+      (Ok(data_len), Some(commitment),) if data_len >= KEY_LENGTH => {
+        let data_len = data_len - KEY_LENGTH;
+        let data = &buffer[buffer_len..][..data_len + KEY_LENGTH];
+        let mut key = [0; KEY_LENGTH];
+        key.copy_from_slice(&data[data_len..],);
+
+        let opening = Opening::new(key, commitment,);
+        match opening.verify(&data[..data_len],) {
+          Ok(()) => (Ok(data_len), Some(opening),),
+          Err(_) => (Err(Error::Franking), None,),
+        }
+      },
+      (Ok(_), Some(_),) => (Err(Error::Franking), None,),
+      (data_len, _,) => (data_len, None,),
+    };
+    //The length of buffer which is used.
+    let len = buffer_len + *data_len.as_ref().unwrap_or(&0,);
+
+    //Clear the unused data.
+    buffer[len..].clear();
+    //Remove the unused data.
+    buffer.truncate(len,);
+
+    data_len.map(move |_,| (&mut buffer[buffer_len..], opening,),)
+  },)
+}
+
 impl<D, S, A, R, L,> Default for OpenClient<D, S, A, R, L,>
   where S: ArrayLength<u8>,
     A: Algorithm,
@@ -133,6 +292,8 @@ impl<D, S, A, R, L,> Default for OpenClient<D, S, A, R, L,>
       current_public_key: ClearOnDrop::new(GenericArray::default(),),
       current_keys: HashMap::default(),
       previous_keys: HashMap::default(),
+      chain_fingerprint: None,
+      retired: HashMap::default(),
     }
   }
 }
@@ -149,8 +310,8 @@ mod tests {
   use super::*;
   use crate::{
     typenum::consts,
-    message::Header,
     client::{LockClient, aead::Aes256Gcm,},
+    message::{Extensions, Expires,},
   };
   use sha1::Sha1;
 
@@ -172,18 +333,96 @@ mod tests {
       current_keys: vec![(0, OpenData::new(&mut ratchet,),),].into_iter().collect(),
       ratchet,
       previous_keys: Default::default(),
+      chain_fingerprint: None,
+      retired: HashMap::default(),
     };
     let msg = [1; 20];
-    let locked_msg = lock.lock(&mut msg.clone(),)
+    let locked_msg = lock.lock(&mut msg.clone(), Padding::None, None, Extensions::new(), None,)
       .expect("Error locking message");
     let mut buffer = Vec::new();
     
-    open.open(Message { data: [1; 100].as_ref().into(), ..locked_msg }, &mut buffer,)
+    open.open(Message { data: [1; 100].as_ref().into(), ..locked_msg.clone() }, &mut buffer, Padding::None, 0,)
       .expect_err("Opened a corrupted message");
 
-    let other_msg = open.open(locked_msg.clone(), &mut buffer,)
+    let other_msg = open.open(locked_msg.clone(), &mut buffer, Padding::None, 0,)
       .expect("Error opening message");
 
     assert_eq!(other_msg, msg.as_ref(), "Opened message corrupted",);
   }
+  #[test]
+  fn test_open_client_expiry() {
+    let mut ratchet = Ratchet::new(&mut rand::thread_rng(),);
+    let public_key = [1; 32];
+    let mut lock = LockClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,> {
+      ratchet: ratchet.clone(),
+      next_header: Header {
+        public_key,
+        ..Header::default()
+      },
+      ..LockClient::default()
+    };
+    let mut open = OpenClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,> {
+      current_public_key: ClearOnDrop::new(public_key.into(),),
+      sent_count: 2,
+      current_keys: vec![
+        (0, OpenData::new(&mut ratchet,),),
+        (1, OpenData::new(&mut ratchet,),),
+      ].into_iter().collect(),
+      ratchet,
+      previous_keys: HashMap::default(),
+      chain_fingerprint: None,
+      retired: HashMap::default(),
+    };
+    let message = lock.lock(&mut [1; 20], Padding::None, Some(10), Extensions::new(), None,)
+      .expect("Error locking message");
+
+    //The expiry is authenticated.
+    let mut extended = message.clone();
+    extended.extensions.set(&Expires(20,),);
+    match open.open(extended, &mut Vec::new(), Padding::None, 15,) {
+      Err((_, Error::Decryption,)) => {},
+      _ => panic!("Opened a message with a modified expiry"),
+    }
+    open.open(message.clone(), &mut Vec::new(), Padding::None, 9,)
+      .expect("Error opening unexpired message");
+
+    let message = lock.lock(&mut [1; 20], Padding::None, Some(10), Extensions::new(), None,)
+      .expect("Error locking message");
+    let mut buffer = Vec::new();
+
+    match open.open(message.clone(), &mut buffer, Padding::None, 10,) {
+      Err((_, Error::Expired,)) => {},
+      _ => panic!("Opened an expired message"),
+    }
+    assert!(buffer.is_empty(), "Expired message data was written",);
+    //The key of an expired message is destroyed.
+    match open.open(message, &mut buffer, Padding::None, 0,) {
+      Err((_, Error::NoKey,)) => {},
+      _ => panic!("Expired message key was kept"),
+    }
+  }
+  #[test]
+  fn test_open_client_purge() {
+    let mut ratchet = Ratchet::<Sha1, consts::U500, consts::U1,>::new(&mut rand::thread_rng(),);
+    let mut open = OpenClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,>::default();
+    let old_key = ClearOnDrop::new([1; 32].into(),);
+    let new_key = ClearOnDrop::new([2; 32].into(),);
+
+    for (key, time,) in vec![(old_key.clone(), 10,), (new_key.clone(), 20,),] {
+      open.previous_keys.insert(key.clone(), vec![(0, OpenData::new(&mut ratchet,),),].into_iter().collect(),);
+      open.retired.insert(key, time,);
+    }
+
+    assert!(open.purge(15, 30,) == vec![(old_key.clone(), 1,),], "Bad evicted keys",);
+    assert!(!open.previous_keys.contains_key(&old_key,), "Old step was not purged",);
+    assert!(open.previous_keys.contains_key(&new_key,), "New step was purged",);
+
+    //Steps from older versions are retained from the first time they are seen.
+    let untracked = ClearOnDrop::new([3; 32].into(),);
+    open.previous_keys.insert(untracked.clone(), vec![(0, OpenData::new(&mut ratchet,),),].into_iter().collect(),);
+    assert!(open.purge(15, 30,).is_empty(), "Untracked step was purged",);
+    assert_eq!(open.retired.get(&untracked,), Some(&30), "Untracked step was not stamped",);
+    assert!(open.purge(25, 40,) == vec![(new_key.clone(), 1,),], "Bad evicted keys",);
+    assert!(open.purge(31, 50,) == vec![(untracked.clone(), 1,),], "Untracked step was not purged",);
+  }
 }
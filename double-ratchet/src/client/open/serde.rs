@@ -1,7 +1,7 @@
 //! Defines serde for OpenClient.
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-12
+//! Last Moddified --- 2026-10-18
 
 use super::*;
 use ::serde::{
@@ -16,6 +16,8 @@ static FIELDS: &[&str] = &[
   "current_public_key",
   "current_keys",
   "previous_keys",
+  "chain_fingerprint",
+  "retired",
 ];
 
 impl<D, S, A, R, L,> Serialize for OpenClient<D, S, A, R, L,>
@@ -36,6 +38,11 @@ impl<D, S, A, R, L,> Serialize for OpenClient<D, S, A, R, L,>
       //This is safe because we are simply converting the type of the key which is a wrapper around an array already.
       mem::transmute::<_, &HashMap<[u8; 32], HashMap<u32, OpenData<A, L,>>>,>(&self.previous_keys,)
     },)?;
+    serializer.serialize_field(&self.chain_fingerprint,)?;
+    serializer.serialize_field(unsafe {
+      //This is safe because we are simply converting the type of the key which is a wrapper around an array already.
+      mem::transmute::<_, &HashMap<[u8; 32], u64>,>(&self.retired,)
+    },)?;
     serializer.end()
   }
 }
@@ -78,8 +85,16 @@ impl<'de, D, S: 'de, A, R, L,> Deserialize<'de> for OpenClient<D, S, A, R, L,>
           
           unsafe { mem::transmute::<_, HashMap<ClearOnDrop<GenericArray<u8, U32>>, HashMap<u32, OpenData<A, L,>>>>(previous_keys,) }
         };
+        //Clients from older versions do not store a fingerprint.
+        let chain_fingerprint = seq.next_element()?.unwrap_or_default();
+        //Clients from older versions do not record when steps were retired.
+        let retired = {
+          let retired = seq.next_element::<HashMap<[u8; 32], u64>>()?.unwrap_or_default();
+
+          unsafe { mem::transmute::<_, HashMap<ClearOnDrop<GenericArray<u8, U32>>, u64>>(retired,) }
+        };
 
-        Ok(OpenClient { ratchet, sent_count, current_public_key, current_keys, previous_keys, })
+        Ok(OpenClient { ratchet, sent_count, current_public_key, current_keys, previous_keys, chain_fingerprint, retired, })
       }
     }
 
@@ -100,12 +115,16 @@ mod tests {
     let current_public_key = ClearOnDrop::new([1; 32].into(),);
     let current_keys = HashMap::new();
     let previous_keys = HashMap::new();
+    let chain_fingerprint = Some([2; 8]);
+    let retired = vec![(ClearOnDrop::new([3; 32].into(),), 4,),].into_iter().collect();
     let client = OpenClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,> {
       ratchet,
       sent_count,
       current_public_key,
       current_keys,
       previous_keys,
+      chain_fingerprint,
+      retired,
     };
     let mut serialised = [0u8; 1024];
     let serialised = {
@@ -0,0 +1,56 @@
+//! Defines the state needed to rollback a pending open.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::{aead::Algorithm, OpenData, LockClient,};
+use crate::{
+  message::Header,
+  generic_array::{ArrayLength, GenericArray,},
+  typenum::consts::U32,
+};
+use ratchet::Ratchet;
+use clear_on_drop::ClearOnDrop;
+use std::ops::Range;
+
+mod serde;
+
+/// The state replaced by opening a message which has not been committed.
+pub(crate) struct Pending<Digest, State, Algorithm, Rounds, AadLength,>
+  where State: ArrayLength<u8>,
+    Algorithm: super::aead::Algorithm,
+    AadLength: ArrayLength<u8>, {
+  /// The header of the opened message.
+  pub header: Header,
+  /// The OpenData used to open the message.
+  pub open_data: Option<OpenData<Algorithm, AadLength,>>,
+  /// The opening Ratchet before the message was opened.
+  pub ratchet: Ratchet<Digest, State, Rounds,>,
+  /// The sent count before the message was opened.
+  pub sent_count: u32,
+  /// The indexes of the keys generated in the current step to open the message.
+  pub generated: Range<u32>,
+  /// The ratchet step taken to open the message.
+  pub step: Option<Step<Digest, State, Algorithm, Rounds, AadLength,>>,
+}
+
+/// The state replaced by a ratchet step.
+pub(crate) struct Step<Digest, State, Algorithm, Rounds, AadLength,>
+  where State: ArrayLength<u8>,
+    Algorithm: super::aead::Algorithm,
+    AadLength: ArrayLength<u8>, {
+  /// The locking half before the step.
+  pub lock: LockClient<Digest, State, Algorithm, Rounds, AadLength,>,
+  /// The private key before the step.
+  pub private_key: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The root key before the step.
+  pub root: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The remote PublicKey before the step.
+  pub current_public_key: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The chain fingerprint before the step.
+  pub chain_fingerprint: Option<[u8; 8]>,
+  /// The exporter secret before the step.
+  pub exporter: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The indexes of the keys generated in the previous step.
+  pub skipped: Range<u32>,
+}
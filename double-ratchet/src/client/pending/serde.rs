@@ -0,0 +1,155 @@
+//! Defines serde for Pending.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+use std::marker::PhantomData;
+
+static PENDING_FIELDS: &[&str] = &[
+  "header",
+  "open_data",
+  "ratchet",
+  "sent_count",
+  "generated",
+  "step",
+];
+
+impl<D, S, A, R, L,> Serialize for Pending<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  fn serialize<Ser,>(&self, serializer: Ser,) -> Result<Ser::Ok, Ser::Error>
+    where Ser: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(Pending,), PENDING_FIELDS.len(),)?;
+
+    serializer.serialize_field(&self.header,)?;
+    serializer.serialize_field(&self.open_data,)?;
+    serializer.serialize_field(&self.ratchet,)?;
+    serializer.serialize_field(&self.sent_count,)?;
+    serializer.serialize_field(&(self.generated.start, self.generated.end,),)?;
+    serializer.serialize_field(&self.step,)?;
+    serializer.end()
+  }
+}
+
+impl<'de, D, S: 'de, A, R, L,> Deserialize<'de> for Pending<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  fn deserialize<Des,>(deserializer: Des,) -> Result<Self, Des::Error>
+    where Des: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct PendingVisitor<D, S, A, R, L,>(PhantomData<(D, S, A, R, L,)>,);
+
+    impl<'de, D, S: 'de, A, R, L,> Visitor<'de> for PendingVisitor<D, S, A, R, L,>
+      where S: ArrayLength<u8>,
+        A: Algorithm,
+        L: ArrayLength<u8>, {
+      type Value = Pending<D, S, A, R, L,>;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", PENDING_FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let header = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(PENDING_FIELDS[0],),)?;
+        let open_data = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(PENDING_FIELDS[1],),)?;
+        let ratchet = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(PENDING_FIELDS[2],),)?;
+        let sent_count = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(PENDING_FIELDS[3],),)?;
+        let (start, end,) = seq.next_element::<(u32, u32,)>()?
+          .ok_or(Acc::Error::missing_field(PENDING_FIELDS[4],),)?;
+        let step = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(PENDING_FIELDS[5],),)?;
+
+        Ok(Pending { header, open_data, ratchet, sent_count, generated: start..end, step, })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(Pending,), PENDING_FIELDS.len(), PendingVisitor(PhantomData,),)
+  }
+}
+
+static STEP_FIELDS: &[&str] = &[
+  "lock",
+  "private_key",
+  "root",
+  "current_public_key",
+  "chain_fingerprint",
+  "exporter",
+  "skipped",
+];
+
+impl<D, S, A, R, L,> Serialize for Step<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  fn serialize<Ser,>(&self, serializer: Ser,) -> Result<Ser::Ok, Ser::Error>
+    where Ser: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(Step,), STEP_FIELDS.len(),)?;
+
+    serializer.serialize_field(&self.lock,)?;
+    serializer.serialize_field(self.private_key.as_ref(),)?;
+    serializer.serialize_field(self.root.as_ref(),)?;
+    serializer.serialize_field(self.current_public_key.as_ref(),)?;
+    serializer.serialize_field(&self.chain_fingerprint,)?;
+    serializer.serialize_field(self.exporter.as_ref(),)?;
+    serializer.serialize_field(&(self.skipped.start, self.skipped.end,),)?;
+    serializer.end()
+  }
+}
+
+impl<'de, D, S: 'de, A, R, L,> Deserialize<'de> for Step<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  fn deserialize<Des,>(deserializer: Des,) -> Result<Self, Des::Error>
+    where Des: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct StepVisitor<D, S, A, R, L,>(PhantomData<(D, S, A, R, L,)>,);
+
+    impl<'de, D, S: 'de, A, R, L,> Visitor<'de> for StepVisitor<D, S, A, R, L,>
+      where S: ArrayLength<u8>,
+        A: Algorithm,
+        L: ArrayLength<u8>, {
+      type Value = Step<D, S, A, R, L,>;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", STEP_FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let lock = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[0],),)?;
+        let private_key = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[1],),)?.into(),);
+        let root = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[2],),)?.into(),);
+        let current_public_key = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[3],),)?.into(),);
+        let chain_fingerprint = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[4],),)?;
+        let exporter = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[5],),)?.into(),);
+        let (start, end,) = seq.next_element::<(u32, u32,)>()?
+          .ok_or(Acc::Error::missing_field(STEP_FIELDS[6],),)?;
+
+        Ok(Step { lock, private_key, root, current_public_key, chain_fingerprint, exporter, skipped: start..end, })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(Step,), STEP_FIELDS.len(), StepVisitor(PhantomData,),)
+  }
+}
@@ -1,7 +1,7 @@
 //! Defines serde for Client.
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-21
+//! Last Moddified --- 2026-10-18
 
 use super::*;
 use ::serde::{
@@ -114,6 +114,18 @@ static FIELDS: &[&str] = &[
   "lock",
   "open",
   "private_key",
+  "local_identity",
+  "remote_identity",
+  "exporter",
+  "root",
+  "padding",
+  "expiry",
+  "retention",
+  "registry",
+  "transcript",
+  "delegated",
+  "franking",
+  "pending",
 ];
 
 impl<D, S, A, R, L,> Serialize for InnerClient<D, S, A, R, L,>
@@ -122,11 +134,35 @@ impl<D, S, A, R, L,> Serialize for InnerClient<D, S, A, R, L,>
     L: ArrayLength<u8>, {
   fn serialize<Ser,>(&self, serializer: Ser,) -> Result<Ser::Ok, Ser::Error>
     where Ser: Serializer, {
+    use ::serde::ser::Error;
+
+    //The seed of a Client which can still be demoted derives every chain of the session.
+    if self.standby.is_some() {
+      return Err(Ser::Error::custom("a Client cannot be serialised until it commits a ratchet step",))
+    }
+
     let mut serializer = serializer.serialize_tuple_struct(stringify!(Client,), FIELDS.len(),)?;
 
     serializer.serialize_field(&self.lock,)?;
     serializer.serialize_field(&self.open,)?;
     serializer.serialize_field(self.private_key.as_ref(),)?;
+    serializer.serialize_field(&self.local_identity,)?;
+    serializer.serialize_field(&self.remote_identity,)?;
+    serializer.serialize_field(self.exporter.as_ref(),)?;
+    serializer.serialize_field(self.root.as_ref(),)?;
+    serializer.serialize_field(&self.padding,)?;
+    serializer.serialize_field(&self.expiry,)?;
+    serializer.serialize_field(&self.retention,)?;
+    serializer.serialize_field(&self.registry.iter().collect::<Vec<_>>(),)?;
+    serializer.serialize_field(&self.transcript,)?;
+    serializer.serialize_field(&{
+      let mut delegated = self.delegated.iter().collect::<Vec<_>>();
+      delegated.sort();
+      delegated
+    },)?;
+    serializer.serialize_field(&self.franking,)?;
+    //A pending open is persisted so it can still be committed or aborted after a restart.
+    serializer.serialize_field(&self.pending,)?;
     serializer.end()
   }
 }
@@ -161,11 +197,48 @@ impl<'de, D, S: 'de, A, R, L,> Deserialize<'de> for Box<InnerClient<D, S, A, R,
           .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
         let mut private_key = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
           .ok_or(Acc::Error::missing_field(FIELDS[2],),)?.into(),);
+        let local_identity = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[3],),)?;
+        let remote_identity = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[4],),)?;
+        let mut exporter = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
+          .ok_or(Acc::Error::missing_field(FIELDS[5],),)?.into(),);
+        //Clients serialised before the root key was added took ratchet steps which the
+        //remote Client cannot open so they are refused.
+        let mut root = ClearOnDrop::new(seq.next_element::<[u8; 32]>()?
+          .ok_or(Acc::Error::missing_field(FIELDS[6],),)?.into(),);
+        //Clients serialised before padding was added do not pad messages.
+        let padding = seq.next_element()?.unwrap_or_default();
+        //Clients serialised before expiry was added keep messages and keys forever.
+        let expiry = seq.next_element()?.unwrap_or_default();
+        let retention = seq.next_element()?.unwrap_or_default();
+        //Clients serialised before extensions were added understand no extensions.
+        let registry = seq.next_element::<Vec<u16>>()?.unwrap_or_default();
+        //Clients serialised before transcripts were added start with an empty transcript.
+        let transcript = seq.next_element()?.unwrap_or_default();
+        //Clients serialised before delegation was added have delegated no keys.
+        let delegated = seq.next_element::<Vec<([u8; 32], u32,)>>()?.unwrap_or_default();
+        //Clients serialised before franking was added do not frank messages.
+        let franking = seq.next_element()?.unwrap_or_default();
+        //Clients serialised before pending opens were persisted have no pending open.
+        let pending = seq.next_element()?.unwrap_or_default();
         let mut client = Self::Value::default();
 
         mem::swap(&mut client.lock, &mut lock,);
         mem::swap(&mut client.open, &mut open,);
         mem::swap(&mut client.private_key, &mut private_key,);
+        client.local_identity = local_identity;
+        client.remote_identity = remote_identity;
+        mem::swap(&mut client.exporter, &mut exporter,);
+        mem::swap(&mut client.root, &mut root,);
+        client.padding = padding;
+        client.expiry = expiry;
+        client.retention = retention;
+        client.registry = registry.into_iter().collect();
+        client.transcript = transcript;
+        client.delegated = delegated.into_iter().collect();
+        client.franking = franking;
+        client.pending = pending;
 
         Ok(client)
       }
@@ -200,6 +273,8 @@ mod tests {
       let current_public_key = ClearOnDrop::new([1; 32].into(),);
       let current_keys = HashMap::new();
       let previous_keys = HashMap::new();
+      let chain_fingerprint = Some([5; 8]);
+      let retired = HashMap::new();
 
       OpenClient {
         ratchet,
@@ -207,10 +282,32 @@ mod tests {
         current_public_key,
         current_keys,
         previous_keys,
+        chain_fingerprint,
+        retired,
       }
     };
     let private_key = ClearOnDrop::new([2; 32].into(),);
-    let client = InnerClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,> { lock, open, private_key, };
+    let mut client = InnerClient::<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,> {
+      lock,
+      open,
+      private_key,
+      root: ClearOnDrop::new([12; 32].into(),),
+      local_identity: [3; 32],
+      remote_identity: [4; 32],
+      exporter: ClearOnDrop::new([6; 32].into(),),
+      padding: Padding::Padme,
+      franking: true,
+      opening: None,
+      expiry: Some(Duration::from_secs(7,),),
+      retention: Some(Duration::from_secs(8,),),
+      clock: Arc::new(SystemClock,),
+      observer: None,
+      registry: vec![9,].into_iter().collect(),
+      transcript: Checkpoint::default(),
+      delegated: vec![([10; 32], 11,),].into_iter().collect(),
+      pending: None,
+      standby: None,
+    };
     let mut serialised = [0u8; 2048];
     let serialised = {
       let writer = &mut serialised.as_mut();
@@ -239,5 +336,20 @@ mod tests {
     };
 
     assert!(serialised == other_serialised, "Client deserialised incorrectly",);
+
+    //Clients serialised before the root key was added are refused.
+    let old = serde_cbor::ser::to_vec_packed(&(
+      &client.lock,
+      &client.open,
+      client.private_key.as_ref(),
+      &client.local_identity,
+      &client.remote_identity,
+      client.exporter.as_ref(),
+    ),).expect("Error serialising an old Client");
+    assert!(serde_cbor::from_slice::<Box<InnerClient<Sha1, consts::U500, Aes256Gcm, consts::U1, consts::U100,>>>(&old,).is_err(), "Deserialised a Client without a root key",);
+
+    //A Client which can still be demoted keeps its seed and is not serialised.
+    client.standby = Some(ClearOnDrop::new([13; 32].into(),),);
+    assert!(serde_cbor::to_vec(&client,).is_err(), "Serialised the seed of a Client",);
   }
 }
\ No newline at end of file
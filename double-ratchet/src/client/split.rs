@@ -0,0 +1,265 @@
+//! Defines the [LockHalf] and [OpenHalf] of a split [Client].
+//! 
+//! A Client can only lock or open one message at a time; splitting it lets one thread
+//! send while another receives.
+//! The open half takes every ratchet step and hands the new locking state to the lock
+//! half over a channel once the step is committed.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::{aead::Algorithm, InnerClient, LockClient, Error,};
+use crate::{
+  ratchet::Ratchet,
+  message::{Message, Extensions,},
+  padding::Padding,
+  clock::Clock,
+  franking::Opening,
+  transcript::Accumulator,
+  generic_array::ArrayLength,
+};
+use rand::{RngCore, CryptoRng,};
+use std::{
+  mem,
+  time::Duration,
+  sync::{Arc, mpsc::{self, Sender, Receiver,},},
+};
+
+/// The sending half of a split Client.
+pub struct LockHalf<Digest, State, Algorithm, Rounds, AadLength,>
+  where State: 'static + ArrayLength<u8>,
+    Algorithm: super::aead::Algorithm,
+    AadLength: 'static + ArrayLength<u8>, {
+  /// The locking state.
+  lock: LockClient<Digest, State, Algorithm, Rounds, AadLength,>,
+  /// Receives the locking state of every ratchet step taken by the open half.
+  steps: Receiver<LockClient<Digest, State, Algorithm, Rounds, AadLength,>>,
+  /// The padding policy applied to locked messages.
+  padding: Padding,
+  /// The time after which locked messages expire.
+  expiry: Option<Duration>,
+  /// Indicates whether locked messages are franked.
+  franking: bool,
+  /// The source of the current time.
+  clock: Arc<dyn Clock + Send + Sync>,
+  /// The transcript of the messages sent.
+  sent: Accumulator,
+}
+
+impl<D, S, A, R, L,> LockHalf<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  /// Encrypts the passed message.
+  /// 
+  /// The buffer will be cleared if the message is encrypted successfully.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to encrypt.  
+  #[inline]
+  pub fn lock(&mut self, message: &mut [u8],) -> Result<Message, Error> {
+    self.lock_with_extensions(message, Extensions::new(),)
+  }
+  /// Encrypts the passed message sending the extensions with it.
+  /// 
+  /// The buffer will be cleared if the message is encrypted successfully.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to encrypt.  
+  /// extensions --- The extensions to authenticate and send with the message.  
+  #[inline]
+  pub fn lock_with_extensions(&mut self, message: &mut [u8], extensions: Extensions,) -> Result<Message, Error> {
+    self.lock_with_extensions_and_rng(message, extensions, &mut rand::thread_rng(),)
+  }
+  /// Encrypts the passed message sending the extensions with it using the passed source
+  /// of randomness.
+  /// 
+  /// The buffer will be cleared if the message is encrypted successfully.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to encrypt.  
+  /// extensions --- The extensions to authenticate and send with the message.  
+  /// rand --- The source of randomness used to generate franking keys.  
+  pub fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    //Move to the latest ratchet step.
+    if let Some(mut lock) = self.steps.try_iter().last() {
+      //Only the lock half knows how many messages were locked in the previous step.
+      lock.next_header.previous_step = self.lock.next_header.message_index;
+      self.lock = lock;
+    }
+
+    let expires = self.expiry.map(|expiry,| self.clock.now().saturating_add(expiry.as_secs(),),);
+    let message = self.lock.lock_franked(message, self.padding, expires, extensions, self.franking, rand,)?;
+
+    self.sent.push(&message.header,);
+    Ok(message)
+  }
+}
+
+impl<D, S, A, R, L,> LockHalf<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// The transcript of the messages sent.
+  #[inline]
+  pub fn sent(&self,) -> Accumulator { self.sent }
+}
+
+/// The receiving half of a split Client.
+pub struct OpenHalf<Digest, State, Algorithm, Rounds, AadLength,>
+  where State: 'static + ArrayLength<u8>,
+    Algorithm: super::aead::Algorithm,
+    AadLength: 'static + ArrayLength<u8>, {
+  /// The Client state used to open messages.
+  client: Box<InnerClient<Digest, State, Algorithm, Rounds, AadLength,>>,
+  /// Indicates whether this Client is the initiator of the communication.
+  local: bool,
+  /// The public key of the locking state last handed to the lock half.
+  public_key: [u8; 32],
+  /// Sends the locking state of every ratchet step to the lock half.
+  steps: Sender<LockClient<Digest, State, Algorithm, Rounds, AadLength,>>,
+}
+
+impl<D, S, A, R, L,> OpenHalf<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  /// Receives a message from the connected `Client`.
+  /// 
+  /// If the message is decrypted successfully the message data is appended to `buffer`.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  #[inline]
+  pub fn open<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
+    self.open_with_rng(message, buffer, &mut rand::thread_rng(),)
+  }
+  /// Receives a message from the connected `Client` using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn open_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    let data = self.open_pending_with_rng(message, buffer, rand,)?;
+
+    self.commit(); Ok(data)
+  }
+  /// Receives a message from the connected `Client` without consuming its key.
+  /// 
+  /// A ratchet step taken to open the message is only handed to the lock half once the
+  /// open is committed.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  #[inline]
+  pub fn open_pending<'a,>(&mut self, message: Message, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], (Message, Error,)> {
+    self.open_pending_with_rng(message, buffer, &mut rand::thread_rng(),)
+  }
+  /// Receives a message from the connected `Client` without consuming its key using the
+  /// passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The Message to decrypt.  
+  /// buffer --- The buffer to write the decrypted message too.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    let data = self.client.open_pending(message, buffer, self.local, rand,);
+
+    //Steps taken opening expired messages are committed immediately.
+    self.handoff();
+    data
+  }
+  /// Completes a pending open consuming the key of the opened message.
+  #[inline]
+  pub fn commit(&mut self,) { self.client.commit(); self.handoff() }
+  /// Cancels a pending open restoring the key of the opened message.
+  #[inline]
+  pub fn abort(&mut self,) { self.client.abort() }
+}
+
+impl<D, S, A, R, L,> OpenHalf<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// The transcript of the messages received.
+  #[inline]
+  pub fn received(&self,) -> Accumulator { self.client.transcript.received }
+  /// The franking [Opening] of the last message opened or `None` if it was not franked.
+  #[inline]
+  pub fn opening(&self,) -> Option<&Opening> { self.client.opening() }
+  /// Hands the locking state of a committed ratchet step to the lock half.
+  fn handoff(&mut self,) {
+    if self.client.pending.is_some()
+      || self.client.lock.next_header.public_key == self.public_key { return }
+
+    self.public_key = self.client.lock.next_header.public_key;
+    let lock = mem::replace(&mut self.client.lock, placeholder(self.public_key,),);
+    //The lock half may have been dropped in which case the step is discarded.
+    let _ = self.steps.send(lock,);
+  }
+}
+
+/// Splits a Client into its lock and open halves.
+/// 
+/// # Params
+/// 
+/// client --- The Client to split.  
+/// local --- Indicates whether this Client is the initiator of the communication.  
+pub(crate) fn split<D, S, A, R, L,>(mut client: Box<InnerClient<D, S, A, R, L,>>, local: bool,) -> (LockHalf<D, S, A, R, L,>, OpenHalf<D, S, A, R, L,>,)
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  //A pending open would be lost by its rollback.
+  client.abort();
+  //A split Client cannot be demoted.
+  client.standby = None;
+
+  let (sender, receiver,) = mpsc::channel();
+  let public_key = client.lock.next_header.public_key;
+  let lock = LockHalf {
+    lock: mem::replace(&mut client.lock, placeholder(public_key,),),
+    steps: receiver,
+    padding: client.padding,
+    expiry: client.expiry,
+    franking: client.franking,
+    clock: client.clock.clone(),
+    sent: client.transcript.sent,
+  };
+  let open = OpenHalf { client, local, public_key, steps: sender, };
+
+  (lock, open,)
+}
+
+/// The locking state left in the open half after it is handed to the lock half.
+/// 
+/// The placeholder keeps the public key so that rolling back a step to it is not
+/// mistaken for a new step.
+/// 
+/// # Params
+/// 
+/// public_key --- The public key of the locking state handed to the lock half.  
+fn placeholder<D, S, A, R, L,>(public_key: [u8; 32],) -> LockClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  let mut lock = LockClient::default();
+
+  lock.next_header.public_key = public_key;
+  lock
+}
@@ -0,0 +1,26 @@
+//! Defines the [Clock] trait used to expire messages.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use std::time::{SystemTime, UNIX_EPOCH,};
+
+/// A source of the current time.
+pub trait Clock {
+  /// The current time in seconds since the UNIX epoch.
+  fn now(&self,) -> u64;
+}
+
+/// A [Clock] which reads the system time.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default,)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  #[inline]
+  fn now(&self,) -> u64 {
+    //A system clock set before the epoch is treated as the epoch.
+    SystemTime::now().duration_since(UNIX_EPOCH,)
+      .map(|time,| time.as_secs(),)
+      .unwrap_or(0,)
+  }
+}
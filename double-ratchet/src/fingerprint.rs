@@ -0,0 +1,155 @@
+//! Defines the [SafetyNumber] type used to verify the identity of a partner Client.
+//! 
+//! A `SafetyNumber` is derived from the identity public keys of both Clients and is the
+//! same on both ends of a communication; users compare the displayed numbers out of band
+//! to confirm that they are talking to the expected partner.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{generic_array::GenericArray, typenum::consts::{U30, U60,},};
+use ring::{digest, constant_time,};
+use x25519_dalek::PublicKey;
+use std::fmt;
+
+/// The version of the fingerprint derivation.
+const VERSION: [u8; 2] = [0, 0];
+/// The number of hash iterations used to derive a fingerprint.
+const ITERATIONS: usize = 5200;
+/// The number of digits in a displayed chunk.
+const CHUNK_DIGITS: usize = 5;
+/// The number of bytes used to produce a displayed chunk.
+const CHUNK_BYTES: usize = 5;
+
+/// Derives the fingerprint of a single identity key.
+/// 
+/// # Params
+/// 
+/// key --- The identity public key to fingerprint.  
+fn fingerprint(key: &PublicKey,) -> GenericArray<u8, U30> {
+  let mut hash = {
+    let mut context = digest::Context::new(&digest::SHA512,);
+
+    context.update(&VERSION,);
+    context.update(key.as_bytes(),);
+    context.finish()
+  };
+
+  for _ in 0..ITERATIONS {
+    let mut context = digest::Context::new(&digest::SHA512,);
+
+    context.update(hash.as_ref(),);
+    context.update(key.as_bytes(),);
+    hash = context.finish();
+  }
+
+  GenericArray::clone_from_slice(&hash.as_ref()[..30],)
+}
+
+/// A displayable number identifying the pair of identity keys used by two Clients.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct SafetyNumber {
+  /// The combined fingerprints of both identity keys, lowest first.
+  fingerprint: GenericArray<u8, U60>,
+}
+
+impl SafetyNumber {
+  /// Derives the `SafetyNumber` for a pair of identity keys.
+  /// 
+  /// The order of the keys does not matter; both Clients derive the same value.
+  /// 
+  /// # Params
+  /// 
+  /// local --- The identity public key of this Client.  
+  /// remote --- The identity public key of the partner Client.  
+  pub fn new(local: &PublicKey, remote: &PublicKey,) -> Self {
+    let local = fingerprint(local,);
+    let remote = fingerprint(remote,);
+    let (first, second,) = if local <= remote { (local, remote,) } else { (remote, local,) };
+    let mut fingerprint = GenericArray::default();
+
+    fingerprint[..30].copy_from_slice(&first,);
+    fingerprint[30..].copy_from_slice(&second,);
+
+    Self { fingerprint, }
+  }
+  /// The raw bytes of the `SafetyNumber`.
+  #[inline]
+  pub fn as_bytes(&self,) -> &[u8] { &self.fingerprint }
+  /// Returns the `SafetyNumber` as groups of five decimal digits separated by spaces.
+  pub fn numeric(&self,) -> String {
+    self.fingerprint.chunks(CHUNK_BYTES,)
+      .map(|chunk,| {
+        let value = chunk.iter().fold(0u64, |value, &byte,| (value << 8) | byte as u64,);
+
+        format!("{:01$}", value % 100_000, CHUNK_DIGITS,)
+      },)
+      .collect::<Vec<_>>()
+      .join(" ",)
+  }
+  /// Returns the `SafetyNumber` as lowercase hexadecimal.
+  pub fn hex(&self,) -> String {
+    self.fingerprint.iter().map(|byte,| format!("{:02x}", byte,),).collect()
+  }
+  /// Compares two `SafetyNumber`s in constant time.
+  /// 
+  /// # Params
+  /// 
+  /// other --- The `SafetyNumber` to compare against.  
+  #[inline]
+  pub fn matches(&self, other: &Self,) -> bool {
+    constant_time::verify_slices_are_equal(&self.fingerprint, &other.fingerprint,).is_ok()
+  }
+  /// Compares the `SafetyNumber` against a number entered or scanned by a user.
+  /// 
+  /// Both the numeric and hexadecimal forms are accepted; whitespace is ignored.
+  /// 
+  /// # Params
+  /// 
+  /// displayed --- The displayed form of the partners `SafetyNumber`.  
+  pub fn matches_str(&self, displayed: &str,) -> bool {
+    let displayed = displayed.chars()
+      .filter(|c,| !c.is_whitespace(),)
+      .flat_map(char::to_lowercase,)
+      .collect::<String>();
+    let expected = if displayed.len() == self.fingerprint.len() * 2 { self.hex() }
+      else { self.numeric().split_whitespace().collect() };
+
+    constant_time::verify_slices_are_equal(expected.as_bytes(), displayed.as_bytes(),).is_ok()
+  }
+}
+
+impl fmt::Display for SafetyNumber {
+  #[inline]
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result { write!(fmt, "{}", self.numeric(),) }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use x25519_dalek::StaticSecret;
+
+  #[test]
+  fn test_safety_number() {
+    let key1 = PublicKey::from(&StaticSecret::from([1; 32],),);
+    let key2 = PublicKey::from(&StaticSecret::from([2; 32],),);
+    let key3 = PublicKey::from(&StaticSecret::from([3; 32],),);
+    let number = SafetyNumber::new(&key1, &key2,);
+
+    assert_eq!(number, SafetyNumber::new(&key2, &key1,), "SafetyNumber depends on key order",);
+    assert!(number.matches(&SafetyNumber::new(&key2, &key1,),), "SafetyNumbers failed to match",);
+    assert!(!number.matches(&SafetyNumber::new(&key1, &key3,),), "Different keys matched",);
+
+    let numeric = number.numeric();
+    assert_eq!(numeric.split_whitespace().count(), 12, "Bad numeric group count",);
+    assert!(numeric.split_whitespace().all(|group,| group.len() == 5 && group.chars().all(|c,| c.is_ascii_digit(),),),
+      "Bad numeric group",
+    );
+    assert_eq!(number.hex().len(), 120, "Bad hex length",);
+
+    assert!(number.matches_str(&numeric,), "Numeric form failed to match",);
+    assert!(number.matches_str(&numeric.replace(" ", "",),), "Unspaced numeric form failed to match",);
+    assert!(number.matches_str(&number.hex().to_uppercase(),), "Hex form failed to match",);
+    assert!(!number.matches_str(&SafetyNumber::new(&key1, &key3,).numeric(),), "Different number matched",);
+  }
+}
@@ -1,10 +1,12 @@
 //! Defines the `Framed` interface.
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-21
+//! Last Moddified --- 2026-10-18
 
 use crate::{message::Message, client::{self, Client,},};
 use clear_on_drop::ClearOnDrop;
+use rand::{RngCore, CryptoRng,};
+use serde::{Serialize, de::DeserializeOwned,};
 use std::{io::{self, Read, Write,}, task::Poll,};
 
 /// Removes `len` bytes from the front of buffer clearing the unused tail bytes.
@@ -32,7 +34,25 @@ fn consume_from_front(buffer: &mut Vec<u8>, len: usize,) {
 /// client --- The `Client` to open the message with.  
 /// input --- The input buffer to receive a message from.  
 /// buffer --- The output buffer to write the received message data out too.  
-pub fn receive_one<'a,>(mut client: impl Client, input: &mut Vec<u8>, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], Error> {
+#[inline]
+pub fn receive_one<'a,>(client: impl Client, input: &mut Vec<u8>, buffer: &'a mut Vec<u8>,) -> Result<&'a mut [u8], Error> {
+  receive_one_with_rng(client, input, buffer, &mut rand::thread_rng(),)
+}
+
+/// Receives a single message from the start of an input buffer which contains one or
+/// more encoded messages using the passed source of randomness.
+/// 
+/// If a message is received the data encoding the message is removed from the input
+/// buffer and the decrypted message data is appended to `buffer`.
+/// 
+/// # Params
+/// 
+/// client --- The `Client` to open the message with.  
+/// input --- The input buffer to receive a message from.  
+/// buffer --- The output buffer to write the received message data out too.  
+/// rand --- The source of randomness used to generate keys for a new ratchet step.  
+pub fn receive_one_with_rng<'a, Rand,>(mut client: impl Client, input: &mut Vec<u8>, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], Error>
+  where Rand: RngCore + CryptoRng, {
   use serde_cbor::de;
 
   //Deserialise a message.
@@ -60,7 +80,7 @@ pub fn receive_one<'a,>(mut client: impl Client, input: &mut Vec<u8>, buffer: &'
     Err(e) => return Err(Error::Deserialise(e,)),
   };
   
-  Ok(client.open(message, buffer,)?)
+  Ok(client.open_with_rng(message, buffer, rand,)?)
 }
 
 /// Wraps a `Client` and a Stream/Sink to parse messages to/from.
@@ -86,6 +106,20 @@ impl<Io, Client,> Framed<Io, Client,> {
   pub(super) const fn new(io: Io, client: Client,) -> Self {
     Self { io, client, buffer: Vec::new(), }
   }
+  /// The [Client] used to lock/open messages.
+  #[inline]
+  pub fn client(&self,) -> &Client { &self.client }
+  /// The [Client] used to lock/open messages.
+  #[inline]
+  pub fn client_mut(&mut self,) -> &mut Client { &mut self.client }
+  /// The Stream/Sink messages are sent/received on.
+  #[inline]
+  pub fn io(&self,) -> &Io { &self.io }
+  /// The Stream/Sink messages are sent/received on.
+  /// 
+  /// Reading or writing directly will corrupt the stream of messages.
+  #[inline]
+  pub fn io_mut(&mut self,) -> &mut Io { &mut self.io }
 }
 
 impl<I, C,> Framed<I, C,>
@@ -100,6 +134,19 @@ impl<I, C,> Framed<I, C,>
     use serde_cbor::ser;
     let message = self.client.lock(message,)?;
     
+    ser::to_writer_packed(&mut self.io, &message,)
+    .map_err(move |e,| Error::Send(message, e,),)
+  }
+  /// Attempts to serialise and send the passed value.
+  /// 
+  /// # Params
+  /// 
+  /// value --- The value to encrypt and send.  
+  pub fn send_value<T,>(&mut self, value: &T,) -> Result<(), Error>
+    where T: ?Sized + Serialize, {
+    use serde_cbor::ser;
+    let message = self.client.lock_value(value,)?;
+
     ser::to_writer_packed(&mut self.io, &message,)
     .map_err(move |e,| Error::Send(message, e,),)
   }
@@ -151,11 +198,26 @@ impl<I, C,> Framed<I, C,>
   /// # Params
   /// 
   /// buffer --- The buffer to append the decrypted message too.  
+  #[inline]
   pub fn recv<'a,>(&mut self, buffer: &'a mut Vec<u8>,) -> Result<Poll<Option<&'a mut [u8]>>, Error> {
+    self.recv_with_rng(buffer, &mut rand::thread_rng(),)
+  }
+  /// Attempts to receive the next message using the passed source of randomness.
+  /// 
+  /// If the inner IO object is non blocking this function will not block.
+  /// 
+  /// `Ready(None)` means EOF was encountered.
+  /// 
+  /// # Params
+  /// 
+  /// buffer --- The buffer to append the decrypted message too.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn recv_with_rng<'a, Rand,>(&mut self, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<Poll<Option<&'a mut [u8]>>, Error>
+    where Rand: RngCore + CryptoRng, {
     use std::io::ErrorKind;
 
     //Deserialise a message.
-    match receive_one(&mut self.client, &mut self.buffer, unsafe { &mut *(buffer as *mut _) },) {
+    match receive_one_with_rng(&mut self.client, &mut self.buffer, unsafe { &mut *(buffer as *mut _) }, rand,) {
       //Consume the used bytes.
       Ok(v) => Ok(Poll::Ready(Some(v))),
       //We are waiting on data.
@@ -177,7 +239,7 @@ impl<I, C,> Framed<I, C,>
         }
 
         //New data was read; recursively receive a message.
-        return self.recv(buffer,);
+        return self.recv_with_rng(buffer, rand,);
       },
       //Else return the error.
       Err(e) => Err(e),
@@ -185,6 +247,48 @@ impl<I, C,> Framed<I, C,>
   }
 }
 
+impl<I, C,> Framed<I, C,>
+  where I: Read,
+    C: Client, {
+  /// Attempts to receive the next value.
+  /// 
+  /// If the inner IO object is non blocking this function will not block.
+  /// 
+  /// `Ready(None)` means EOF was encountered.
+  #[inline]
+  pub fn recv_value<T,>(&mut self,) -> Result<Poll<Option<T>>, Error>
+    where T: DeserializeOwned, {
+    self.recv_value_with_rng(&mut rand::thread_rng(),)
+  }
+  /// Attempts to receive the next value using the passed source of randomness.
+  /// 
+  /// If the inner IO object is non blocking this function will not block.
+  /// 
+  /// `Ready(None)` means EOF was encountered.
+  /// 
+  /// # Params
+  /// 
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn recv_value_with_rng<T, Rand,>(&mut self, rand: &mut Rand,) -> Result<Poll<Option<T>>, Error>
+    where T: DeserializeOwned,
+      Rand: RngCore + CryptoRng, {
+    let mut buffer = Vec::new();
+    let res = match self.recv_with_rng(&mut buffer, rand,) {
+      Ok(Poll::Ready(Some(data))) => serde_cbor::from_slice(data,)
+        .map(|value,| Poll::Ready(Some(value)),)
+        .map_err(Error::Payload,),
+      Ok(Poll::Ready(None)) => Ok(Poll::Ready(None)),
+      Ok(Poll::Pending) => Ok(Poll::Pending),
+      Err(e) => Err(e),
+    };
+
+    //Clear the plaintext.
+    ClearOnDrop::new(buffer.as_mut_slice(),);
+
+    res
+  }
+}
+
 impl<I, C,> Framed<I, C,>
   where I: Read + Write,
     C: Client, {
@@ -266,6 +370,8 @@ pub enum Error {
   Open(Message, crate::client::Error,),
   /// There was an error deserialising a message.
   Deserialise(serde_cbor::error::Error,),
+  /// There was an error deserialising the value carried by an opened message.
+  Payload(serde_cbor::error::Error,),
 }
 
 impl From<io::Error> for Error {
@@ -345,6 +451,14 @@ mod tests {
       "Received third message corrupted",
     );
 
+    //Values.
+    let value = (String::from("value",), 4u64,);
+    local.send_value(&value,).expect("Error sending value");
+    let other = loop {
+      if let Poll::Ready(other) = remote.recv_value().expect("Error receiving value") { break other }
+    };
+    assert_eq!(other, Some(value), "Received value corrupted",);
+
     //Dropping remote closing the socket.
     std::mem::drop(remote,);
     assert_eq!(local.recv(&mut Vec::new(),).expect("Error on EOF"),
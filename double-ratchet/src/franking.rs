@@ -0,0 +1,178 @@
+//! Defines the message franking types used to report abusive messages.
+//! 
+//! A franking Client commits to the plaintext of each message it locks using a random
+//! franking key; the key travels inside the ciphertext and the commitment travels in the
+//! clear with the [Message](crate::message::Message).
+//! The server relaying the message stamps the commitment with a [Moderator] and the
+//! recipient can later reveal the plaintext and franking key in a [Report] which the
+//! moderator verifies without either Client giving up any other messages.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use ring::{digest, hmac,};
+
+mod serde;
+
+/// The length of a franking key.
+pub const KEY_LENGTH: usize = 32;
+
+/// Commits to the plaintext of a message using a franking key.
+/// 
+/// # Params
+/// 
+/// key --- The franking key of the message.  
+/// message --- The plaintext of the message.  
+pub fn commit(key: &[u8; KEY_LENGTH], message: &[u8],) -> [u8; 32] {
+  let key = hmac::SigningKey::new(&digest::SHA256, key,);
+  let mut commitment = [0; 32];
+
+  commitment.copy_from_slice(hmac::sign(&key, message,).as_ref(),);
+  commitment
+}
+
+/// The franking key and commitment of an opened message.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct Opening {
+  /// The franking key of the message.
+  key: [u8; KEY_LENGTH],
+  /// The commitment sent with the message.
+  commitment: [u8; 32],
+}
+
+impl Opening {
+  /// Constructs a new `Opening`.
+  #[inline]
+  pub(crate) fn new(key: [u8; KEY_LENGTH], commitment: [u8; 32],) -> Self {
+    Self { key, commitment, }
+  }
+  /// The franking key of the message.
+  #[inline]
+  pub fn key(&self,) -> &[u8; KEY_LENGTH] { &self.key }
+  /// The commitment sent with the message.
+  #[inline]
+  pub fn commitment(&self,) -> &[u8; 32] { &self.commitment }
+  /// Checks that `message` is the plaintext committed to.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The plaintext of the message.  
+  pub fn verify(&self, message: &[u8],) -> Result<(), Error> {
+    let key = hmac::SigningKey::new(&digest::SHA256, &self.key,);
+
+    hmac::verify_with_own_key(&key, message, &self.commitment,)
+      .map_err(|_,| Error::Opening,)
+  }
+  /// Reveals the plaintext of the message for a moderator.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The plaintext of the message.  
+  pub fn report(&self, message: &[u8],) -> Report {
+    Report { message: message.into(), opening: self.clone(), }
+  }
+}
+
+/// An abusive message revealed to a moderator.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct Report {
+  /// The plaintext of the message.
+  message: Box<[u8]>,
+  /// The franking key and commitment of the message.
+  opening: Opening,
+}
+
+impl Report {
+  /// The plaintext of the reported message.
+  #[inline]
+  pub fn message(&self,) -> &[u8] { &self.message }
+  /// The franking key and commitment of the reported message.
+  #[inline]
+  pub fn opening(&self,) -> &Opening { &self.opening }
+  /// Checks that the reported plaintext is the plaintext committed to.
+  #[inline]
+  pub fn verify(&self,) -> Result<(), Error> { self.opening.verify(&self.message,) }
+}
+
+/// Stamps the commitments of relayed messages and verifies reports of them.
+/// 
+/// The stamp binds a commitment to the `context` the message was relayed in, such as the
+/// identities of both Clients and the time, so that a report proves who sent the message.
+pub struct Moderator {
+  /// The secret key of the moderator.
+  key: hmac::SigningKey,
+}
+
+impl Moderator {
+  /// Constructs a new `Moderator`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The secret key of the moderator.  
+  #[inline]
+  pub fn new(key: &[u8],) -> Self {
+    Self { key: hmac::SigningKey::new(&digest::SHA256, key,), }
+  }
+  /// Stamps the commitment of a relayed message.
+  /// 
+  /// # Params
+  /// 
+  /// commitment --- The commitment sent with the message.  
+  /// context --- The context the message was relayed in.  
+  pub fn stamp(&self, commitment: &[u8; 32], context: &[u8],) -> [u8; 32] {
+    let mut signer = hmac::SigningContext::with_key(&self.key,);
+    let mut stamp = [0; 32];
+
+    signer.update(commitment,);
+    signer.update(context,);
+    stamp.copy_from_slice(signer.sign().as_ref(),);
+    stamp
+  }
+  /// Verifies a reported message.
+  /// 
+  /// # Params
+  /// 
+  /// report --- The reported message.  
+  /// context --- The context the message was relayed in.  
+  /// stamp --- The stamp given to the message when it was relayed.  
+  pub fn verify(&self, report: &Report, context: &[u8], stamp: &[u8; 32],) -> Result<(), Error> {
+    let data = report.opening.commitment.iter().chain(context,).copied().collect::<Vec<_>>();
+
+    hmac::verify_with_own_key(&self.key, &data, stamp,)
+      .map_err(|_,| Error::Stamp,)?;
+    report.verify()
+  }
+}
+
+/// An error returned when verifying a franked message.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Error {
+  /// The plaintext does not match the commitment.
+  Opening,
+  /// The stamp does not match the commitment and context.
+  Stamp,
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_franking() {
+    let message = b"abusive message";
+    let key = [1; KEY_LENGTH];
+    let opening = Opening::new(key, commit(&key, message,),);
+    let moderator = Moderator::new(&[2; 32],);
+    let stamp = moderator.stamp(opening.commitment(), b"context",);
+    let report = opening.report(message,);
+
+    assert_eq!(moderator.verify(&report, b"context", &stamp,), Ok(()), "Valid report rejected",);
+    assert_eq!(moderator.verify(&report, b"other", &stamp,), Err(Error::Stamp), "Wrong context accepted",);
+    assert_eq!(Moderator::new(&[3; 32],).verify(&report, b"context", &stamp,), Err(Error::Stamp),
+      "Wrong moderator accepted",
+    );
+
+    let forged = opening.report(b"innocent message",);
+    assert_eq!(moderator.verify(&forged, b"context", &stamp,), Err(Error::Opening), "Forged report accepted",);
+  }
+}
@@ -0,0 +1,76 @@
+//! Defines serde for Report.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+
+static FIELDS: &[&str] = &[
+  "message",
+  "key",
+  "commitment",
+];
+
+impl Serialize for Report {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(Report,), FIELDS.len(),)?;
+
+    serializer.serialize_field(&self.message,)?;
+    serializer.serialize_field(&self.opening.key,)?;
+    serializer.serialize_field(&self.opening.commitment,)?;
+    serializer.end()
+  }
+}
+
+impl<'de,> Deserialize<'de> for Report {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct ReportVisitor;
+
+    impl<'de,> Visitor<'de> for ReportVisitor {
+      type Value = Report;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let message = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+        let key = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+        let commitment = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[2],),)?;
+
+        Ok(Report { message, opening: Opening { key, commitment, }, })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(Report,), FIELDS.len(), ReportVisitor,)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_report_serde() {
+    let key = [1; KEY_LENGTH];
+    let report = Opening::new(key, commit(&key, &[2; 10],),).report(&[2; 10],);
+    let serialised = serde_cbor::to_vec(&report,)
+      .expect("Error serialising the Report");
+    let other = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the Report");
+
+    assert_eq!(report, other, "Report deserialised incorrectly",);
+  }
+}
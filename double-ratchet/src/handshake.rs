@@ -0,0 +1,883 @@
+//! Defines an authenticated handshake used to construct a [Framed] over a raw IO.
+//! 
+//! The handshake follows the `XX` and `IK` patterns of the
+//! [Noise Protocol Framework](https://noiseprotocol.org/noise.html) using
+//! `25519_ChaChaPoly_SHA256` and mutually authenticates the static keys of both Clients.
+//! Handshake messages are prefixed with their length as a big endian `u16`.
+//! Handshakes are built with [Handshake] and the [Mode] of this Client.
+//! The initiator of the handshake becomes the `Local` end of the [Session] and the
+//! responder becomes the `Remote` end; [Mode::Negotiate] picks the roles automatically.
+//! [Handshake::with_store] checks the static key of the remote Client against an
+//! [IdentityStore] as soon as it is authenticated and before the handshake completes.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  ratchet::Ratchet,
+  client::{LocalClient, RemoteClient, aead::Algorithm,},
+  framed::Framed,
+  session::Session,
+  identity::{self, IdentityStore, Trust,},
+  generic_array::{ArrayLength, GenericArray,},
+  typenum::consts::{U32, U64,},
+};
+use clear_on_drop::ClearOnDrop;
+use ring::{aead, digest, hmac,};
+use rand::{RngCore, CryptoRng,};
+use x25519_dalek::{PublicKey, StaticSecret,};
+use std::{hash::Hash, io::{self, Read, Write,},};
+
+/// The length of a key or hash.
+const LEN: usize = 32;
+/// The length of an authentication tag.
+const TAG_LEN: usize = 16;
+/// The maximum length of a handshake message.
+const MAX_MESSAGE_LEN: usize = 65535;
+
+/// A token of a handshake pattern.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+enum Token {
+  /// Send an ephemeral key.
+  E,
+  /// Send the static key.
+  S,
+  /// Mix the ephemeral-ephemeral shared secret.
+  EE,
+  /// Mix the initiator ephemeral-responder static shared secret.
+  ES,
+  /// Mix the initiator static-responder ephemeral shared secret.
+  SE,
+  /// Mix the static-static shared secret.
+  SS,
+}
+
+/// A handshake pattern.
+struct Pattern {
+  /// The name of the protocol.
+  name: &'static [u8],
+  /// Whether the static key of the responder is known before the handshake.
+  responder_known: bool,
+  /// The messages of the handshake starting with the initiator.
+  messages: &'static [&'static [Token]],
+}
+
+/// The `XX` pattern.
+static XX: Pattern = Pattern {
+  name: b"Noise_XX_25519_ChaChaPoly_SHA256",
+  responder_known: false,
+  messages: &[
+    &[Token::E,],
+    &[Token::E, Token::EE, Token::S, Token::ES,],
+    &[Token::S, Token::SE,],
+  ],
+};
+
+/// The `IK` pattern.
+static IK: Pattern = Pattern {
+  name: b"Noise_IK_25519_ChaChaPoly_SHA256",
+  responder_known: true,
+  messages: &[
+    &[Token::E, Token::ES, Token::S, Token::SS,],
+    &[Token::E, Token::EE, Token::SE,],
+  ],
+};
+
+/// Calculates `HMAC-SHA256(key, data...)`.
+/// 
+/// # Params
+/// 
+/// key --- The HMAC key.  
+/// data --- The data to authenticate.  
+fn hmac(key: &[u8], data: &[&[u8]],) -> [u8; LEN] {
+  let key = hmac::SigningKey::new(&digest::SHA256, key,);
+  let mut context = hmac::SigningContext::with_key(&key,);
+  let mut output = [0; LEN];
+
+  for data in data { context.update(data,); }
+  output.copy_from_slice(context.sign().as_ref(),);
+
+  output
+}
+
+/// The Noise `HKDF` function producing two outputs.
+/// 
+/// # Params
+/// 
+/// chaining_key --- The current chaining key.  
+/// input --- The input key material.  
+fn hkdf(chaining_key: &[u8], input: &[u8],) -> ([u8; LEN], [u8; LEN],) {
+  let mut temp = hmac(chaining_key, &[input,],);
+  let first = hmac(&temp, &[&[1],],);
+  let second = hmac(&temp, &[&first, &[2],],);
+
+  ClearOnDrop::new(temp.as_mut(),);
+
+  (first, second,)
+}
+
+/// The Noise `SymmetricState`.
+struct SymmetricState {
+  /// The chaining key.
+  chaining_key: ClearOnDrop<GenericArray<u8, U32>>,
+  /// The handshake hash.
+  hash: [u8; LEN],
+  /// The current cipher key.
+  key: Option<ClearOnDrop<GenericArray<u8, U32>>>,
+  /// The nonce of the next encryption.
+  nonce: u64,
+}
+
+impl SymmetricState {
+  /// Initialises the state for a protocol.
+  /// 
+  /// # Params
+  /// 
+  /// name --- The name of the protocol.  
+  fn new(name: &[u8],) -> Self {
+    let mut hash = [0; LEN];
+
+    if name.len() <= LEN { hash[..name.len()].copy_from_slice(name,); }
+    else { hash.copy_from_slice(digest::digest(&digest::SHA256, name,).as_ref(),); }
+
+    Self { chaining_key: ClearOnDrop::new(hash.into(),), hash, key: None, nonce: 0, }
+  }
+  /// Mixes data into the handshake hash.
+  /// 
+  /// # Params
+  /// 
+  /// data --- The data to mix.  
+  fn mix_hash(&mut self, data: &[u8],) {
+    let mut context = digest::Context::new(&digest::SHA256,);
+
+    context.update(&self.hash,);
+    context.update(data,);
+    self.hash.copy_from_slice(context.finish().as_ref(),);
+  }
+  /// Mixes key material into the chaining key and replaces the cipher key.
+  /// 
+  /// # Params
+  /// 
+  /// input --- The input key material.  
+  fn mix_key(&mut self, input: &[u8],) {
+    let (mut chaining_key, mut key,) = hkdf(&self.chaining_key, input,);
+
+    self.chaining_key = ClearOnDrop::new(chaining_key.into(),);
+    self.key = Some(ClearOnDrop::new(key.into(),));
+    self.nonce = 0;
+
+    ClearOnDrop::new(chaining_key.as_mut(),);
+    ClearOnDrop::new(key.as_mut(),);
+  }
+  /// The Noise nonce for the next encryption.
+  fn nonce(&mut self,) -> aead::Nonce {
+    let mut nonce = [0; 12];
+
+    nonce[4..].copy_from_slice(&self.nonce.to_le_bytes(),);
+    self.nonce += 1;
+
+    aead::Nonce::assume_unique_for_key(nonce,)
+  }
+  /// Encrypts `plaintext` if there is a key and mixes the result into the handshake hash.
+  /// 
+  /// # Params
+  /// 
+  /// plaintext --- The data to encrypt.  
+  /// output --- The buffer to append the ciphertext too.  
+  fn encrypt_and_hash(&mut self, plaintext: &[u8], output: &mut Vec<u8>,) -> Result<(), Error> {
+    let start = output.len();
+
+    output.extend_from_slice(plaintext,);
+    if let Some(key) = self.key.as_ref() {
+      let key = aead::SealingKey::new(&aead::CHACHA20_POLY1305, &key[..],)
+        .map_err(|_,| Error::Encryption,)?;
+      let nonce = self.nonce();
+
+      output.extend_from_slice(&[0; TAG_LEN],);
+      aead::seal_in_place(&key, nonce, aead::Aad::from(&self.hash,), &mut output[start..], TAG_LEN,)
+        .map_err(|_,| Error::Encryption,)?;
+    }
+
+    let ciphertext = output[start..].to_vec();
+    self.mix_hash(&ciphertext,);
+
+    Ok(())
+  }
+  /// Decrypts `ciphertext` if there is a key and mixes it into the handshake hash.
+  /// 
+  /// # Params
+  /// 
+  /// ciphertext --- The data to decrypt.  
+  fn decrypt_and_hash(&mut self, ciphertext: &[u8],) -> Result<Vec<u8>, Error> {
+    let mut plaintext = ciphertext.to_vec();
+
+    if let Some(key) = self.key.as_ref() {
+      let key = aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &key[..],)
+        .map_err(|_,| Error::Decryption,)?;
+      let nonce = self.nonce();
+      let len = aead::open_in_place(&key, nonce, aead::Aad::from(&self.hash,), 0, &mut plaintext,)
+        .map_err(|_,| Error::Decryption,)?
+        .len();
+
+      plaintext.truncate(len,);
+    }
+
+    self.mix_hash(ciphertext,);
+
+    Ok(plaintext)
+  }
+  /// Derives the seed of the session once the handshake is complete.
+  fn split(&self,) -> ClearOnDrop<GenericArray<u8, U64>> {
+    let (mut first, mut second,) = hkdf(&self.chaining_key, &[],);
+    let mut seed = ClearOnDrop::new(GenericArray::default(),);
+
+    seed[..LEN].copy_from_slice(&first,);
+    seed[LEN..].copy_from_slice(&second,);
+    ClearOnDrop::new(first.as_mut(),);
+    ClearOnDrop::new(second.as_mut(),);
+
+    seed
+  }
+}
+
+/// The Noise `HandshakeState`.
+struct HandshakeState<'a,> {
+  /// The symmetric state of the handshake.
+  symmetric: SymmetricState,
+  /// Whether this Client is the initiator.
+  initiator: bool,
+  /// The static key of this Client.
+  private_key: &'a StaticSecret,
+  /// The ephemeral key of this Client.
+  ephemeral: Option<StaticSecret>,
+  /// The static key of the remote Client.
+  remote: Option<PublicKey>,
+  /// The ephemeral key of the remote Client.
+  remote_ephemeral: Option<PublicKey>,
+}
+
+impl<'a,> HandshakeState<'a,> {
+  /// Calculates the shared secret of a DH token.
+  /// 
+  /// # Params
+  /// 
+  /// token --- The DH token.  
+  fn dh(&self, token: Token,) -> Result<[u8; LEN], Error> {
+    let (local, remote,) = match (token, self.initiator,) {
+      (Token::EE, _,) => (self.ephemeral.as_ref(), self.remote_ephemeral.as_ref(),),
+      (Token::ES, true,) | (Token::SE, false,) => (self.ephemeral.as_ref(), self.remote.as_ref(),),
+      (Token::ES, false,) | (Token::SE, true,) => (Some(self.private_key), self.remote_ephemeral.as_ref(),),
+      (Token::SS, _,) => (Some(self.private_key), self.remote.as_ref(),),
+      _ => (None, None,),
+    };
+
+    match (local, remote,) {
+      (Some(local), Some(remote),) => Ok(*local.diffie_hellman(remote,).as_bytes()),
+      _ => Err(Error::InvalidMessage),
+    }
+  }
+  /// Writes a handshake message.
+  /// 
+  /// # Params
+  /// 
+  /// tokens --- The tokens of the message.  
+  /// payload --- The payload of the message.  
+  /// rand --- The source of randomness used to generate the ephemeral key.  
+  fn write_message<Rand,>(&mut self, tokens: &[Token], payload: &[u8], rand: &mut Rand,) -> Result<Vec<u8>, Error>
+    where Rand: RngCore + CryptoRng, {
+    let mut message = Vec::new();
+
+    for &token in tokens {
+      match token {
+        Token::E => {
+          let ephemeral = StaticSecret::new(rand,);
+          let public_key = PublicKey::from(&ephemeral,);
+
+          message.extend_from_slice(public_key.as_bytes(),);
+          self.symmetric.mix_hash(public_key.as_bytes(),);
+          self.ephemeral = Some(ephemeral);
+        },
+        Token::S => {
+          let public_key = PublicKey::from(self.private_key,);
+
+          self.symmetric.encrypt_and_hash(public_key.as_bytes(), &mut message,)?;
+        },
+        _ => {
+          let mut shared = self.dh(token,)?;
+
+          self.symmetric.mix_key(&shared,);
+          ClearOnDrop::new(shared.as_mut(),);
+        },
+      }
+    }
+
+    self.symmetric.encrypt_and_hash(payload, &mut message,)?;
+
+    Ok(message)
+  }
+  /// Reads a handshake message and returns its payload.
+  /// 
+  /// # Params
+  /// 
+  /// tokens --- The tokens of the message.  
+  /// message --- The received message.  
+  fn read_message(&mut self, tokens: &[Token], mut message: &[u8],) -> Result<Vec<u8>, Error> {
+    /// Splits `len` bytes from the front of `message`.
+    fn take<'m,>(message: &mut &'m [u8], len: usize,) -> Result<&'m [u8], Error> {
+      if message.len() < len { return Err(Error::InvalidMessage) }
+
+      let (front, back,) = message.split_at(len,);
+      *message = back;
+
+      Ok(front)
+    }
+
+    for &token in tokens {
+      match token {
+        Token::E => {
+          let mut public_key = [0; LEN];
+
+          public_key.copy_from_slice(take(&mut message, LEN,)?,);
+          self.symmetric.mix_hash(&public_key,);
+          self.remote_ephemeral = Some(public_key.into());
+        },
+        Token::S => {
+          let len = if self.symmetric.key.is_some() { LEN + TAG_LEN } else { LEN };
+          let plaintext = self.symmetric.decrypt_and_hash(take(&mut message, len,)?,)?;
+          let mut public_key = [0; LEN];
+
+          public_key.copy_from_slice(&plaintext,);
+          self.remote = Some(public_key.into());
+        },
+        _ => {
+          let mut shared = self.dh(token,)?;
+
+          self.symmetric.mix_key(&shared,);
+          ClearOnDrop::new(shared.as_mut(),);
+        },
+      }
+    }
+
+    self.symmetric.decrypt_and_hash(message,)
+  }
+}
+
+/// Writes a length prefixed frame.
+/// 
+/// # Params
+/// 
+/// io --- The IO to write too.  
+/// data --- The frame to write.  
+fn write_frame<Io,>(io: &mut Io, data: &[u8],) -> Result<(), Error>
+  where Io: Write, {
+  if data.len() > MAX_MESSAGE_LEN { return Err(Error::InvalidMessage) }
+
+  io.write_all(&(data.len() as u16).to_be_bytes(),)?;
+  io.write_all(data,)?;
+  io.flush().map_err(Error::from,)
+}
+
+/// Reads a length prefixed frame.
+/// 
+/// # Params
+/// 
+/// io --- The IO to read from.  
+fn read_frame<Io,>(io: &mut Io,) -> Result<Vec<u8>, Error>
+  where Io: Read, {
+  let mut len = [0; 2];
+
+  io.read_exact(&mut len,)?;
+
+  let mut data = vec![0; u16::from_be_bytes(len,) as usize];
+  io.read_exact(&mut data,)?;
+
+  Ok(data)
+}
+
+/// Runs a handshake and constructs the resulting [Session].
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// pattern --- The handshake pattern.  
+/// initiator --- Whether this Client is the initiator.  
+/// prologue --- Data both Clients agree on before the handshake.  
+/// private_key --- The static key of this Client.  
+/// remote --- The static key of the responder if it is known.  
+/// verify --- Checks the static key of the remote Client once it is known.  
+/// rand --- The source of randomness used to generate keys.  
+fn handshake<Io, D, S, A, R, L, V, Rand,>(io: &mut Io, pattern: &Pattern, initiator: bool, prologue: &[u8], private_key: &StaticSecret, remote: Option<&PublicKey>, verify: V, rand: &mut Rand,) -> Result<Session<D, S, A, R, L,>, Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    V: FnOnce(&PublicKey,) -> Result<(), Error>,
+    Rand: RngCore + CryptoRng, {
+  /// Checks the static key of the remote Client if it is known and has not been checked.
+  fn check<V,>(remote: Option<&PublicKey>, verify: &mut Option<V>,) -> Result<(), Error>
+    where V: FnOnce(&PublicKey,) -> Result<(), Error>, {
+    match remote {
+      Some(remote) => verify.take().map_or(Ok(()), |verify,| verify(remote,),),
+      None => Ok(()),
+    }
+  }
+
+  let mut verify = Some(verify);
+  let mut state = HandshakeState {
+    symmetric: SymmetricState::new(pattern.name,),
+    initiator,
+    private_key,
+    ephemeral: None,
+    remote: remote.map(|remote,| remote.as_bytes().clone().into(),),
+    remote_ephemeral: None,
+  };
+
+  state.symmetric.mix_hash(prologue,);
+  //Mix the pre-message of the responder.
+  if pattern.responder_known {
+    let responder = if initiator { state.remote.as_ref().map(|key,| *key.as_bytes(),) }
+      else { Some(*PublicKey::from(private_key,).as_bytes()) };
+
+    match responder {
+      Some(responder) => state.symmetric.mix_hash(&responder,),
+      None => return Err(Error::UnknownRemote),
+    }
+  }
+
+  for (index, tokens,) in pattern.messages.iter().enumerate() {
+    //The remote static key is checked as soon as it is authenticated so that nothing more
+    //is sent to an untrusted Client.
+    check(state.remote.as_ref(), &mut verify,)?;
+    //The initiator writes the even messages.
+    if (index % 2 == 0) == initiator {
+      //Handshake messages carry an empty payload.
+      let message = state.write_message(tokens, &[], rand,)?;
+
+      write_frame(io, &message,)?;
+    } else {
+      let message = read_frame(io,)?;
+
+      if !state.read_message(tokens, &message,)?.is_empty() { return Err(Error::InvalidMessage) }
+    }
+  }
+
+  check(state.remote.as_ref(), &mut verify,)?;
+
+  let remote = state.remote.take().ok_or(Error::UnknownRemote,)?;
+  let mut seed = state.symmetric.split();
+
+  Ok(if initiator {
+    Session::Local(LocalClient::from_seed_with_rng(seed.as_mut_slice(), &remote, private_key, rand,),)
+  } else {
+    Session::Remote(RemoteClient::from_seed_with_rng(seed.as_mut_slice(), &remote, private_key, rand,),)
+  })
+}
+
+/// The pattern of a handshake and the role this Client takes in it.
+#[derive(Clone, Copy,)]
+pub enum Mode<'a,> {
+  /// Initiates an `XX` handshake.
+  /// 
+  /// Neither Client needs to know the static key of the other; the static key of the
+  /// remote Client is available from the resulting [Session].
+  InitiateXX,
+  /// Responds to an `XX` handshake.
+  RespondXX,
+  /// Initiates an `IK` handshake with a remote Client whose static key is known.
+  InitiateIK(&'a PublicKey,),
+  /// Responds to an `IK` handshake.
+  RespondIK,
+  /// Runs an `XX` handshake where both Clients may negotiate.
+  /// 
+  /// Both Clients exchange a random nonce and the Client with the greatest nonce becomes
+  /// the initiator; the nonces are bound into the handshake as its prologue.
+  Negotiate,
+}
+
+/// Builds a handshake over a raw IO.
+pub struct Handshake<'a, P = (),>
+  where P: Eq + Hash, {
+  /// The static key of this Client.
+  private_key: &'a StaticSecret,
+  /// The pattern and role of the handshake.
+  mode: Mode<'a,>,
+  /// The store to check the static key of the remote Client against and the peer
+  /// expected at the other end of the IO.
+  store: Option<(&'a mut IdentityStore<P,>, P,)>,
+}
+
+impl<'a,> Handshake<'a,> {
+  /// Starts building a handshake.
+  /// 
+  /// # Params
+  /// 
+  /// private_key --- The static key of this Client.  
+  /// mode --- The pattern of the handshake and the role of this Client.  
+  #[inline]
+  pub fn new(private_key: &'a StaticSecret, mode: Mode<'a,>,) -> Self {
+    Self { private_key, mode, store: None, }
+  }
+}
+
+impl<'a, P,> Handshake<'a, P,>
+  where P: Eq + Hash, {
+  /// Checks the static key of the remote Client against `store`.
+  /// 
+  /// The handshake is aborted with [Error::Identity] before it completes if the key does not
+  /// match the key recorded for `peer`.
+  /// 
+  /// # Params
+  /// 
+  /// store --- The recorded identities of known peers.  
+  /// peer --- The peer expected at the other end of the IO.  
+  #[inline]
+  pub fn with_store<Q,>(self, store: &'a mut IdentityStore<Q,>, peer: Q,) -> Handshake<'a, Q,>
+    where Q: Eq + Hash, {
+    Handshake { private_key: self.private_key, mode: self.mode, store: Some((store, peer,)), }
+  }
+  /// Runs the handshake and constructs a [Framed] around the resulting [Session].
+  /// 
+  /// The [Trust] of the remote Client is returned if a store was passed to `with_store`.
+  /// 
+  /// # Params
+  /// 
+  /// io --- The IO connected to the remote Client.  
+  #[inline]
+  pub fn run<Io, D, S, A, R, L,>(self, io: Io,) -> Result<(Framed<Io, Session<D, S, A, R, L,>>, Option<Trust>,), Error>
+    where Io: Read + Write,
+      S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng, {
+    self.run_with_rng(io, &mut rand::thread_rng(),)
+  }
+  /// Runs the handshake using the passed source of randomness and constructs a [Framed]
+  /// around the resulting [Session].
+  /// 
+  /// The [Trust] of the remote Client is returned if a store was passed to `with_store`.
+  /// 
+  /// # Params
+  /// 
+  /// io --- The IO connected to the remote Client.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn run_with_rng<Io, D, S, A, R, L, Rand,>(self, mut io: Io, rand: &mut Rand,) -> Result<(Framed<Io, Session<D, S, A, R, L,>>, Option<Trust>,), Error>
+    where Io: Read + Write,
+      S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      Rand: RngCore + CryptoRng, {
+    let Handshake { private_key, mode, mut store, } = self;
+    let (pattern, initiator, remote, prologue,) = match mode {
+      Mode::InitiateXX => (&XX, true, None, Vec::new(),),
+      Mode::RespondXX => (&XX, false, None, Vec::new(),),
+      Mode::InitiateIK(remote) => (&IK, true, Some(remote), Vec::new(),),
+      Mode::RespondIK => (&IK, false, None, Vec::new(),),
+      Mode::Negotiate => {
+        let (initiator, prologue,) = negotiate_roles(&mut io, rand,)?;
+
+        (&XX, initiator, None, prologue,)
+      },
+    };
+    let mut trust = None;
+    //Every handshake authenticates the remote static key before it completes so the
+    //store is always checked.
+    let session = handshake(&mut io, pattern, initiator, &prologue, private_key, remote, |remote: &PublicKey,| {
+      if let Some((store, peer,)) = store.take() { trust = Some(store.check(peer, remote,)?); }
+
+      Ok(())
+    }, rand,)?;
+
+    Ok((Framed::new(io, session,), trust,))
+  }
+}
+
+/// Exchanges random nonces to pick the initiator of a negotiated handshake.
+/// 
+/// Returns whether this Client is the initiator and the prologue of the handshake.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// rand --- The source of randomness used to generate the nonce.  
+fn negotiate_roles<Io, Rand,>(io: &mut Io, rand: &mut Rand,) -> Result<(bool, Vec<u8>,), Error>
+  where Io: Read + Write,
+    Rand: RngCore + CryptoRng, {
+  let mut nonce = [0; LEN];
+
+  rand.fill_bytes(&mut nonce,);
+  write_frame(io, &nonce,)?;
+
+  let remote_nonce = read_frame(io,)?;
+  if remote_nonce.len() != LEN { return Err(Error::InvalidMessage) }
+
+  let initiator = match nonce.as_ref().cmp(remote_nonce.as_slice(),) {
+    std::cmp::Ordering::Greater => true,
+    std::cmp::Ordering::Less => false,
+    //Equal nonces only happen if the remote Client echoed our nonce.
+    std::cmp::Ordering::Equal => return Err(Error::InvalidMessage),
+  };
+  let mut prologue = Vec::with_capacity(LEN * 2,);
+
+  if initiator { prologue.extend_from_slice(&nonce,); prologue.extend_from_slice(&remote_nonce,); }
+  else { prologue.extend_from_slice(&remote_nonce,); prologue.extend_from_slice(&nonce,); }
+
+  Ok((initiator, prologue,))
+}
+
+/// An error returned from a handshake.
+#[derive(Debug,)]
+pub enum Error {
+  /// There was an error interacting with the IO.
+  Io(io::Error,),
+  /// A handshake message was malformed.
+  InvalidMessage,
+  /// A handshake message could not be encrypted.
+  Encryption,
+  /// A handshake message failed authentication.
+  Decryption,
+  /// The static key of the remote Client is unknown.
+  UnknownRemote,
+  /// The static key of the remote Client did not match the [IdentityStore].
+  Identity(identity::Error,),
+}
+
+impl From<io::Error> for Error {
+  #[inline]
+  fn from(from: io::Error,) -> Self { Error::Io(from,) }
+}
+
+impl From<identity::Error> for Error {
+  #[inline]
+  fn from(from: identity::Error,) -> Self { Error::Identity(from,) }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{client::aead::Aes256Gcm, typenum::consts,};
+  use sha1::Sha1;
+  use std::{thread, task::Poll, net::{TcpListener, TcpStream,},};
+
+  type TestSession = Session<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>;
+
+  /// Produces the same bytes for every request; fixes the ephemeral keys of a handshake.
+  struct FixedRng([u8; LEN],);
+
+  impl RngCore for FixedRng {
+    fn next_u32(&mut self,) -> u32 { unimplemented!() }
+    fn next_u64(&mut self,) -> u64 { unimplemented!() }
+    fn fill_bytes(&mut self, dest: &mut [u8],) { dest.copy_from_slice(&self.0[..dest.len()],) }
+    fn try_fill_bytes(&mut self, dest: &mut [u8],) -> Result<(), rand::Error> { self.fill_bytes(dest,); Ok(()) }
+  }
+
+  impl CryptoRng for FixedRng {}
+
+  /// Decodes a hex string.
+  fn hex(hex: &str,) -> Vec<u8> {
+    (0..hex.len()).step_by(2,)
+      .map(|index,| u8::from_str_radix(&hex[index..index + 2], 16,).expect("Bad hex"),)
+      .collect()
+  }
+  /// Decodes a hex encoded key.
+  fn key(hex: &str,) -> [u8; LEN] {
+    let mut key = [0; LEN];
+
+    key.copy_from_slice(&self::hex(hex,),);
+    key
+  }
+
+  /// Runs a handshake between two Clients and checks that they can communicate.
+  fn run(
+    local: impl 'static + Send + FnOnce(TcpStream, StaticSecret,) -> Result<Framed<TcpStream, TestSession,>, Error>,
+    remote: impl 'static + Send + FnOnce(TcpStream, StaticSecret,) -> Result<Framed<TcpStream, TestSession,>, Error>,
+  ) -> (bool, bool,) {
+    let listener = TcpListener::bind("127.0.0.1:0",).expect("Error binding listener");
+    let address = listener.local_addr().expect("Error getting address");
+    let remote = thread::spawn(move || {
+      let (io, _,) = listener.accept().expect("Error accepting connection");
+      let mut framed = remote(io, StaticSecret::from([2; 32],),).expect("Error responding to the handshake");
+      let mut buffer = Vec::new();
+
+      match framed.recv(&mut buffer,).expect("Error receiving message") {
+        Poll::Ready(Some(_)) => {},
+        _ => panic!("No message received"),
+      }
+      assert_eq!(buffer, [1; 10].as_ref(), "Message corrupted",);
+      framed.send(&mut [2; 10],).expect("Error sending reply");
+      assert_eq!(framed.client().remote_identity().as_bytes(), PublicKey::from(&StaticSecret::from([1; 32],),).as_bytes(),
+        "Bad local identity",
+      );
+
+      framed.client().is_local()
+    },);
+    let io = TcpStream::connect(address,).expect("Error connecting");
+    let mut framed = local(io, StaticSecret::from([1; 32],),).expect("Error initiating the handshake");
+    let mut buffer = Vec::new();
+
+    framed.send(&mut [1; 10],).expect("Error sending message");
+    match framed.recv(&mut buffer,).expect("Error receiving reply") {
+      Poll::Ready(Some(_)) => {},
+      _ => panic!("No reply received"),
+    }
+    assert_eq!(buffer, [2; 10].as_ref(), "Reply corrupted",);
+    assert_eq!(framed.client().remote_identity().as_bytes(), PublicKey::from(&StaticSecret::from([2; 32],),).as_bytes(),
+      "Bad remote identity",
+    );
+
+    (framed.client().is_local(), remote.join().expect("Remote end panicked"),)
+  }
+
+  #[test]
+  fn test_handshake_xx() {
+    let roles = run(
+      |io, key,| Handshake::new(&key, Mode::InitiateXX,).run(io,).map(|(framed, _,),| framed,),
+      |io, key,| Handshake::new(&key, Mode::RespondXX,).run(io,).map(|(framed, _,),| framed,),
+    );
+
+    assert_eq!(roles, (true, false,), "Bad roles",);
+  }
+  #[test]
+  fn test_handshake_ik() {
+    let remote = PublicKey::from(&StaticSecret::from([2; 32],),);
+    let roles = run(
+      move |io, key,| Handshake::new(&key, Mode::InitiateIK(&remote,),).run(io,).map(|(framed, _,),| framed,),
+      |io, key,| Handshake::new(&key, Mode::RespondIK,).run(io,).map(|(framed, _,),| framed,),
+    );
+
+    assert_eq!(roles, (true, false,), "Bad roles",);
+  }
+  #[test]
+  fn test_handshake_negotiate() {
+    let (local, remote,) = run(
+      |io, key,| Handshake::new(&key, Mode::Negotiate,).run(io,).map(|(framed, _,),| framed,),
+      |io, key,| Handshake::new(&key, Mode::Negotiate,).run(io,).map(|(framed, _,),| framed,),
+    );
+
+    assert_ne!(local, remote, "Both ends took the same role",);
+  }
+  #[test]
+  fn test_handshake_wrong_key() {
+    let listener = TcpListener::bind("127.0.0.1:0",).expect("Error binding listener");
+    let address = listener.local_addr().expect("Error getting address");
+    let remote = thread::spawn(move || {
+      let (io, _,) = listener.accept().expect("Error accepting connection");
+
+      Handshake::new(&StaticSecret::from([2; 32],), Mode::RespondIK,)
+        .run::<_, Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(io,).is_err()
+    },);
+    let io = TcpStream::connect(address,).expect("Error connecting");
+    //The initiator expects the wrong static key.
+    let wrong = PublicKey::from(&StaticSecret::from([3; 32],),);
+    let local = Handshake::new(&StaticSecret::from([1; 32],), Mode::InitiateIK(&wrong,),)
+      .run::<_, Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(io,);
+
+    assert!(remote.join().expect("Remote end panicked"), "Responder accepted the wrong key",);
+    assert!(local.is_err(), "Initiator completed with the wrong key",);
+  }
+  #[test]
+  fn test_handshake_store() {
+    let roles = run(
+      |io, key,| {
+        let (framed, trust,) = Handshake::new(&key, Mode::InitiateXX,).with_store(&mut IdentityStore::new(), "remote",).run(io,)?;
+
+        assert_eq!(trust, Some(Trust::New), "Bad trust",);
+        Ok(framed)
+      },
+      |io, key,| Handshake::new(&key, Mode::RespondXX,).with_store(&mut IdentityStore::new(), "local",).run(io,)
+        .map(|(framed, _,),| framed,),
+    );
+
+    assert_eq!(roles, (true, false,), "Bad roles",);
+
+    let listener = TcpListener::bind("127.0.0.1:0",).expect("Error binding listener");
+    let address = listener.local_addr().expect("Error getting address");
+    let remote = thread::spawn(move || {
+      let (io, _,) = listener.accept().expect("Error accepting connection");
+
+      Handshake::new(&StaticSecret::from([2; 32],), Mode::RespondXX,)
+        .run::<_, Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(io,).is_err()
+    },);
+    let io = TcpStream::connect(address,).expect("Error connecting");
+    let mut store = IdentityStore::new();
+
+    //A different key was recorded for the responder.
+    store.check("remote", &PublicKey::from(&StaticSecret::from([3; 32],),),).expect("Error recording key");
+    let local_key = StaticSecret::from([1; 32],);
+    match Handshake::new(&local_key, Mode::InitiateXX,).with_store(&mut store, "remote",)
+      .run::<_, Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(io,) {
+      Err(Error::Identity(identity::Error::KeyChanged { .. })) => {},
+      _ => panic!("Completed the handshake with a changed key"),
+    }
+    //The initiator aborts before sending its static key.
+    assert!(remote.join().expect("Remote end panicked"), "Responder completed the handshake",);
+  }
+  #[test]
+  fn test_handshake_xx_vector() {
+    //The keys, prologue and payloads of the cacophony `Noise_XX_25519_ChaChaPoly_SHA256`
+    //test vector.
+    let initiator_static = StaticSecret::from(key("e61ef9919cde45dd5f82166404bd08e38bceb5dfdfded0a34c8df7ed542214d1",),);
+    let initiator_ephemeral = key("893e28b9dc6ca8d611ab664754b8ceb7bac5117349a4439a6b0569da977c464a",);
+    let responder_static = StaticSecret::from(key("4a3acbfdb163dec651dfa3194dece676d437029c62a408b4c5ea9114246e4893",),);
+    let responder_ephemeral = key("bbdb4cdbd309f1a1f2e1456967fe288cadd6f712d65dc7b7793d5e63da6b375b",);
+    let prologue = hex("4a6f686e2047616c74",);
+    let messages = [
+      (
+        "4c756477696720766f6e204d69736573",
+        "ca35def5ae56cec33dc2036731ab14896bc4c75dbb07a61f879f8e3afa4c79444c756477696720766f6e204d69736573",
+      ),
+      (
+        "4d757272617920526f746862617264",
+        concat!(
+          "95ebc60d2b1fa672c1f46a8aa265ef51bfe38e7ccb39ec5be34069f14480884381cbad1f276e038c48378ffce2b65285",
+          "e08d6b68aaa3629a5a8639392490e5b9bd5269c2f1e4f488ed8831161f19b7815528f8982ffe09be9b5c412f8a0db50f",
+          "8814c7194e83f23dbd8d162c9326ad",
+        ),
+      ),
+      (
+        "462e20412e20486179656b",
+        concat!(
+          "c7195ffacac1307ff99046f219750fc47693e23c3cb08b89c2af808b444850a80ae475b9df0f169ae80a89be0865b57f",
+          "58c9fea0d4ec82a286427402f113e4b6ae769a1d95941d49b25030",
+        ),
+      ),
+    ];
+    let mut initiator = HandshakeState {
+      symmetric: SymmetricState::new(XX.name,),
+      initiator: true,
+      private_key: &initiator_static,
+      ephemeral: None,
+      remote: None,
+      remote_ephemeral: None,
+    };
+    let mut responder = HandshakeState {
+      symmetric: SymmetricState::new(XX.name,),
+      initiator: false,
+      private_key: &responder_static,
+      ephemeral: None,
+      remote: None,
+      remote_ephemeral: None,
+    };
+
+    initiator.symmetric.mix_hash(&prologue,);
+    responder.symmetric.mix_hash(&prologue,);
+    for (index, (&(payload, expected,), tokens,),) in messages.iter().zip(XX.messages,).enumerate() {
+      let (writer, reader, ephemeral,) = if index % 2 == 0 { (&mut initiator, &mut responder, initiator_ephemeral,) }
+        else { (&mut responder, &mut initiator, responder_ephemeral,) };
+      let message = writer.write_message(tokens, &hex(payload,), &mut FixedRng(ephemeral,),)
+        .expect("Error writing handshake message");
+
+      assert_eq!(message, hex(expected,), "Bad handshake message",);
+      assert_eq!(reader.read_message(tokens, &message,).expect("Error reading handshake message"), hex(payload,),
+        "Bad handshake payload",
+      );
+    }
+
+    assert_eq!(&initiator.symmetric.hash[..], hex("c8e5f64e846193be2a834104c2a009868d6c9f3bd3c186299888b488b2f1f58e",).as_slice(),
+      "Bad handshake hash",
+    );
+    assert_eq!(responder.symmetric.hash, initiator.symmetric.hash, "Handshake hashes differ",);
+    assert_eq!(initiator.symmetric.split().as_slice(), responder.symmetric.split().as_slice(), "Split keys differ",);
+  }
+}
@@ -0,0 +1,325 @@
+//! Defines the [IdentityStore] used to detect changes in the identity keys of partners.
+//! 
+//! The store records the first identity key seen for each peer (trust on first use) and
+//! reports an [Error::KeyChanged] whenever a different key is presented afterwards.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  ratchet::Ratchet,
+  client::{LocalClient, RemoteClient, aead::Algorithm,},
+  session::Session,
+  generic_array::ArrayLength,
+};
+use rand::{RngCore, CryptoRng,};
+use x25519_dalek::{PublicKey, StaticSecret,};
+use std::{hash::Hash, collections::HashMap,};
+
+mod serde;
+
+/// A recorded identity key of a peer.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub struct Identity {
+  /// The identity public key of the peer.
+  key: [u8; 32],
+  /// Whether the key has been verified by the user.
+  verified: bool,
+}
+
+impl Identity {
+  /// The identity public key of the peer.
+  #[inline]
+  pub fn key(&self,) -> PublicKey { self.key.into() }
+  /// Whether the key has been verified by the user.
+  #[inline]
+  pub const fn is_verified(&self,) -> bool { self.verified }
+}
+
+/// The trust level of an identity key which matched the store.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Trust {
+  /// The key was seen for the first time and has been recorded.
+  New,
+  /// The key matches the recorded key.
+  Known,
+  /// The key matches the recorded key and was verified by the user.
+  Verified,
+}
+
+/// Records the first seen identity key of each peer.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct IdentityStore<Peer,>
+  where Peer: Eq + Hash, {
+  /// The recorded identities.
+  identities: HashMap<Peer, Identity>,
+}
+
+impl<P,> IdentityStore<P,>
+  where P: Eq + Hash, {
+  /// Constructs an empty `IdentityStore`.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+  /// Returns the recorded identity of `peer`.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to get the identity of.  
+  #[inline]
+  pub fn get(&self, peer: &P,) -> Option<&Identity> { self.identities.get(peer,) }
+  /// Checks `key` against the recorded identity of `peer`.
+  /// 
+  /// If there is no recorded identity `key` is recorded.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer presenting the key.  
+  /// key --- The identity public key presented by the peer.  
+  pub fn check(&mut self, peer: P, key: &PublicKey,) -> Result<Trust, Error> {
+    use std::collections::hash_map::Entry;
+
+    match self.identities.entry(peer,) {
+      Entry::Occupied(entry) => {
+        let identity = entry.get();
+
+        if &identity.key != key.as_bytes() {
+          Err(Error::KeyChanged { previous: identity.key, current: *key.as_bytes(), verified: identity.verified, })
+        } else if identity.verified { Ok(Trust::Verified) }
+        else { Ok(Trust::Known) }
+      },
+      Entry::Vacant(entry) => {
+        entry.insert(Identity { key: *key.as_bytes(), verified: false, },);
+
+        Ok(Trust::New)
+      },
+    }
+  }
+  /// Marks the recorded identity of `peer` as verified.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer which was verified.  
+  /// key --- The identity public key which was verified.  
+  pub fn mark_verified(&mut self, peer: &P, key: &PublicKey,) -> Result<(), Error> {
+    let identity = self.identities.get_mut(peer,).ok_or(Error::UnknownPeer,)?;
+
+    if &identity.key != key.as_bytes() {
+      return Err(Error::KeyChanged { previous: identity.key, current: *key.as_bytes(), verified: identity.verified, })
+    }
+
+    identity.verified = true;
+
+    Ok(())
+  }
+  /// Replaces the recorded identity of `peer` with `key` after a key change was accepted.
+  /// 
+  /// The new key is not verified. The previous identity is returned.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer which changed keys.  
+  /// key --- The new identity public key of the peer.  
+  #[inline]
+  pub fn accept_change(&mut self, peer: P, key: &PublicKey,) -> Option<Identity> {
+    self.identities.insert(peer, Identity { key: *key.as_bytes(), verified: false, },)
+  }
+  /// Forgets the recorded identity of `peer`.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to forget.  
+  #[inline]
+  pub fn remove(&mut self, peer: &P,) -> Option<Identity> { self.identities.remove(peer,) }
+  /// Checks the identity of `peer` and initiates communication with it.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to connect to.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  #[inline]
+  pub fn connect<D, S, A, R, L,>(&mut self, peer: P, remote: &PublicKey, private_key: &StaticSecret,) -> Result<(LocalClient<D, S, A, R, L,>, Trust,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng, {
+    self.connect_with_rng(peer, remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Checks the identity of `peer` and initiates communication with it using the passed
+  /// source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to connect to.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn connect_with_rng<D, S, A, R, L, Rand,>(&mut self, peer: P, remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Result<(LocalClient<D, S, A, R, L,>, Trust,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      Rand: RngCore + CryptoRng, {
+    let trust = self.check(peer, remote,)?;
+
+    Ok((LocalClient::connect_with_rng(remote, private_key, rand,), trust,))
+  }
+  /// Checks the identity of `peer` and accepts communication from it.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to accept.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  #[inline]
+  pub fn accept<D, S, A, R, L,>(&mut self, peer: P, remote: &PublicKey, private_key: &StaticSecret,) -> Result<(RemoteClient<D, S, A, R, L,>, Trust,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng, {
+    self.accept_with_rng(peer, remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Checks the identity of `peer` and accepts communication from it using the passed
+  /// source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to accept.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn accept_with_rng<D, S, A, R, L, Rand,>(&mut self, peer: P, remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Result<(RemoteClient<D, S, A, R, L,>, Trust,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      Rand: RngCore + CryptoRng, {
+    let trust = self.check(peer, remote,)?;
+
+    Ok((RemoteClient::accept_with_rng(remote, private_key, rand,), trust,))
+  }
+  /// Checks the identity of `peer` and establishes communication with it, see
+  /// [Session::establish].
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to communicate with.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  #[inline]
+  pub fn establish<D, S, A, R, L,>(&mut self, peer: P, remote: &PublicKey, private_key: &StaticSecret,) -> Result<(Session<D, S, A, R, L,>, Trust,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng, {
+    self.establish_with_rng(peer, remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Checks the identity of `peer` and establishes communication with it using the
+  /// passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// peer --- The peer to communicate with.  
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn establish_with_rng<D, S, A, R, L, Rand,>(&mut self, peer: P, remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Result<(Session<D, S, A, R, L,>, Trust,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      Rand: RngCore + CryptoRng, {
+    //The only error establishing a `Session` is a peer presenting our own key, which must
+    //not be recorded.
+    let session = Session::establish_with_rng(remote, private_key, rand,).map_err(|_,| Error::SameIdentity,)?;
+    let trust = self.check(peer, remote,)?;
+
+    Ok((session, trust,))
+  }
+}
+
+impl<P,> Default for IdentityStore<P,>
+  where P: Eq + Hash, {
+  #[inline]
+  fn default() -> Self { Self { identities: HashMap::new(), } }
+}
+
+/// An error returned from checking an identity.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Error {
+  /// The peer presented a different identity key to the recorded key.
+  KeyChanged {
+    /// The recorded identity key.
+    previous: [u8; 32],
+    /// The presented identity key.
+    current: [u8; 32],
+    /// Whether the recorded identity key was verified.
+    verified: bool,
+  },
+  /// There is no recorded identity for the peer.
+  UnknownPeer,
+  /// The peer presented the identity key of this Client.
+  SameIdentity,
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{client::{Client, aead,}, typenum::consts,};
+  use sha1::Sha1;
+
+  #[test]
+  fn test_identity_store() {
+    let key1 = PublicKey::from(&StaticSecret::from([1; 32],),);
+    let key2 = PublicKey::from(&StaticSecret::from([2; 32],),);
+    let mut store = IdentityStore::new();
+
+    assert_eq!(store.check("peer", &key1,), Ok(Trust::New), "First key not recorded",);
+    assert_eq!(store.check("peer", &key1,), Ok(Trust::Known), "Recorded key not known",);
+    assert_eq!(store.check("peer", &key2,),
+      Err(Error::KeyChanged { previous: *key1.as_bytes(), current: *key2.as_bytes(), verified: false, }),
+      "Key change not detected",
+    );
+    assert_eq!(store.get(&"peer",).map(Identity::key,).map(|key,| *key.as_bytes(),), Some(*key1.as_bytes()),
+      "Key change replaced the recorded key",
+    );
+
+    store.mark_verified(&"peer", &key2,).expect_err("Verified the wrong key");
+    store.mark_verified(&"peer", &key1,).expect("Error verifying key");
+    assert_eq!(store.check("peer", &key1,), Ok(Trust::Verified), "Key not verified",);
+    assert_eq!(store.mark_verified(&"other", &key1,), Err(Error::UnknownPeer), "Verified an unknown peer",);
+
+    let previous = store.accept_change("peer", &key2,).expect("No previous identity");
+    assert!(previous.is_verified(), "Previous identity lost verification",);
+    assert_eq!(store.check("peer", &key2,), Ok(Trust::Known), "Accepted key not known",);
+  }
+  #[test]
+  fn test_identity_store_connect() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let other_sec = StaticSecret::from([3; 32],);
+    let mut local_store = IdentityStore::new();
+    let mut remote_store = IdentityStore::new();
+    let (mut local, trust,) = local_store.connect::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U0,>("remote", &(&remote_sec).into(), &local_sec,)
+      .expect("Error connecting");
+    assert_eq!(trust, Trust::New, "First connection was trusted",);
+    let (mut remote, _,) = remote_store.accept::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U0,>("local", &(&local_sec).into(), &remote_sec,)
+      .expect("Error accepting");
+
+    let message = local.lock(&mut [1; 10],).expect("Error locking message");
+    remote.open(message, &mut Vec::new(),).expect("Error opening message");
+
+    match local_store.connect::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U0,>("remote", &(&other_sec).into(), &local_sec,) {
+      Err(Error::KeyChanged { .. }) => {},
+      _ => panic!("Connected to a changed identity"),
+    }
+    match local_store.establish::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U0,>("remote", &(&other_sec).into(), &local_sec,) {
+      Err(Error::KeyChanged { .. }) => {},
+      _ => panic!("Established a Session with a changed identity"),
+    }
+    match local_store.establish::<Sha1, consts::U64, aead::Aes256Gcm, consts::U1, consts::U0,>("self", &(&local_sec).into(), &local_sec,) {
+      Err(Error::SameIdentity) => assert!(local_store.get(&"self",).is_none(), "Recorded the own identity",),
+      _ => panic!("Established a Session with its own identity"),
+    }
+  }
+}
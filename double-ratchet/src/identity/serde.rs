@@ -0,0 +1,96 @@
+//! Defines serde for the IdentityStore.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+
+static FIELDS: &[&str] = &[
+  "key",
+  "verified",
+];
+
+impl Serialize for Identity {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(Identity,), FIELDS.len(),)?;
+
+    serializer.serialize_field(&self.key,)?;
+    serializer.serialize_field(&self.verified,)?;
+    serializer.end()
+  }
+}
+
+impl<'de,> Deserialize<'de> for Identity {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct IdentityVisitor;
+
+    impl<'de,> Visitor<'de> for IdentityVisitor {
+      type Value = Identity;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let key = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+        let verified = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+
+        Ok(Identity { key, verified, })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(Identity,), FIELDS.len(), IdentityVisitor,)
+  }
+}
+
+impl<P,> Serialize for IdentityStore<P,>
+  where P: Eq + Hash + Serialize, {
+  #[inline]
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    self.identities.serialize(serializer,)
+  }
+}
+
+impl<'de, P,> Deserialize<'de> for IdentityStore<P,>
+  where P: Eq + Hash + Deserialize<'de>, {
+  #[inline]
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    HashMap::deserialize(deserializer,).map(|identities,| IdentityStore { identities, },)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_identity_store_serde() {
+    let key1 = PublicKey::from(&StaticSecret::from([1; 32],),);
+    let key2 = PublicKey::from(&StaticSecret::from([2; 32],),);
+    let mut store = IdentityStore::new();
+
+    store.check(1u32, &key1,).expect("Error recording first key");
+    store.check(2u32, &key2,).expect("Error recording second key");
+    store.mark_verified(&2, &key2,).expect("Error verifying key");
+
+    let serialised = serde_cbor::ser::to_vec_packed(&store,)
+      .expect("Error serialising the IdentityStore");
+    let other: IdentityStore<u32,> = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the IdentityStore");
+
+    assert_eq!(store, other, "IdentityStore deserialised incorrectly",);
+  }
+}
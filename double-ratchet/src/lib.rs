@@ -29,7 +29,7 @@
 //! ```
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-21
+//! Last Moddified --- 2026-10-18
 
 #![deny(missing_docs,)]
 #![feature(const_fn, maybe_uninit, maybe_uninit_ref, bind_by_move_pattern_guards, const_vec_new,)]
@@ -39,7 +39,24 @@ pub use ratchet::{digest, typenum, generic_array,};
 pub use x25519_dalek;
 
 pub mod message;
+#[macro_use]
 pub mod client;
 pub mod framed;
+pub mod fingerprint;
+pub mod identity;
+pub mod xeddsa;
+pub mod reset;
+pub mod session;
+pub mod pake;
+pub mod handshake;
+pub mod attachment;
+pub mod padding;
+pub mod clock;
+pub mod transcript;
+pub mod receipts;
+pub mod reorder;
+pub mod franking;
+pub mod observer;
+pub mod shared;
 
-pub use self::client::{Client, LocalClient, RemoteClient,};
+pub use self::{client::{Client, LocalClient, RemoteClient,}, session::Session, shared::SharedClient,};
@@ -1,9 +1,15 @@
 //! Defines `Message` types.
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-12
+//! Last Moddified --- 2026-10-18
 
 mod serde;
+mod extension;
+
+pub use self::extension::*;
+
+/// Tags the extensions in the authenticated data of a message.
+const EXTENSIONS_TAG: u8 = 0x02;
 
 /// A `Message` is a message [Header] and associated data.
 #[derive(PartialEq, Eq, Clone, Debug,)]
@@ -12,9 +18,34 @@ pub struct Message {
   pub header: Header,
   /// The `Message` data.
   pub data: Box<[u8]>,
+  /// The [Extensions] sent with the `Message`.
+  /// 
+  /// The extensions are authenticated with the message data; every field added to the
+  /// protocol, such as [Expires], is carried as an extension so the [Header] never changes.
+  pub extensions: Extensions,
+}
+
+impl Message {
+  /// The time in seconds since the UNIX epoch after which the message cannot be opened.
+  #[inline]
+  pub fn expires(&self,) -> Option<u64> { self.extensions.value::<Expires>().map(|Expires(expires,),| expires,) }
+  /// Identifies the ratchet chain the message was locked with.
+  #[inline]
+  pub fn chain_fingerprint(&self,) -> Option<[u8; 8]> {
+    self.extensions.value::<ChainFingerprint>().map(|ChainFingerprint(fingerprint,),| fingerprint,)
+  }
+  /// The [franking](crate::franking) commitment to the plaintext of the message.
+  #[inline]
+  pub fn franking(&self,) -> Option<[u8; 32]> { self.extensions.value::<Franking>().map(|Franking(commitment,),| commitment,) }
+  /// The extensions authenticated with the message data.
+  #[inline]
+  pub(crate) fn authenticated_data(&self,) -> Vec<u8> { self.extensions.authenticated_data() }
 }
 
 /// The headers tagged with a message.
+/// 
+/// The header only identifies the key of a message; everything else is sent as an
+/// [Extension].
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Default,)]
 pub struct Header {
   /// The `PublicKey` of the communication partner.
@@ -24,3 +55,69 @@ pub struct Header {
   /// The number of messages in the previous step.
   pub previous_step: u32,
 }
+
+/// The time in seconds since the UNIX epoch after which a message cannot be opened.
+/// 
+/// A Client which does not understand expiry must refuse the message so the extension
+/// is critical.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub struct Expires(pub u64,);
+
+impl ExtensionType for Expires {
+  const KIND: u16 = 0xff01;
+  const CRITICAL: bool = true;
+
+  #[inline]
+  fn encode(&self,) -> Box<[u8]> { self.0.to_be_bytes().as_ref().into() }
+  fn decode(value: &[u8],) -> Option<Self> {
+    if value.len() != 8 { return None }
+
+    let mut expires = [0; 8];
+    expires.copy_from_slice(value,);
+    Some(Expires(u64::from_be_bytes(expires,),))
+  }
+}
+
+/// Identifies the ratchet chain a message was locked with.
+/// 
+/// Both Clients derive the same fingerprint for a ratchet step so a mismatch indicates
+/// that the Clients have diverged.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub struct ChainFingerprint(pub [u8; 8],);
+
+impl ExtensionType for ChainFingerprint {
+  const KIND: u16 = 0xff02;
+  const CRITICAL: bool = false;
+
+  #[inline]
+  fn encode(&self,) -> Box<[u8]> { self.0.as_ref().into() }
+  fn decode(value: &[u8],) -> Option<Self> {
+    if value.len() != 8 { return None }
+
+    let mut fingerprint = [0; 8];
+    fingerprint.copy_from_slice(value,);
+    Some(ChainFingerprint(fingerprint,))
+  }
+}
+
+/// The [franking](crate::franking) commitment to the plaintext of a message.
+/// 
+/// The franking key is encrypted with the message data so a Client which does not
+/// understand franking must refuse the message.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub struct Franking(pub [u8; 32],);
+
+impl ExtensionType for Franking {
+  const KIND: u16 = 0xff03;
+  const CRITICAL: bool = true;
+
+  #[inline]
+  fn encode(&self,) -> Box<[u8]> { self.0.as_ref().into() }
+  fn decode(value: &[u8],) -> Option<Self> {
+    if value.len() != 32 { return None }
+
+    let mut commitment = [0; 32];
+    commitment.copy_from_slice(value,);
+    Some(Franking(commitment,))
+  }
+}
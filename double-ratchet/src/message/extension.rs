@@ -0,0 +1,297 @@
+//! Defines the typed extension fields carried with a [Message].
+//! 
+//! Extensions are encoded as type-length-value records and authenticated with the
+//! message data; they can only be trusted once the message has been opened.
+//! A Client refuses to open a message carrying a critical extension which has not been
+//! registered in its [Registry] while unknown non-critical extensions are ignored.
+//! 
+//! Type identifiers from `0xff00` are reserved for the extensions defined by this crate,
+//! such as [Expires](super::Expires), which every Client understands.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::{Expires, ChainFingerprint, Franking,};
+use std::collections::BTreeSet;
+
+/// The flag marking an extension as critical.
+const CRITICAL_FLAG: u8 = 0x01;
+/// The length of the type, flags and length fields of an encoded extension.
+const RECORD_HEADER_LENGTH: usize = 7;
+/// The type identifiers of the extensions defined by this crate.
+const BUILTIN_KINDS: &[u16] = &[Expires::KIND, ChainFingerprint::KIND, Franking::KIND,];
+
+/// An application defined extension type.
+pub trait ExtensionType: Sized {
+  /// The type identifier of the extension.
+  const KIND: u16;
+  /// Whether Clients which do not understand the extension must refuse the message.
+  const CRITICAL: bool;
+
+  /// Encodes the extension value.
+  fn encode(&self,) -> Box<[u8]>;
+  /// Decodes the extension value.
+  /// 
+  /// Returns `None` if the value is invalid.
+  /// 
+  /// # Params
+  /// 
+  /// value --- The encoded extension value.  
+  fn decode(value: &[u8],) -> Option<Self>;
+}
+
+/// A single extension field.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct Extension {
+  /// The type identifier of the extension.
+  pub kind: u16,
+  /// Whether Clients which do not understand the extension must refuse the message.
+  pub critical: bool,
+  /// The extension value.
+  pub value: Box<[u8]>,
+}
+
+/// The extension fields of a [Message] ordered by type identifier.
+#[derive(PartialEq, Eq, Clone, Debug, Default,)]
+pub struct Extensions(Vec<Extension>,);
+
+impl Extensions {
+  /// Constructs an empty set of `Extensions`.
+  #[inline]
+  pub const fn new() -> Self { Extensions(Vec::new(),) }
+  /// Returns `true` if there are no extensions.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.0.is_empty() }
+  /// Iterates the extensions in order of type identifier.
+  #[inline]
+  pub fn iter(&self,) -> impl Iterator<Item = &Extension> { self.0.iter() }
+  /// Gets the extension with the passed type identifier.
+  /// 
+  /// # Params
+  /// 
+  /// kind --- The type identifier of the extension.  
+  pub fn get(&self, kind: u16,) -> Option<&Extension> {
+    self.0.binary_search_by_key(&kind, |extension,| extension.kind,).ok()
+      .map(|index,| &self.0[index],)
+  }
+  /// Inserts an extension returning any extension of the same type it replaced.
+  /// 
+  /// # Params
+  /// 
+  /// extension --- The extension to insert.  
+  pub fn insert(&mut self, extension: Extension,) -> Option<Extension> {
+    use std::mem;
+
+    match self.0.binary_search_by_key(&extension.kind, |extension,| extension.kind,) {
+      Ok(index) => Some(mem::replace(&mut self.0[index], extension,)),
+      Err(index) => { self.0.insert(index, extension,); None },
+    }
+  }
+  /// Removes the extension with the passed type identifier.
+  /// 
+  /// # Params
+  /// 
+  /// kind --- The type identifier of the extension.  
+  pub fn remove(&mut self, kind: u16,) -> Option<Extension> {
+    self.0.binary_search_by_key(&kind, |extension,| extension.kind,).ok()
+      .map(|index,| self.0.remove(index,),)
+  }
+  /// Gets and decodes a typed extension.
+  /// 
+  /// Returns `None` if the extension is missing or its value is invalid.
+  pub fn value<E,>(&self,) -> Option<E>
+    where E: ExtensionType, {
+    self.get(E::KIND,).and_then(|extension,| E::decode(&extension.value,),)
+  }
+  /// Encodes and inserts a typed extension.
+  /// 
+  /// # Params
+  /// 
+  /// value --- The extension value to insert.  
+  pub fn set<E,>(&mut self, value: &E,)
+    where E: ExtensionType, {
+    self.insert(Extension { kind: E::KIND, critical: E::CRITICAL, value: value.encode(), },);
+  }
+  /// Encodes the extensions as a sequence of type-length-value records.
+  pub fn encode(&self,) -> Vec<u8> {
+    let len = self.0.iter()
+      .map(|extension,| RECORD_HEADER_LENGTH + extension.value.len(),)
+      .sum();
+    let mut data = Vec::with_capacity(len,);
+
+    for extension in self.0.iter() {
+      data.extend_from_slice(&extension.kind.to_be_bytes(),);
+      data.push(if extension.critical { CRITICAL_FLAG } else { 0 },);
+      data.extend_from_slice(&(extension.value.len() as u32).to_be_bytes(),);
+      data.extend_from_slice(&extension.value,);
+    }
+
+    data
+  }
+  /// Decodes a sequence of type-length-value records.
+  /// 
+  /// Returns `None` if the records are malformed, unordered or repeated.
+  /// 
+  /// # Params
+  /// 
+  /// data --- The encoded extensions.  
+  pub fn decode(mut data: &[u8],) -> Option<Self> {
+    let mut extensions = Vec::new();
+
+    while !data.is_empty() {
+      if data.len() < RECORD_HEADER_LENGTH { return None }
+
+      let kind = u16::from_be_bytes([data[0], data[1],],);
+      let critical = match data[2] {
+        0 => false,
+        CRITICAL_FLAG => true,
+        _ => return None,
+      };
+      let len = u32::from_be_bytes([data[3], data[4], data[5], data[6],],) as usize;
+
+      data = &data[RECORD_HEADER_LENGTH..];
+      if data.len() < len { return None }
+
+      //Records must be strictly ordered so every set of extensions has one encoding.
+      if extensions.last().map_or(false, |last: &Extension,| last.kind >= kind,) { return None }
+
+      extensions.push(Extension { kind, critical, value: data[..len].into(), },);
+      data = &data[len..];
+    }
+
+    Some(Extensions(extensions,))
+  }
+  /// The extensions as authenticated with the message data.
+  pub(crate) fn authenticated_data(&self,) -> Vec<u8> {
+    if self.is_empty() { return Vec::new() }
+
+    let extensions = self.encode();
+    let mut data = Vec::with_capacity(extensions.len() + 5,);
+
+    data.push(super::EXTENSIONS_TAG,);
+    data.extend_from_slice(&(extensions.len() as u32).to_be_bytes(),);
+    data.extend_from_slice(&extensions,);
+
+    data
+  }
+}
+
+/// The extension types understood by a Client.
+#[derive(PartialEq, Eq, Clone, Debug, Default,)]
+pub struct Registry(BTreeSet<u16>,);
+
+impl Registry {
+  /// Constructs an empty `Registry`.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+  /// Registers a typed extension.
+  #[inline]
+  pub fn register<E,>(&mut self,)
+    where E: ExtensionType, { self.register_kind(E::KIND,) }
+  /// Registers an extension type identifier.
+  /// 
+  /// # Params
+  /// 
+  /// kind --- The type identifier of the extension.  
+  #[inline]
+  pub fn register_kind(&mut self, kind: u16,) { self.0.insert(kind,); }
+  /// Removes an extension type identifier.
+  /// 
+  /// # Params
+  /// 
+  /// kind --- The type identifier of the extension.  
+  #[inline]
+  pub fn unregister_kind(&mut self, kind: u16,) { self.0.remove(&kind,); }
+  /// Returns `true` if the extension type identifier is registered or is defined by this
+  /// crate.
+  /// 
+  /// # Params
+  /// 
+  /// kind --- The type identifier of the extension.  
+  #[inline]
+  pub fn is_registered(&self, kind: u16,) -> bool { BUILTIN_KINDS.contains(&kind,) || self.0.contains(&kind,) }
+  /// Iterates the registered type identifiers.
+  #[inline]
+  pub fn iter(&self,) -> impl Iterator<Item = u16> + '_ { self.0.iter().copied() }
+  /// Finds the first critical extension which is not registered.
+  /// 
+  /// # Params
+  /// 
+  /// extensions --- The extensions to check.  
+  pub fn unknown_critical(&self, extensions: &Extensions,) -> Option<u16> {
+    extensions.iter()
+      .find(|extension,| extension.critical && !self.is_registered(extension.kind,),)
+      .map(|extension,| extension.kind,)
+  }
+}
+
+impl std::iter::FromIterator<u16> for Registry {
+  #[inline]
+  fn from_iter<I,>(iter: I,) -> Self
+    where I: IntoIterator<Item = u16>, { Registry(iter.into_iter().collect(),) }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  struct ContentType(String,);
+
+  impl ExtensionType for ContentType {
+    const KIND: u16 = 1;
+    const CRITICAL: bool = false;
+
+    fn encode(&self,) -> Box<[u8]> { self.0.as_bytes().into() }
+    fn decode(value: &[u8],) -> Option<Self> {
+      std::str::from_utf8(value,).ok().map(|value,| ContentType(value.into(),),)
+    }
+  }
+
+  #[test]
+  fn test_extensions() {
+    let mut extensions = Extensions::new();
+
+    extensions.insert(Extension { kind: 7, critical: true, value: vec![1, 2, 3,].into(), },);
+    extensions.set(&ContentType("text/plain".into(),),);
+
+    assert_eq!(extensions.iter().map(|extension,| extension.kind,).collect::<Vec<_>>(), vec![1, 7,],
+      "Extensions are unordered",
+    );
+    assert_eq!(extensions.value::<ContentType>().map(|value,| value.0,), Some("text/plain".into()),
+      "Bad typed extension",
+    );
+
+    let encoded = extensions.encode();
+
+    assert_eq!(Extensions::decode(&encoded,), Some(extensions.clone()), "Extensions decoded incorrectly",);
+    assert_eq!(Extensions::decode(&encoded[..encoded.len() - 1],), None, "Decoded truncated extensions",);
+
+    let mut unordered = encoded[17..].to_vec();
+    unordered.extend_from_slice(&encoded[..17],);
+    assert_eq!(Extensions::decode(&unordered,), None, "Decoded unordered extensions",);
+
+    assert!(extensions.remove(7,).is_some(), "Failed to remove extension",);
+    assert!(extensions.get(7,).is_none(), "Extension not removed",);
+  }
+  #[test]
+  fn test_registry() {
+    let mut extensions = Extensions::new();
+    let mut registry = Registry::new();
+
+    extensions.set(&ContentType("text/plain".into(),),);
+    assert_eq!(registry.unknown_critical(&extensions,), None, "Rejected a non-critical extension",);
+
+    extensions.insert(Extension { kind: 7, critical: true, value: Box::new([],), },);
+    assert_eq!(registry.unknown_critical(&extensions,), Some(7), "Accepted an unknown critical extension",);
+
+    registry.register_kind(7,);
+    registry.register::<ContentType>();
+    assert_eq!(registry.unknown_critical(&extensions,), None, "Rejected a registered extension",);
+    assert_eq!(registry.iter().collect::<Vec<_>>(), vec![1, 7,], "Bad registered kinds",);
+
+    //The extensions defined by this crate are always understood.
+    let mut builtin = Extensions::new();
+    builtin.set(&Expires(1,),);
+    assert_eq!(Registry::new().unknown_critical(&builtin,), None, "Rejected a builtin extension",);
+  }
+}
@@ -1,7 +1,7 @@
 //! Defines serde for the Message type.
 //! 
 //! Author -- daniel.bechaz@gmail.com  
-//! Last Moddified --- 2019-05-11
+//! Last Moddified --- 2026-10-18
 
 use super::*;
 use ::serde::{
@@ -15,6 +15,7 @@ mod message {
   static FIELDS: &[&str] = &[
     "header",
     "data",
+    "extensions",
   ];
 
   impl Serialize for Message {
@@ -24,6 +25,7 @@ mod message {
 
       serializer.serialize_field(&self.header,)?;
       serializer.serialize_field(&self.data,)?;
+      serializer.serialize_field(&self.extensions.encode(),)?;
       serializer.end()
     }
   }
@@ -31,7 +33,7 @@ mod message {
   impl<'de,> Deserialize<'de> for Message {
     fn deserialize<D>(deserializer: D,) -> Result<Self, D::Error>
       where D: Deserializer<'de>, {
-      use ::serde::de::Error;
+      use ::serde::de::{Error, Unexpected,};
       use std::fmt;
 
       struct MessageVisitor;
@@ -48,8 +50,14 @@ mod message {
             .ok_or(Error::missing_field(FIELDS[0],),)?;
           let data = seq.next_element()?
             .ok_or(Error::missing_field(FIELDS[1],),)?;
+          //Messages from older versions do not carry extensions.
+          let extensions = match seq.next_element::<Box<[u8]>>()? {
+            Some(extensions) => Extensions::decode(&extensions,)
+              .ok_or(Error::invalid_value(Unexpected::Bytes(&extensions,), &"encoded extensions",),)?,
+            None => Extensions::default(),
+          };
 
-          Ok(Message { header, data, })
+          Ok(Message { header, data, extensions, })
         }
       }
       
@@ -68,7 +76,12 @@ mod message {
       let previous_step = 2;
       let header = Header { public_key, message_index, previous_step, };
       let data = vec![1, 2, 3, 4,].into_boxed_slice();
-      let message = Message { header, data, };
+      let mut extensions = Extensions::new();
+      extensions.insert(Extension { kind: 5, critical: true, value: vec![6, 7,].into(), },);
+      extensions.set(&Expires(4,),);
+      extensions.set(&ChainFingerprint([3; 8],),);
+      extensions.set(&Franking([8; 32],),);
+      let message = Message { header, data, extensions, };
       let mut serialised = [0u8; 1024];
       let serialised = {
         let writer = &mut serialised.as_mut();
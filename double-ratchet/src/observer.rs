@@ -0,0 +1,100 @@
+//! Defines the [Observer] trait used to monitor the ratchet of a Client.
+//! 
+//! Events never contain any secrets; public keys are identified by the same short
+//! fingerprint used by [SessionInfo](crate::client::SessionInfo).
+//! With the `tracing` feature enabled every event is also emitted as a `tracing` event.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::client::Error;
+
+/// An event in the ratchet of a Client.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Event {
+  /// The Client took a ratchet step after receiving a new public key.
+  StepAdvanced {
+    /// The fingerprint of the new public key of the remote Client.
+    remote_key: [u8; 8],
+    /// The fingerprint of the new public key of this Client.
+    local_key: [u8; 8],
+    /// The number of messages the remote Client locked in its previous step.
+    previous_step: u32,
+  },
+  /// The Client generated the keys of messages which have not been received yet.
+  KeysSkipped {
+    /// The fingerprint of the public key the keys belong to.
+    remote_key: [u8; 8],
+    /// The index of the first skipped message.
+    start: u32,
+    /// The index after the last skipped message.
+    end: u32,
+  },
+  /// The Client destroyed message keys which were never used.
+  KeyEvicted {
+    /// The fingerprint of the public key the keys belonged to.
+    remote_key: [u8; 8],
+    /// The number of keys destroyed.
+    count: usize,
+  },
+  /// The Client failed to open a message.
+  DecryptFailed {
+    /// The fingerprint of the public key of the message.
+    remote_key: [u8; 8],
+    /// The index of the message.
+    message_index: u32,
+    /// The reason the message could not be opened.
+    error: Error,
+  },
+  /// The Client reverted the changes made while opening a message.
+  RolledBack {
+    /// The fingerprint of the public key of the message.
+    remote_key: [u8; 8],
+    /// The index of the message.
+    message_index: u32,
+    /// Indicates whether a ratchet step was reverted.
+    step: bool,
+  },
+}
+
+impl Event {
+  /// Emits the event as a `tracing` event.
+  #[cfg(feature = "tracing",)]
+  pub(crate) fn trace(&self,) {
+    use tracing::{debug, warn,};
+
+    match *self {
+      Event::StepAdvanced { remote_key, local_key, previous_step, } => debug!(
+        remote_key = ?remote_key, local_key = ?local_key, previous_step, "step advanced",
+      ),
+      Event::KeysSkipped { remote_key, start, end, } => debug!(
+        remote_key = ?remote_key, start, end, "keys skipped",
+      ),
+      Event::KeyEvicted { remote_key, count, } => debug!(
+        remote_key = ?remote_key, count = count as u64, "key evicted",
+      ),
+      Event::DecryptFailed { remote_key, message_index, error, } => warn!(
+        remote_key = ?remote_key, message_index, error = ?error, "decrypt failed",
+      ),
+      Event::RolledBack { remote_key, message_index, step, } => debug!(
+        remote_key = ?remote_key, message_index, step, "rollback performed",
+      ),
+    }
+  }
+}
+
+/// Receives the [Event]s of a Client.
+pub trait Observer {
+  /// Called after every event.
+  /// 
+  /// # Params
+  /// 
+  /// event --- The event which occurred.  
+  fn observe(&self, event: &Event,);
+}
+
+impl<F,> Observer for F
+  where F: Fn(&Event,), {
+  #[inline]
+  fn observe(&self, event: &Event,) { self(event,) }
+}
@@ -0,0 +1,124 @@
+//! Defines the [Padding] policies used to hide the length of messages.
+//! 
+//! Padded messages end with a `0x80` marker byte followed by zero bytes which are
+//! removed and checked when the message is opened.
+//! Both Clients must use the same `Padding` policy.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+mod serde;
+
+/// The byte which marks the end of the message data.
+const MARKER: u8 = 0x80;
+
+/// A policy deciding the length messages are padded too.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Padding {
+  /// Messages are not padded.
+  None,
+  /// Messages are padded to a multiple of the block size.
+  Block(u32,),
+  /// Messages are padded using [Padmé](https://arxiv.org/abs/1806.03160) which leaks
+  /// at most `O(log log n)` bits of the length.
+  Padme,
+  /// Messages are padded to a fixed length; longer messages cannot be locked.
+  Fixed(u32,),
+}
+
+impl Padding {
+  /// Calculates the length of a message once it is padded.
+  /// 
+  /// Returns `None` if the message is too long to be padded.
+  /// 
+  /// # Params
+  /// 
+  /// len --- The length of the message data.  
+  pub fn padded_length(&self, len: usize,) -> Option<usize> {
+    if *self == Padding::None { return Some(len) }
+
+    //Padded messages always include the marker.
+    let len = len.checked_add(1,)?;
+
+    match *self {
+      Padding::None | Padding::Block(0,) => Some(len),
+      Padding::Block(size,) => {
+        let size = size as usize;
+
+        len.checked_add(size - 1,).map(|len,| len / size * size,)
+      },
+      Padding::Padme => {
+        //The position of the highest bit of the length.
+        let exponent = (0usize.count_zeros() - len.leading_zeros() - 1) as usize;
+        if exponent == 0 { return Some(len) }
+
+        //The number of bits needed to represent the exponent.
+        let bits = (0usize.count_zeros() - exponent.leading_zeros()) as usize;
+        let mask = (1usize << (exponent - bits)) - 1;
+
+        len.checked_add(mask,).map(|len,| len & !mask,)
+      },
+      Padding::Fixed(size,) => if len <= size as usize { Some(size as usize) } else { None },
+    }
+  }
+  /// The marker appended to the message data.
+  #[inline]
+  pub(crate) fn marker(&self,) -> Option<u8> {
+    match self { Padding::None => None, _ => Some(MARKER), }
+  }
+  /// Calculates the length of a padded message once the padding is removed.
+  /// 
+  /// Returns `None` if the padding is invalid.
+  /// 
+  /// # Params
+  /// 
+  /// data --- The padded message data.  
+  pub(crate) fn unpad(&self, data: &[u8],) -> Option<usize> {
+    match self {
+      Padding::None => Some(data.len()),
+      _ => {
+        let len = data.iter().rposition(|&byte,| byte != 0,)?;
+
+        if data[len] == MARKER { Some(len) } else { None }
+      },
+    }
+  }
+}
+
+impl Default for Padding {
+  #[inline]
+  fn default() -> Self { Padding::None }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_padding_length() {
+    assert_eq!(Padding::None.padded_length(10,), Some(10), "Bad unpadded length",);
+    assert_eq!(Padding::Block(16,).padded_length(10,), Some(16), "Bad block length",);
+    assert_eq!(Padding::Block(16,).padded_length(15,), Some(16), "Bad full block length",);
+    assert_eq!(Padding::Block(16,).padded_length(16,), Some(32), "Bad overflowing block length",);
+    assert_eq!(Padding::Fixed(100,).padded_length(10,), Some(100), "Bad fixed length",);
+    assert_eq!(Padding::Fixed(100,).padded_length(100,), None, "Padded a long message",);
+
+    for len in 0..5000 {
+      let padded = Padding::Padme.padded_length(len,).expect("Error padding message");
+
+      assert!(padded > len, "Padmé did not fit the marker",);
+      //Padmé adds at most 12% overhead.
+      assert!(padded - (len + 1) <= (len + 1) / 8, "Padmé overhead too large",);
+    }
+    assert_eq!(Padding::Padme.padded_length(1000,), Some(1024), "Bad Padmé length",);
+    //Padmé only rounds to a multiple of a smaller power of two.
+    assert_eq!(Padding::Padme.padded_length(1099,), Some(1152), "Bad Padmé length",);
+  }
+  #[test]
+  fn test_padding_unpad() {
+    assert_eq!(Padding::Padme.unpad(&[1, 2, MARKER, 0, 0,],), Some(2), "Bad unpadded length",);
+    assert_eq!(Padding::Padme.unpad(&[1, 2, 3, 0, 0,],), None, "Accepted a missing marker",);
+    assert_eq!(Padding::Padme.unpad(&[0, 0,],), None, "Accepted empty padding",);
+    assert_eq!(Padding::None.unpad(&[1, 0,],), Some(2), "Unpadded an unpadded message",);
+  }
+}
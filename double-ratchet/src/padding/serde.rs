@@ -0,0 +1,56 @@
+//! Defines serde for Padding.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer,},
+  de::{Deserialize, Deserializer,},
+};
+
+impl Serialize for Padding {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    let value = match *self {
+      Padding::None => (0u8, 0u32,),
+      Padding::Block(size,) => (1, size,),
+      Padding::Padme => (2, 0,),
+      Padding::Fixed(size,) => (3, size,),
+    };
+
+    value.serialize(serializer,)
+  }
+}
+
+impl<'de,> Deserialize<'de> for Padding {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::{Error, Unexpected,};
+
+    match <(u8, u32,)>::deserialize(deserializer,)? {
+      (0, _,) => Ok(Padding::None),
+      (1, size,) => Ok(Padding::Block(size,)),
+      (2, _,) => Ok(Padding::Padme),
+      (3, size,) => Ok(Padding::Fixed(size,)),
+      (kind, _,) => Err(Error::invalid_value(Unexpected::Unsigned(kind as u64,), &"a padding kind",)),
+    }
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_padding_serde() {
+    for padding in vec![Padding::None, Padding::Block(16,), Padding::Padme, Padding::Fixed(256,),] {
+      let serialised = serde_cbor::ser::to_vec_packed(&padding,)
+        .expect("Error serialising the Padding");
+      let other: Padding = serde_cbor::from_slice(&serialised,)
+        .expect("Error deserialising the Padding");
+
+      assert_eq!(padding, other, "Padding deserialised incorrectly",);
+    }
+  }
+}
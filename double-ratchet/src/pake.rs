@@ -0,0 +1,566 @@
+//! Defines a password authenticated key exchange used to bootstrap a session.
+//! 
+//! Two Clients which only share a short code can run `connect` and `accept` over any
+//! `Read + Write` IO to agree on a session without knowing each others public keys.
+//! The exchange is [CPace](https://tools.ietf.org/html/draft-irtf-cfrg-cpace) over
+//! ristretto255; an attacker gets a single password guess per exchange.
+//! Once the shared key is agreed both Clients send their identity public keys with a
+//! confirmation tag and the resulting `LocalClient`/`RemoteClient` can be used with
+//! [Framed](crate::framed::Framed) as normal.
+//! The `*_with_store` variants check the received identity key against an
+//! [IdentityStore] before the exchange completes.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  ratchet::Ratchet,
+  client::{LocalClient, RemoteClient, aead::Algorithm,},
+  identity::{self, IdentityStore, Trust,},
+  generic_array::{ArrayLength, GenericArray,},
+  typenum::consts::U64,
+};
+use curve25519_dalek::{
+  ristretto::{RistrettoPoint, CompressedRistretto,},
+  scalar::Scalar,
+};
+use sha2::{Sha512, Digest,};
+use clear_on_drop::ClearOnDrop;
+use ring::{digest, hmac, constant_time,};
+use rand::{RngCore, CryptoRng,};
+use x25519_dalek::{PublicKey, StaticSecret,};
+use std::{hash::Hash, io::{self, Read, Write,},};
+
+/// The domain separation tag of the generator.
+const DSI: &[u8] = b"CPaceRistretto255";
+/// The domain separation tag of the intermediate session key.
+const DSI_ISK: &[u8] = b"CPaceRistretto255_ISK";
+/// The label of the confirmation tag sent by the initiator.
+const CONFIRM_INITIATOR: &[u8] = b"DoubleRatchetPakeInitiator";
+/// The label of the confirmation tag sent by the responder.
+const CONFIRM_RESPONDER: &[u8] = b"DoubleRatchetPakeResponder";
+/// The length of a confirmation tag.
+const TAG_LENGTH: usize = 32;
+
+/// Appends `bytes` to `data` prefixed with its length.
+/// 
+/// # Params
+/// 
+/// data --- The buffer to append too.  
+/// bytes --- The bytes to append.  
+fn append(data: &mut Vec<u8>, bytes: &[u8],) {
+  data.extend_from_slice(&(bytes.len() as u64).to_le_bytes(),);
+  data.extend_from_slice(bytes,);
+}
+
+/// Derives the generator of the exchange from the password.
+/// 
+/// # Params
+/// 
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+fn generator(password: &[u8], context: &[u8],) -> RistrettoPoint {
+  let mut data = Vec::new();
+
+  append(&mut data, DSI,);
+  append(&mut data, password,);
+  append(&mut data, context,);
+
+  let generator = RistrettoPoint::hash_from_bytes::<Sha512>(&data,);
+
+  //Clear the password.
+  ClearOnDrop::new(data.as_mut_slice(),);
+
+  generator
+}
+
+/// Generates a secret scalar and the public share sent to the remote Client.
+/// 
+/// # Params
+/// 
+/// generator --- The generator of the exchange.  
+/// rand --- The source of randomness used to generate the scalar.  
+fn share<Rand,>(generator: &RistrettoPoint, rand: &mut Rand,) -> (Scalar, [u8; 32],)
+  where Rand: RngCore + CryptoRng, {
+  let mut bytes = [0; 64];
+
+  rand.fill_bytes(&mut bytes,);
+
+  let scalar = Scalar::from_bytes_mod_order_wide(&bytes,);
+  ClearOnDrop::new(bytes.as_mut(),);
+
+  (scalar, (generator * scalar).compress().to_bytes(),)
+}
+
+/// Derives the session key from the shares of both Clients.
+/// 
+/// # Params
+/// 
+/// scalar --- The secret scalar of this Client.  
+/// remote --- The share of the remote Client.  
+/// initiator --- The share of the initiating Client.  
+/// responder --- The share of the responding Client.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+fn session_key(scalar: &Scalar, remote: &[u8; 32], initiator: &[u8; 32], responder: &[u8; 32], context: &[u8],) -> Result<ClearOnDrop<GenericArray<u8, U64>>, Error> {
+  let point = CompressedRistretto(*remote,).decompress()
+    .ok_or(Error::InvalidShare,)?;
+  let mut shared = (point * scalar).compress().to_bytes();
+
+  //The identity point would make the key independent of the password.
+  if shared == [0; 32] { return Err(Error::InvalidShare) }
+
+  let hash = Sha512::new()
+    .chain(DSI_ISK,)
+    .chain(&(context.len() as u64).to_le_bytes(),)
+    .chain(context,)
+    .chain(&shared[..],)
+    .chain(&initiator[..],)
+    .chain(&responder[..],)
+    .result();
+  ClearOnDrop::new(shared.as_mut(),);
+
+  Ok(ClearOnDrop::new(GenericArray::clone_from_slice(&hash[..],),))
+}
+
+/// Calculates the confirmation tag of an identity key.
+/// 
+/// # Params
+/// 
+/// session_key --- The agreed session key.  
+/// label --- The role of the Client sending the tag.  
+/// initiator --- The share of the initiating Client.  
+/// responder --- The share of the responding Client.  
+/// identity --- The identity public key being confirmed.  
+fn confirmation(session_key: &[u8], label: &[u8], initiator: &[u8; 32], responder: &[u8; 32], identity: &[u8; 32],) -> hmac::Signature {
+  let key = hmac::SigningKey::new(&digest::SHA256, &session_key[..32],);
+  let mut context = hmac::SigningContext::with_key(&key,);
+
+  context.update(label,);
+  context.update(initiator,);
+  context.update(responder,);
+  context.update(identity,);
+  context.sign()
+}
+
+/// Writes the identity key of this Client with its confirmation tag.
+/// 
+/// # Params
+/// 
+/// io --- The IO to write too.  
+/// session_key --- The agreed session key.  
+/// label --- The role of this Client.  
+/// initiator --- The share of the initiating Client.  
+/// responder --- The share of the responding Client.  
+/// identity --- The identity public key of this Client.  
+fn send_identity<Io,>(io: &mut Io, session_key: &[u8], label: &[u8], initiator: &[u8; 32], responder: &[u8; 32], identity: &PublicKey,) -> Result<(), Error>
+  where Io: Write, {
+  let tag = confirmation(session_key, label, initiator, responder, identity.as_bytes(),);
+
+  io.write_all(identity.as_bytes(),)?;
+  io.write_all(tag.as_ref(),)?;
+  io.flush().map_err(Error::from,)
+}
+
+/// Reads the identity key of the remote Client and checks its confirmation tag.
+/// 
+/// # Params
+/// 
+/// io --- The IO to read from.  
+/// session_key --- The agreed session key.  
+/// label --- The role of the remote Client.  
+/// initiator --- The share of the initiating Client.  
+/// responder --- The share of the responding Client.  
+fn receive_identity<Io,>(io: &mut Io, session_key: &[u8], label: &[u8], initiator: &[u8; 32], responder: &[u8; 32],) -> Result<PublicKey, Error>
+  where Io: Read, {
+  let mut identity = [0; 32];
+  let mut tag = [0; TAG_LENGTH];
+
+  io.read_exact(&mut identity,)?;
+  io.read_exact(&mut tag,)?;
+
+  let expected = confirmation(session_key, label, initiator, responder, &identity,);
+  constant_time::verify_slices_are_equal(expected.as_ref(), &tag,)
+    .map_err(|_,| Error::Confirmation,)?;
+
+  Ok(identity.into())
+}
+
+/// Derives the seed of the session from the session key and the identity keys.
+/// 
+/// # Params
+/// 
+/// session_key --- The agreed session key.  
+/// remote --- The identity public key of the remote Client.  
+/// private_key --- The identity private key of this Client.  
+fn seed(session_key: &[u8], remote: &PublicKey, private_key: &StaticSecret,) -> ClearOnDrop<GenericArray<u8, U64>> {
+  let mut seed = ClearOnDrop::new(GenericArray::default(),);
+
+  seed[..32].copy_from_slice(&session_key[32..],);
+  seed[32..].copy_from_slice(private_key.diffie_hellman(remote,).as_bytes(),);
+
+  seed
+}
+
+/// Runs the initiating end of the exchange.
+/// 
+/// Returns the new Client and the identity public key of the remote Client.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+#[inline]
+pub fn connect<Io, D, S, A, R, L,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret,) -> Result<(LocalClient<D, S, A, R, L,>, PublicKey,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  connect_with_rng(io, password, context, private_key, &mut rand::thread_rng(),)
+}
+
+/// Runs the initiating end of the exchange using the passed source of randomness.
+/// 
+/// Returns the new Client and the identity public key of the remote Client.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// rand --- The source of randomness used to generate keys.  
+pub fn connect_with_rng<Io, D, S, A, R, L, Rand,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, rand: &mut Rand,) -> Result<(LocalClient<D, S, A, R, L,>, PublicKey,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    Rand: RngCore + CryptoRng, {
+  initiate(io, password, context, private_key, |_,| Ok(()), rand,)
+}
+
+/// Runs the initiating end of the exchange checking the identity key of the remote
+/// Client against `store`.
+/// 
+/// The exchange is aborted with [Error::Identity] if the key does not match the key
+/// recorded for `peer`.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// store --- The recorded identities of known peers.  
+/// peer --- The peer expected at the other end of `io`.  
+#[inline]
+pub fn connect_with_store<Io, D, S, A, R, L, P,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, store: &mut IdentityStore<P,>, peer: P,) -> Result<(LocalClient<D, S, A, R, L,>, Trust,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    P: Eq + Hash, {
+  connect_with_store_and_rng(io, password, context, private_key, store, peer, &mut rand::thread_rng(),)
+}
+
+/// Runs the initiating end of the exchange checking the identity key of the remote
+/// Client against `store` using the passed source of randomness.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// store --- The recorded identities of known peers.  
+/// peer --- The peer expected at the other end of `io`.  
+/// rand --- The source of randomness used to generate keys.  
+pub fn connect_with_store_and_rng<Io, D, S, A, R, L, P, Rand,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, store: &mut IdentityStore<P,>, peer: P, rand: &mut Rand,) -> Result<(LocalClient<D, S, A, R, L,>, Trust,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    P: Eq + Hash,
+    Rand: RngCore + CryptoRng, {
+  let mut trust = None;
+  let (client, _,) = initiate(io, password, context, private_key, |remote,| {
+    trust = Some(store.check(peer, remote,)?);
+
+    Ok(())
+  }, rand,)?;
+
+  Ok((client, trust.ok_or(Error::Confirmation,)?,))
+}
+
+/// Runs the initiating end of the exchange.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// verify --- Checks the identity key of the remote Client once it is confirmed.  
+/// rand --- The source of randomness used to generate keys.  
+fn initiate<Io, D, S, A, R, L, V, Rand,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, verify: V, rand: &mut Rand,) -> Result<(LocalClient<D, S, A, R, L,>, PublicKey,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    V: FnOnce(&PublicKey,) -> Result<(), Error>,
+    Rand: RngCore + CryptoRng, {
+  let (scalar, initiator,) = share(&generator(password, context,), rand,);
+  let mut responder = [0; 32];
+
+  io.write_all(&initiator,)?;
+  io.flush()?;
+  io.read_exact(&mut responder,)?;
+
+  let session_key = session_key(&scalar, &responder, &initiator, &responder, context,)?;
+
+  send_identity(io, &session_key, CONFIRM_INITIATOR, &initiator, &responder, &PublicKey::from(private_key,),)?;
+  let remote = receive_identity(io, &session_key, CONFIRM_RESPONDER, &initiator, &responder,)?;
+  verify(&remote,)?;
+  let mut seed = seed(&session_key, &remote, private_key,);
+
+  Ok((LocalClient::from_seed_with_rng(seed.as_mut_slice(), &remote, private_key, rand,), remote,))
+}
+
+/// Runs the responding end of the exchange.
+/// 
+/// Returns the new Client and the identity public key of the remote Client.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+#[inline]
+pub fn accept<Io, D, S, A, R, L,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret,) -> Result<(RemoteClient<D, S, A, R, L,>, PublicKey,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  accept_with_rng(io, password, context, private_key, &mut rand::thread_rng(),)
+}
+
+/// Runs the responding end of the exchange using the passed source of randomness.
+/// 
+/// Returns the new Client and the identity public key of the remote Client.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// rand --- The source of randomness used to generate keys.  
+pub fn accept_with_rng<Io, D, S, A, R, L, Rand,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, rand: &mut Rand,) -> Result<(RemoteClient<D, S, A, R, L,>, PublicKey,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    Rand: RngCore + CryptoRng, {
+  respond(io, password, context, private_key, |_,| Ok(()), rand,)
+}
+
+/// Runs the responding end of the exchange checking the identity key of the remote
+/// Client against `store`.
+/// 
+/// The exchange is aborted with [Error::Identity] before this Client confirms if the key
+/// does not match the key recorded for `peer`.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// store --- The recorded identities of known peers.  
+/// peer --- The peer expected at the other end of `io`.  
+#[inline]
+pub fn accept_with_store<Io, D, S, A, R, L, P,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, store: &mut IdentityStore<P,>, peer: P,) -> Result<(RemoteClient<D, S, A, R, L,>, Trust,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    P: Eq + Hash, {
+  accept_with_store_and_rng(io, password, context, private_key, store, peer, &mut rand::thread_rng(),)
+}
+
+/// Runs the responding end of the exchange checking the identity key of the remote
+/// Client against `store` using the passed source of randomness.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// store --- The recorded identities of known peers.  
+/// peer --- The peer expected at the other end of `io`.  
+/// rand --- The source of randomness used to generate keys.  
+pub fn accept_with_store_and_rng<Io, D, S, A, R, L, P, Rand,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, store: &mut IdentityStore<P,>, peer: P, rand: &mut Rand,) -> Result<(RemoteClient<D, S, A, R, L,>, Trust,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    P: Eq + Hash,
+    Rand: RngCore + CryptoRng, {
+  let mut trust = None;
+  let (client, _,) = respond(io, password, context, private_key, |remote,| {
+    trust = Some(store.check(peer, remote,)?);
+
+    Ok(())
+  }, rand,)?;
+
+  Ok((client, trust.ok_or(Error::Confirmation,)?,))
+}
+
+/// Runs the responding end of the exchange.
+/// 
+/// # Params
+/// 
+/// io --- The IO connected to the remote Client.  
+/// password --- The code shared by both Clients.  
+/// context --- Data identifying the exchange which both Clients agree on.  
+/// private_key --- The identity private key of this Client.  
+/// verify --- Checks the identity key of the remote Client once it is confirmed.  
+/// rand --- The source of randomness used to generate keys.  
+fn respond<Io, D, S, A, R, L, V, Rand,>(io: &mut Io, password: &[u8], context: &[u8], private_key: &StaticSecret, verify: V, rand: &mut Rand,) -> Result<(RemoteClient<D, S, A, R, L,>, PublicKey,), Error>
+  where Io: Read + Write,
+    S: 'static + ArrayLength<u8>,
+    A: Algorithm,
+    L: 'static + ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng,
+    V: FnOnce(&PublicKey,) -> Result<(), Error>,
+    Rand: RngCore + CryptoRng, {
+  let (scalar, responder,) = share(&generator(password, context,), rand,);
+  let mut initiator = [0; 32];
+
+  io.read_exact(&mut initiator,)?;
+  io.write_all(&responder,)?;
+  io.flush()?;
+
+  let session_key = session_key(&scalar, &initiator, &initiator, &responder, context,)?;
+  //The responder only confirms after the initiator has proven knowledge of the password.
+  let remote = receive_identity(io, &session_key, CONFIRM_INITIATOR, &initiator, &responder,)?;
+  verify(&remote,)?;
+  send_identity(io, &session_key, CONFIRM_RESPONDER, &initiator, &responder, &PublicKey::from(private_key,),)?;
+  let mut seed = seed(&session_key, &remote, private_key,);
+
+  Ok((RemoteClient::from_seed_with_rng(seed.as_mut_slice(), &remote, private_key, rand,), remote,))
+}
+
+/// An error returned from a password authenticated key exchange.
+#[derive(Debug,)]
+pub enum Error {
+  /// There was an error interacting with the IO.
+  Io(io::Error,),
+  /// The remote Client sent an invalid share.
+  InvalidShare,
+  /// The remote Client could not confirm the session key; most likely the passwords do
+  /// not match.
+  Confirmation,
+  /// The identity key of the remote Client did not match the [IdentityStore].
+  Identity(identity::Error,),
+}
+
+impl From<io::Error> for Error {
+  #[inline]
+  fn from(from: io::Error,) -> Self { Error::Io(from,) }
+}
+
+impl From<identity::Error> for Error {
+  #[inline]
+  fn from(from: identity::Error,) -> Self { Error::Identity(from,) }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{client::{Client, aead::Aes256Gcm,}, typenum::consts,};
+  use sha1::Sha1;
+  use std::{thread, net::{TcpListener, TcpStream,},};
+
+  type Local = LocalClient<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>;
+  type Remote = RemoteClient<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>;
+
+  /// Runs an exchange between two Clients.
+  fn exchange(local_password: &'static [u8], remote_password: &'static [u8],) -> (Result<(Local, PublicKey,), Error>, Result<(Remote, PublicKey,), Error>,) {
+    let listener = TcpListener::bind("127.0.0.1:0",).expect("Error binding listener");
+    let address = listener.local_addr().expect("Error getting address");
+    let remote = thread::spawn(move || {
+      let (mut io, _,) = listener.accept().expect("Error accepting connection");
+
+      accept(&mut io, remote_password, b"test", &StaticSecret::from([2; 32],),)
+    },);
+    let mut io = TcpStream::connect(address,).expect("Error connecting");
+    let local = connect(&mut io, local_password, b"test", &StaticSecret::from([1; 32],),);
+
+    (local, remote.join().expect("Remote end panicked"),)
+  }
+
+  #[test]
+  fn test_pake() {
+    let (local, remote,) = exchange(b"1234", b"1234",);
+    let (mut local, local_remote,) = local.expect("Error connecting");
+    let (mut remote, remote_remote,) = remote.expect("Error accepting");
+
+    assert_eq!(local_remote.as_bytes(), PublicKey::from(&StaticSecret::from([2; 32],),).as_bytes(), "Bad remote identity",);
+    assert_eq!(remote_remote.as_bytes(), PublicKey::from(&StaticSecret::from([1; 32],),).as_bytes(), "Bad local identity",);
+
+    let msg = [1; 10];
+    let message = local.lock(&mut msg.clone(),).expect("Error locking message");
+    let mut buffer = Vec::new();
+    remote.open(message, &mut buffer,).expect("Error opening message");
+    assert_eq!(buffer, msg.as_ref(), "Message corrupted",);
+  }
+  #[test]
+  fn test_pake_wrong_password() {
+    let (local, remote,) = exchange(b"1234", b"4321",);
+
+    match remote {
+      Err(Error::Confirmation) => {},
+      _ => panic!("Accepted the wrong password"),
+    }
+    //The responder closes the connection without confirming.
+    assert!(local.is_err(), "Connected with the wrong password",);
+  }
+  #[test]
+  fn test_pake_store() {
+    let listener = TcpListener::bind("127.0.0.1:0",).expect("Error binding listener");
+    let address = listener.local_addr().expect("Error getting address");
+    let remote = thread::spawn(move || {
+      let (mut io, _,) = listener.accept().expect("Error accepting connection");
+      let mut store = IdentityStore::new();
+
+      //A different key was recorded for the initiator.
+      store.check("local", &PublicKey::from(&StaticSecret::from([3; 32],),),).expect("Error recording key");
+      accept_with_store::<_, Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0, _,>(&mut io, b"1234", b"test", &StaticSecret::from([2; 32],), &mut store, "local",)
+        .map(|_,| (),)
+    },);
+    let mut io = TcpStream::connect(address,).expect("Error connecting");
+    let mut store = IdentityStore::new();
+    let local = connect_with_store::<_, Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0, _,>(&mut io, b"1234", b"test", &StaticSecret::from([1; 32],), &mut store, "remote",);
+
+    match remote.join().expect("Remote end panicked") {
+      Err(Error::Identity(identity::Error::KeyChanged { .. })) => {},
+      _ => panic!("Accepted a changed identity"),
+    }
+    //The responder aborts without confirming.
+    assert!(local.is_err(), "Connected to a Client which refused its identity",);
+    assert!(store.get(&"remote",).is_none(), "Recorded the identity of an unconfirmed Client",);
+  }
+}
@@ -0,0 +1,311 @@
+//! Defines a delivery and read receipts layer on top of a [Client].
+//! 
+//! Every message sent through [Receipts] is assigned an id which the remote end
+//! acknowledges with authenticated delivery and read receipts sent as ratchet messages.
+//! Messages which have not been acknowledged are kept so they can be retransmitted; a
+//! retransmitted message is locked again under a new key and duplicates are recognised
+//! by their id.
+//! The `Receipts` state serialises so it can be stored alongside the `Client`.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{message::Message, client::{self, Client,}, clock::{Clock, SystemClock,},};
+use clear_on_drop::ClearOnDrop;
+use rand::{RngCore, CryptoRng,};
+use std::collections::{BTreeMap, BTreeSet,};
+
+mod serde;
+
+/// The kind of a receipt.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Receipt {
+  /// The messages were received.
+  Delivered,
+  /// The messages were read by the user.
+  Read,
+}
+
+/// The plaintext of a message sent through [Receipts].
+enum Envelope {
+  /// Application data.
+  Data(u64, Vec<u8>,),
+  /// A receipt for the contained message ids.
+  Receipt(Receipt, Vec<u64>,),
+}
+
+impl Drop for Envelope {
+  #[inline]
+  fn drop(&mut self,) {
+    if let Envelope::Data(_, data,) = self { ClearOnDrop::new(data.as_mut_slice(),); }
+  }
+}
+
+/// A message which has not been acknowledged.
+struct Outbound {
+  /// The message data.
+  data: Box<[u8]>,
+  /// The time in seconds since the UNIX epoch the message was last sent.
+  sent: u64,
+  /// The number of times the message has been sent.
+  attempts: u32,
+}
+
+impl Drop for Outbound {
+  #[inline]
+  fn drop(&mut self,) { ClearOnDrop::new(self.data.as_mut(),); }
+}
+
+/// An event produced by opening a message with [Receipts].
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub enum Event {
+  /// A new message was received and its data was appended to the buffer.
+  Message(u64,),
+  /// A retransmission of a message which was already received; it should be
+  /// acknowledged again as the previous receipt may have been lost.
+  Duplicate(u64,),
+  /// The remote end received the contained messages.
+  Delivered(Vec<u64>,),
+  /// The remote end read the contained messages.
+  Read(Vec<u64>,),
+}
+
+/// The receipts state of a conversation.
+pub struct Receipts {
+  /// The id of the next message sent.
+  next_id: u64,
+  /// The messages sent which have not been acknowledged.
+  outbound: BTreeMap<u64, Outbound>,
+  /// Every message id below this value has been received.
+  received_below: u64,
+  /// The ids of the messages received above `received_below`.
+  received: BTreeSet<u64>,
+  /// The source of the current time.
+  clock: Box<dyn Clock + Send + Sync>,
+}
+
+impl Receipts {
+  /// Constructs a new empty `Receipts` state.
+  pub fn new() -> Self {
+    Self {
+      next_id: 0,
+      outbound: BTreeMap::new(),
+      received_below: 0,
+      received: BTreeSet::new(),
+      clock: Box::new(SystemClock,),
+    }
+  }
+  /// Sets the [Clock] used to time retransmissions.
+  /// 
+  /// The clock is not serialised; a deserialised `Receipts` reads the system time.
+  /// 
+  /// # Params
+  /// 
+  /// clock --- The source of the current time.  
+  #[inline]
+  pub fn set_clock<C,>(&mut self, clock: C,)
+    where C: 'static + Clock + Send + Sync, { self.clock = Box::new(clock,) }
+  /// Sends a message assigning it a new id.
+  /// 
+  /// The message is kept until it is acknowledged.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The `Client` to lock the message with.  
+  /// data --- The message data to send.  
+  pub fn send<C,>(&mut self, client: &mut C, data: &[u8],) -> Result<(u64, Message,), client::Error>
+    where C: Client, {
+    let id = self.next_id;
+    let message = client.lock_value(&Envelope::Data(id, data.to_vec(),),)?;
+
+    self.next_id += 1;
+    self.outbound.insert(id, Outbound { data: data.into(), sent: self.clock.now(), attempts: 1, },);
+
+    Ok((id, message,))
+  }
+  /// Sends a receipt for the passed message ids.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The `Client` to lock the receipt with.  
+  /// receipt --- The kind of receipt to send.  
+  /// ids --- The ids of the messages being acknowledged.  
+  #[inline]
+  pub fn receipt<C,>(&self, client: &mut C, receipt: Receipt, ids: &[u64],) -> Result<Message, client::Error>
+    where C: Client, {
+    client.lock_value(&Envelope::Receipt(receipt, ids.to_vec(),),)
+  }
+  /// Opens a message and processes its envelope.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The `Client` to open the message with.  
+  /// message --- The message to open.  
+  /// buffer --- The buffer to append the data of new messages too.  
+  #[inline]
+  pub fn open<C,>(&mut self, client: &mut C, message: Message, buffer: &mut Vec<u8>,) -> Result<Event, (Option<Message>, client::Error,)>
+    where C: Client, {
+    self.open_with_rng(client, message, buffer, &mut rand::thread_rng(),)
+  }
+  /// Opens a message and processes its envelope using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The `Client` to open the message with.  
+  /// message --- The message to open.  
+  /// buffer --- The buffer to append the data of new messages too.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  pub fn open_with_rng<C, Rand,>(&mut self, client: &mut C, message: Message, buffer: &mut Vec<u8>, rand: &mut Rand,) -> Result<Event, (Option<Message>, client::Error,)>
+    where C: Client,
+      Rand: RngCore + CryptoRng, {
+    let event = match client.open_value_with_rng(message, rand,)? {
+      Envelope::Data(id, ref data,) => if self.insert_received(id,) {
+        buffer.extend_from_slice(data,);
+
+        Event::Message(id,)
+      } else { Event::Duplicate(id,) },
+      Envelope::Receipt(receipt, ref ids,) => {
+        //Acknowledged messages no longer need to be retransmitted.
+        for id in ids { self.outbound.remove(id,); }
+
+        match receipt {
+          Receipt::Delivered => Event::Delivered(ids.clone(),),
+          Receipt::Read => Event::Read(ids.clone(),),
+        }
+      },
+    };
+
+    Ok(event)
+  }
+  /// Records a received message id returning `false` if it was already received.
+  /// 
+  /// # Params
+  /// 
+  /// id --- The id of the received message.  
+  fn insert_received(&mut self, id: u64,) -> bool {
+    if id < self.received_below || !self.received.insert(id,) { return false }
+
+    //Compact the contiguous ids.
+    while self.received.remove(&self.received_below,) { self.received_below += 1; }
+
+    true
+  }
+  /// The ids of the messages sent which have not been acknowledged.
+  #[inline]
+  pub fn unacknowledged(&self,) -> impl Iterator<Item = u64> + '_ { self.outbound.keys().copied() }
+  /// The ids of the unacknowledged messages which were last sent at least `timeout`
+  /// seconds ago.
+  /// 
+  /// # Params
+  /// 
+  /// timeout --- The number of seconds to wait for an acknowledgement.  
+  pub fn retransmissions(&self, timeout: u64,) -> Vec<u64> {
+    let now = self.clock.now();
+
+    self.outbound.iter()
+      .filter(|(_, outbound,),| outbound.sent.saturating_add(timeout,) <= now,)
+      .map(|(&id, _,),| id,)
+      .collect()
+  }
+  /// The number of times an unacknowledged message has been sent.
+  /// 
+  /// # Params
+  /// 
+  /// id --- The id of the message.  
+  #[inline]
+  pub fn attempts(&self, id: u64,) -> Option<u32> { self.outbound.get(&id,).map(|outbound,| outbound.attempts,) }
+  /// Locks an unacknowledged message again under a new key.
+  /// 
+  /// Returns `None` if the message has been acknowledged or was never sent.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The `Client` to lock the message with.  
+  /// id --- The id of the message to retransmit.  
+  pub fn retransmit<C,>(&mut self, client: &mut C, id: u64,) -> Option<Result<Message, client::Error>>
+    where C: Client, {
+    let now = self.clock.now();
+    let outbound = self.outbound.get_mut(&id,)?;
+    let message = client.lock_value(&Envelope::Data(id, outbound.data.to_vec(),),);
+
+    if message.is_ok() {
+      outbound.sent = now;
+      outbound.attempts += 1;
+    }
+
+    Some(message)
+  }
+  /// Stops tracking an unacknowledged message.
+  /// 
+  /// # Params
+  /// 
+  /// id --- The id of the message to forget.  
+  #[inline]
+  pub fn forget(&mut self, id: u64,) -> bool { self.outbound.remove(&id,).is_some() }
+}
+
+impl Default for Receipts {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{client::{LocalClient, RemoteClient,}, typenum::consts,};
+  use x25519_dalek::StaticSecret;
+  use sha1::Sha1;
+  use std::sync::{Arc, atomic::{AtomicU64, Ordering,},};
+
+  struct TestClock(Arc<AtomicU64>,);
+
+  impl Clock for TestClock {
+    fn now(&self,) -> u64 { self.0.load(Ordering::SeqCst,) }
+  }
+
+  #[test]
+  fn test_receipts() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,);
+    let mut remote = RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,);
+    let time = Arc::new(AtomicU64::new(100,),);
+    let mut sender = Receipts::new();
+    let mut receiver = Receipts::new();
+
+    sender.set_clock(TestClock(time.clone(),),);
+    let (first, message,) = sender.send(&mut local, &[1; 10],).expect("Error sending first message");
+    let (second, _,) = sender.send(&mut local, &[2; 10],).expect("Error sending second message");
+    let mut buffer = Vec::new();
+
+    assert_eq!(receiver.open(&mut remote, message, &mut buffer,), Ok(Event::Message(first,)), "Bad message event",);
+    assert_eq!(buffer, [1; 10], "Message data corrupted",);
+    assert_eq!(sender.unacknowledged().collect::<Vec<_>>(), vec![first, second,], "Bad unacknowledged messages",);
+
+    //The second message was dropped.
+    let receipt = receiver.receipt(&mut remote, Receipt::Delivered, &[first,],).expect("Error sending receipt");
+    assert_eq!(sender.open(&mut local, receipt, &mut buffer,), Ok(Event::Delivered(vec![first,],)), "Bad receipt event",);
+    assert_eq!(sender.unacknowledged().collect::<Vec<_>>(), vec![second,], "Delivered message not acknowledged",);
+
+    time.store(110, Ordering::SeqCst,);
+    assert_eq!(sender.retransmissions(30,), Vec::<u64>::new(), "Retransmitted too early",);
+    time.store(130, Ordering::SeqCst,);
+    assert_eq!(sender.retransmissions(30,), vec![second,], "Bad retransmission candidates",);
+
+    let message = sender.retransmit(&mut local, second,).expect("Message acknowledged")
+      .expect("Error retransmitting message");
+    let duplicate = sender.retransmit(&mut local, second,).expect("Message acknowledged")
+      .expect("Error retransmitting message");
+    assert_eq!(sender.retransmissions(30,), Vec::<u64>::new(), "Retransmitted message not rescheduled",);
+
+    assert_eq!(sender.attempts(second,), Some(3), "Bad attempt count",);
+    buffer.clear();
+    assert_eq!(receiver.open(&mut remote, message, &mut buffer,), Ok(Event::Message(second,)), "Bad retransmitted event",);
+    assert_eq!(buffer, [2; 10], "Retransmitted data corrupted",);
+    assert_eq!(receiver.open(&mut remote, duplicate, &mut buffer,), Ok(Event::Duplicate(second,)), "Duplicate not detected",);
+
+    let receipt = receiver.receipt(&mut remote, Receipt::Read, &[first, second,],).expect("Error sending receipt");
+    assert_eq!(sender.open(&mut local, receipt, &mut buffer,), Ok(Event::Read(vec![first, second,],)), "Bad read event",);
+    assert_eq!(sender.unacknowledged().count(), 0, "Read messages not acknowledged",);
+  }
+}
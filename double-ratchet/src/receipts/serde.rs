@@ -0,0 +1,135 @@
+//! Defines serde for Receipts.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+
+impl Serialize for Envelope {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    match self {
+      Envelope::Data(id, data,) => (0u8, [*id,], data,).serialize(serializer,),
+      Envelope::Receipt(Receipt::Delivered, ids,) => (1u8, ids, [0u8; 0],).serialize(serializer,),
+      Envelope::Receipt(Receipt::Read, ids,) => (2u8, ids, [0u8; 0],).serialize(serializer,),
+    }
+  }
+}
+
+impl<'de,> Deserialize<'de> for Envelope {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::{Error, Unexpected,};
+
+    let (kind, ids, data,) = <(u8, Vec<u64>, Vec<u8>,)>::deserialize(deserializer,)?;
+    let envelope = match kind {
+      0 => match ids.as_slice() {
+        &[id,] => Envelope::Data(id, data,),
+        _ => return Err(D::Error::invalid_length(ids.len(), &"a single message id",)),
+      },
+      1 => Envelope::Receipt(Receipt::Delivered, ids,),
+      2 => Envelope::Receipt(Receipt::Read, ids,),
+      _ => return Err(D::Error::invalid_value(Unexpected::Unsigned(kind as u64,), &"an envelope kind",)),
+    };
+
+    Ok(envelope)
+  }
+}
+
+static FIELDS: &[&str] = &[
+  "next_id",
+  "outbound",
+  "received_below",
+  "received",
+];
+
+impl Serialize for Receipts {
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    let mut serializer = serializer.serialize_tuple_struct(stringify!(Receipts,), FIELDS.len(),)?;
+    let outbound = self.outbound.iter()
+      .map(|(id, outbound,),| (id, &outbound.data, outbound.sent, outbound.attempts,),)
+      .collect::<Vec<_>>();
+
+    serializer.serialize_field(&self.next_id,)?;
+    serializer.serialize_field(&outbound,)?;
+    serializer.serialize_field(&self.received_below,)?;
+    serializer.serialize_field(&self.received,)?;
+    serializer.end()
+  }
+}
+
+impl<'de,> Deserialize<'de> for Receipts {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::Error;
+    use std::fmt;
+
+    struct ReceiptsVisitor;
+
+    impl<'de,> Visitor<'de> for ReceiptsVisitor {
+      type Value = Receipts;
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        write!(fmt, "a tuple of length {}", FIELDS.len(),)
+      }
+      fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+        where Acc: SeqAccess<'de>, {
+        let next_id = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+        let outbound = seq.next_element::<Vec<(u64, Box<[u8]>, u64, u32,)>>()?
+          .ok_or(Acc::Error::missing_field(FIELDS[1],),)?
+          .into_iter()
+          .map(|(id, data, sent, attempts,),| (id, Outbound { data, sent, attempts, },),)
+          .collect();
+        let received_below = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[2],),)?;
+        let received = seq.next_element()?
+          .ok_or(Acc::Error::missing_field(FIELDS[3],),)?;
+
+        Ok(Receipts { next_id, outbound, received_below, received, clock: Box::new(SystemClock,), })
+      }
+    }
+
+    deserializer.deserialize_tuple_struct(stringify!(Receipts,), FIELDS.len(), ReceiptsVisitor,)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_receipts_serde() {
+    let mut receipts = Receipts::new();
+
+    receipts.next_id = 3;
+    receipts.outbound.insert(2, Outbound { data: vec![1, 2, 3,].into(), sent: 100, attempts: 2, },);
+    receipts.insert_received(0,);
+    receipts.insert_received(4,);
+
+    let serialised = serde_cbor::ser::to_vec_packed(&receipts,)
+      .expect("Error serialising the Receipts");
+    let other: Receipts = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the Receipts");
+    let other_serialised = serde_cbor::ser::to_vec_packed(&other,)
+      .expect("Error serialising the Receipts");
+
+    assert_eq!(serialised, other_serialised, "Receipts deserialised incorrectly",);
+    assert_eq!(other.received_below, 1, "Bad received ids",);
+  }
+  #[test]
+  fn test_envelope_serde() {
+    let serialised = serde_cbor::ser::to_vec_packed(&Envelope::Data(7, vec![1, 2,],),)
+      .expect("Error serialising the Envelope");
+
+    match serde_cbor::from_slice(&serialised,).expect("Error deserialising the Envelope") {
+      Envelope::Data(7, ref data,) if data == &[1, 2,] => {},
+      _ => panic!("Envelope deserialised incorrectly"),
+    }
+  }
+}
@@ -0,0 +1,430 @@
+//! Defines a reorder buffer which releases opened messages in the order they were sent.
+//! 
+//! A [Client] opens messages in whatever order they arrive.
+//! [Reorder] buffers the plaintext of opened messages keyed on the ratchet step, index
+//! and previous step length of their headers and releases them in send order.
+//! A step is only released once the step before it is known; a step whose previous step
+//! never arrives is released once the wait elapses.
+//! If a message is missing for longer than the configured wait it is reported as a gap
+//! and skipped; a message which arrives after its gap was skipped is released as late.
+//! 
+//! The buffer is bounded: once [Reorder::max_buffered] messages are waiting gaps are
+//! skipped without waiting and only the last [MAX_FINISHED] released steps are remembered
+//! to report late messages.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  message::{Message, Header,},
+  client::{self, Client,},
+  clock::{Clock, SystemClock,},
+};
+use clear_on_drop::ClearOnDrop;
+use std::{collections::{HashMap, VecDeque,}, ops::Range, time::Duration, mem,};
+
+/// The number of fully released steps remembered to report late messages.
+/// 
+/// A message of a step released before the last `MAX_FINISHED` steps is treated as the
+/// first message of a new step.
+pub const MAX_FINISHED: usize = 64;
+/// The default number of messages which can wait for earlier messages.
+pub const MAX_BUFFERED: usize = 1024;
+
+/// The plaintext of a buffered message.
+struct Buffered(Vec<u8>,);
+
+impl Buffered {
+  /// Takes the plaintext out of the buffer.
+  #[inline]
+  fn take(mut self,) -> Vec<u8> { mem::replace(&mut self.0, Vec::new(),) }
+}
+
+impl Drop for Buffered {
+  #[inline]
+  fn drop(&mut self,) { ClearOnDrop::new(self.0.as_mut_slice(),); }
+}
+
+/// The delivery state of a ratchet step.
+struct Step {
+  /// The PublicKey of the step.
+  public_key: [u8; 32],
+  /// The index of the next message to release.
+  next_index: u32,
+  /// The number of messages in the step once a later step has started.
+  length: Option<u32>,
+  /// The number of messages in the step before this one.
+  previous_step: u32,
+}
+
+/// An event released by a [Reorder] buffer.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub enum Event {
+  /// The next message in send order.
+  Message(Header, Vec<u8>,),
+  /// A message whose position was already skipped as a gap.
+  Late(Header, Vec<u8>,),
+  /// Messages which were not received within the wait and have been skipped.
+  Gap {
+    /// The PublicKey of the step the messages belong too.
+    public_key: [u8; 32],
+    /// The indexes of the missing messages.
+    missing: Range<u32>,
+  },
+}
+
+/// A buffer releasing opened messages in send order.
+pub struct Reorder {
+  /// The steps which have not been fully released, oldest first.
+  steps: VecDeque<Step>,
+  /// The steps whose previous step has not been received, in arrival order.
+  held: Vec<Step>,
+  /// The PublicKeys of the last steps which have been fully released, oldest first.
+  finished: VecDeque<[u8; 32]>,
+  /// The plaintext of messages waiting for earlier messages.
+  buffered: HashMap<([u8; 32], u32,), (Header, Buffered,)>,
+  /// The number of buffered messages after which gaps are skipped without waiting.
+  max_buffered: usize,
+  /// Messages which can be released immediately.
+  ready: VecDeque<Event>,
+  /// The time to wait for a missing message.
+  wait: Duration,
+  /// The time in seconds since the UNIX epoch that delivery was blocked by a gap.
+  blocked: Option<u64>,
+  /// The source of the current time.
+  clock: Box<dyn Clock + Send + Sync>,
+}
+
+impl Reorder {
+  /// Constructs a new empty `Reorder` buffer.
+  /// 
+  /// # Params
+  /// 
+  /// wait --- The time to wait for a missing message before skipping it.  
+  pub fn new(wait: Duration,) -> Self {
+    Self {
+      steps: VecDeque::new(),
+      held: Vec::new(),
+      finished: VecDeque::new(),
+      buffered: HashMap::new(),
+      max_buffered: MAX_BUFFERED,
+      ready: VecDeque::new(),
+      wait,
+      blocked: None,
+      clock: Box::new(SystemClock,),
+    }
+  }
+  /// Sets the [Clock] used to time gaps.
+  /// 
+  /// # Params
+  /// 
+  /// clock --- The source of the current time.  
+  #[inline]
+  pub fn set_clock<C,>(&mut self, clock: C,)
+    where C: 'static + Clock + Send + Sync, { self.clock = Box::new(clock,) }
+  /// The number of messages waiting for earlier messages.
+  #[inline]
+  pub fn buffered(&self,) -> usize { self.buffered.len() }
+  /// The number of buffered messages after which gaps are skipped without waiting.
+  #[inline]
+  pub fn max_buffered(&self,) -> usize { self.max_buffered }
+  /// Sets the number of buffered messages after which gaps are skipped without waiting.
+  /// 
+  /// Once the buffer is full [Reorder::poll] releases the buffered messages, reporting
+  /// the messages still missing as gaps, until it is below the limit again.
+  /// 
+  /// # Params
+  /// 
+  /// max_buffered --- The number of messages which can wait for earlier messages.  
+  #[inline]
+  pub fn set_max_buffered(&mut self, max_buffered: usize,) { self.max_buffered = max_buffered }
+  /// Opens a message with `client` and buffers its plaintext.
+  /// 
+  /// # Params
+  /// 
+  /// client --- The `Client` to open the message with.  
+  /// message --- The message to open.  
+  pub fn open<C,>(&mut self, client: &mut C, message: Message,) -> Result<(), (Message, client::Error,)>
+    where C: Client, {
+    let header = message.header;
+    let mut buffer = Vec::new();
+
+    client.open(message, &mut buffer,)?;
+    self.push(header, buffer,);
+
+    Ok(())
+  }
+  /// Buffers the plaintext of an opened message.
+  /// 
+  /// The buffer only shrinks as events are polled so messages should be polled after
+  /// each push.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the opened message.  
+  /// data --- The plaintext of the opened message.  
+  pub fn push(&mut self, header: Header, data: Vec<u8>,) {
+    let data = Buffered(data,);
+
+    //The step has already been released.
+    if self.finished.contains(&header.public_key,) {
+      return self.ready.push_back(Event::Late(header, data.take(),),)
+    }
+
+    let position = self.steps.iter().position(|step,| step.public_key == header.public_key,);
+
+    //The message starts a new step.
+    if position.is_none() && !self.held.iter().any(|step,| step.public_key == header.public_key,) {
+      let step = Step {
+        public_key: header.public_key,
+        next_index: 0,
+        length: None,
+        previous_step: header.previous_step,
+      };
+
+      if self.follows(step.previous_step,) { self.link(step,) }
+      else { self.held.push(step,) }
+    }
+
+    //The position of the message has already been released or skipped.
+    if position == Some(0) && header.message_index < self.steps[0].next_index {
+      return self.ready.push_back(Event::Late(header, data.take(),),)
+    }
+
+    self.buffered.insert((header.public_key, header.message_index,), (header, data,),);
+  }
+  /// Returns `true` if a step can follow the last known step.
+  /// 
+  /// # Params
+  /// 
+  /// previous_step --- The length of the step before the step.  
+  fn follows(&self, previous_step: u32,) -> bool {
+    match self.steps.back() {
+      //Only the first step has no step before it.
+      None => previous_step == 0,
+      //The last step cannot have released or received messages past its length.
+      Some(last) => last.length.map_or(true, |length,| length == previous_step,)
+        && last.next_index <= previous_step
+        && !self.buffered.keys()
+          .any(|&(public_key, index,),| public_key == last.public_key && index >= previous_step,),
+    }
+  }
+  /// Appends a step after the last known step and links any held steps which follow it.
+  /// 
+  /// # Params
+  /// 
+  /// step --- The step to append.  
+  fn link(&mut self, mut step: Step,) {
+    loop {
+      //The header of a message records the length of the step before it.
+      if let Some(last) = self.steps.back_mut() {
+        if last.length.is_none() { last.length = Some(step.previous_step); }
+      }
+
+      self.steps.push_back(step,);
+
+      step = match self.held.iter().position(|held,| self.follows(held.previous_step,),) {
+        Some(position) => self.held.remove(position,),
+        None => break,
+      };
+    }
+  }
+  /// Returns `true` if delivery has been blocked for longer than the wait or the buffer
+  /// is full.
+  fn elapsed(&mut self,) -> bool {
+    if self.buffered.len() >= self.max_buffered { return true }
+
+    let now = self.clock.now();
+    let blocked = *self.blocked.get_or_insert(now,);
+
+    now >= blocked.saturating_add(self.wait.as_secs(),)
+  }
+  /// Releases the next event.
+  /// 
+  /// Returns `None` if the next message has not been received and the wait for it has
+  /// not elapsed.
+  pub fn poll(&mut self,) -> Option<Event> {
+    if let Some(event) = self.ready.pop_front() { return Some(event) }
+
+    loop {
+      //Only steps whose previous step has not been received are waiting.
+      if self.steps.is_empty() {
+        if self.held.is_empty() || !self.elapsed() { return None }
+
+        let step = self.held.remove(0,);
+
+        self.blocked = None;
+        self.link(step,);
+        continue;
+      }
+
+      let steps = self.steps.len();
+      let step = self.steps.front_mut()?;
+
+      //Release the next message.
+      if let Some((header, data,)) = self.buffered.remove(&(step.public_key, step.next_index,),) {
+        step.next_index += 1;
+        self.blocked = None;
+
+        return Some(Event::Message(header, data.take(),))
+      }
+      //Move to the next step once this step is complete.
+      if step.length.map_or(false, |length,| step.next_index >= length,) && steps > 1 {
+        let public_key = step.public_key;
+
+        self.steps.pop_front();
+        //Forget the oldest released step.
+        if self.finished.len() >= MAX_FINISHED { self.finished.pop_front(); }
+        self.finished.push_back(public_key,);
+        continue;
+      }
+      //Nothing is waiting for the missing message.
+      if self.buffered.is_empty() { self.blocked = None; return None }
+
+      if !self.elapsed() { return None }
+
+      let (public_key, start,) = (self.steps[0].public_key, self.steps[0].next_index,);
+      //Skip the missing messages up to the next buffered message of the step.
+      let end = self.buffered.keys()
+        .filter(|(key, _,),| *key == public_key,)
+        .map(|&(_, index,),| index,)
+        .min()
+        .or(self.steps[0].length,);
+      let end = match end {
+        Some(end) => end,
+        //The step after this one is held; its header records the length of this step.
+        None if !self.held.is_empty() => {
+          let step = self.held.remove(0,);
+          let end = step.previous_step.max(start,);
+
+          self.link(step,);
+          end
+        },
+        None => start + 1,
+      };
+
+      self.blocked = None;
+      if end <= start { continue }
+
+      self.steps[0].next_index = end;
+
+      return Some(Event::Gap { public_key, missing: start..end, })
+    }
+  }
+}
+
+impl Iterator for Reorder {
+  type Item = Event;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.poll() }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use std::sync::{Arc, atomic::{AtomicU64, Ordering,},};
+
+  struct TestClock(Arc<AtomicU64>,);
+
+  impl Clock for TestClock {
+    fn now(&self,) -> u64 { self.0.load(Ordering::SeqCst,) }
+  }
+
+  fn header(public_key: u8, message_index: u32, previous_step: u32,) -> Header {
+    Header { public_key: [public_key; 32], message_index, previous_step, }
+  }
+
+  #[test]
+  fn test_reorder() {
+    let time = Arc::new(AtomicU64::new(100,),);
+    let mut reorder = Reorder::new(Duration::from_secs(10,),);
+
+    reorder.set_clock(TestClock(time.clone(),),);
+    reorder.push(header(1, 1, 0,), vec![2],);
+    assert_eq!(reorder.poll(), None, "Released a message out of order",);
+
+    reorder.push(header(1, 0, 0,), vec![1],);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 0, 0,), vec![1],)), "Bad first message",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 1, 0,), vec![2],)), "Bad second message",);
+
+    //The fourth message of the step and the first of the next step arrive.
+    reorder.push(header(2, 0, 4,), vec![5],);
+    reorder.push(header(1, 3, 0,), vec![4],);
+    assert_eq!(reorder.poll(), None, "Skipped a gap early",);
+
+    time.store(110, Ordering::SeqCst,);
+    assert_eq!(reorder.poll(), Some(Event::Gap { public_key: [1; 32], missing: 2..3, }), "Bad gap",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 3, 0,), vec![4],)), "Bad message after gap",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(2, 0, 4,), vec![5],)), "Bad message of next step",);
+
+    //The skipped message arrives late.
+    reorder.push(header(1, 2, 0,), vec![3],);
+    assert_eq!(reorder.poll(), Some(Event::Late(header(1, 2, 0,), vec![3],)), "Bad late message",);
+    assert_eq!(reorder.poll(), None, "Released a message which was not received",);
+  }
+  #[test]
+  fn test_reorder_steps() {
+    let time = Arc::new(AtomicU64::new(100,),);
+    let mut reorder = Reorder::new(Duration::from_secs(10,),);
+
+    reorder.set_clock(TestClock(time.clone(),),);
+    //The first message of the second step arrives before the first step.
+    reorder.push(header(2, 0, 2,), vec![3],);
+    assert_eq!(reorder.poll(), None, "Released a step before its previous step",);
+
+    reorder.push(header(1, 0, 0,), vec![1],);
+    reorder.push(header(1, 1, 0,), vec![2],);
+    reorder.push(header(2, 1, 2,), vec![4],);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 0, 0,), vec![1],)), "Bad first message",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 1, 0,), vec![2],)), "Bad second message",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(2, 0, 2,), vec![3],)), "Bad third message",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(2, 1, 2,), vec![4],)), "Bad fourth message",);
+    assert_eq!(reorder.poll(), None, "Released a message which was not received",);
+
+    //The previous step of a step never arrives.
+    let mut reorder = Reorder::new(Duration::from_secs(10,),);
+
+    reorder.set_clock(TestClock(time.clone(),),);
+    reorder.push(header(4, 0, 3,), vec![5],);
+    assert_eq!(reorder.poll(), None, "Released a step before its previous step",);
+
+    time.store(120, Ordering::SeqCst,);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(4, 0, 3,), vec![5],)), "Bad held message",);
+  }
+  #[test]
+  fn test_reorder_bounds() {
+    let time = Arc::new(AtomicU64::new(100,),);
+    let mut reorder = Reorder::new(Duration::from_secs(10,),);
+
+    reorder.set_clock(TestClock(time.clone(),),);
+    reorder.set_max_buffered(2,);
+    reorder.push(header(1, 1, 0,), vec![2],);
+    assert_eq!(reorder.poll(), None, "Skipped a gap early",);
+
+    //A full buffer skips the gap without waiting.
+    reorder.push(header(1, 2, 0,), vec![3],);
+    assert_eq!(reorder.poll(), Some(Event::Gap { public_key: [1; 32], missing: 0..1, }), "Bad gap",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 1, 0,), vec![2],)), "Bad first message",);
+    assert_eq!(reorder.poll(), Some(Event::Message(header(1, 2, 0,), vec![3],)), "Bad second message",);
+    assert_eq!(reorder.buffered(), 0, "Messages still buffered",);
+
+    //Only the last released steps are remembered.
+    let mut reorder = Reorder::new(Duration::from_secs(10,),);
+
+    reorder.set_clock(TestClock(time.clone(),),);
+    reorder.push(header(0, 0, 0,), vec![0],);
+    for step in 1..=(MAX_FINISHED + 2) as u8 {
+      reorder.push(header(step, 0, 1,), vec![step],);
+    }
+    for step in 0..=(MAX_FINISHED + 2) as u8 {
+      let previous_step = if step == 0 { 0 } else { 1 };
+
+      assert_eq!(reorder.poll(), Some(Event::Message(header(step, 0, previous_step,), vec![step],)), "Bad message",);
+    }
+    assert_eq!(reorder.finished.len(), MAX_FINISHED, "Finished steps not bounded",);
+
+    reorder.push(header(MAX_FINISHED as u8 + 1, 1, 1,), vec![0],);
+    assert_eq!(reorder.poll(), Some(Event::Late(header(MAX_FINISHED as u8 + 1, 1, 1,), vec![0],)), "Bad late message",);
+  }
+}
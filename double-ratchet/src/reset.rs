@@ -0,0 +1,469 @@
+//! Defines the protocol used to reset a session after its Clients have diverged.
+//! 
+//! Once a Client reports [client::Error::Desync](crate::client::Error::Desync) neither
+//! Client can open the messages of the other.
+//! The Client which detects the divergence starts a [Reset] and sends its [ResetRequest];
+//! the partner answers with a [ResetResponse] and both ends construct fresh Clients.
+//! Both control messages are signed with the identity keys of their senders and carry an
+//! ephemeral key so the new session does not depend on the diverged state.
+//! Requests are timestamped; a request is only accepted within [RESET_WINDOW] of its
+//! creation and when it is newer than the last request accepted from the same Client so
+//! that a recorded request cannot be replayed to reset the session again.
+//! `Reset::complete_with_store` checks the identity of the responding Client against an
+//! [IdentityStore](crate::identity::IdentityStore) instead of an expected key.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  ratchet::Ratchet,
+  client::{LocalClient, RemoteClient, aead::Algorithm,},
+  clock::{Clock, SystemClock,},
+  identity::{self, IdentityStore, Trust,},
+  generic_array::{ArrayLength, GenericArray,},
+  typenum::consts::U64,
+  xeddsa::{self, Signature,},
+};
+use clear_on_drop::ClearOnDrop;
+use ring::constant_time;
+use rand::{RngCore, CryptoRng,};
+use x25519_dalek::{PublicKey, StaticSecret,};
+use std::hash::Hash;
+
+mod serde;
+
+/// The context signed by a [ResetRequest].
+const REQUEST_CONTEXT: &[u8] = b"DoubleRatchetResetRequest";
+/// The context signed by a [ResetResponse].
+const RESPONSE_CONTEXT: &[u8] = b"DoubleRatchetResetResponse";
+/// The number of seconds either side of its timestamp a [ResetRequest] is accepted for.
+pub const RESET_WINDOW: u64 = 300;
+
+/// Builds the data signed by a control message.
+/// 
+/// # Params
+/// 
+/// context --- The type of control message.  
+/// keys --- The keys carried by the control message.  
+fn signed_data(context: &[u8], keys: &[&[u8; 32]],) -> Vec<u8> {
+  let mut data = Vec::with_capacity(context.len() + keys.len() * 32,);
+
+  data.extend_from_slice(context,);
+  for key in keys { data.extend_from_slice(key.as_ref(),); }
+
+  data
+}
+
+/// Builds the data signed by a [ResetRequest].
+/// 
+/// # Params
+/// 
+/// identity --- The identity public key of the requesting Client.  
+/// ephemeral --- The ephemeral public key of the requesting Client.  
+/// timestamp --- The time in seconds since the UNIX epoch the request was created.  
+fn request_data(identity: &[u8; 32], ephemeral: &[u8; 32], timestamp: u64,) -> Vec<u8> {
+  let mut data = signed_data(REQUEST_CONTEXT, &[identity, ephemeral,],);
+
+  data.extend_from_slice(&timestamp.to_be_bytes(),);
+  data
+}
+
+/// Derives the seed of the new session.
+/// 
+/// # Params
+/// 
+/// private_key --- The identity private key of this Client.  
+/// remote --- The identity public key of the remote Client.  
+/// ephemeral --- The ephemeral private key of this Client.  
+/// remote_ephemeral --- The ephemeral public key of the remote Client.  
+fn seed(private_key: &StaticSecret, remote: &PublicKey, ephemeral: &StaticSecret, remote_ephemeral: &PublicKey,) -> ClearOnDrop<GenericArray<u8, U64>> {
+  let mut seed = ClearOnDrop::new(GenericArray::default(),);
+
+  seed[..32].copy_from_slice(private_key.diffie_hellman(remote,).as_bytes(),);
+  seed[32..].copy_from_slice(ephemeral.diffie_hellman(remote_ephemeral,).as_bytes(),);
+
+  seed
+}
+
+/// Checks the identity and signature of a control message.
+/// 
+/// # Params
+/// 
+/// remote --- The expected identity public key of the sender.  
+/// identity --- The identity public key carried by the message.  
+/// data --- The signed data.  
+/// signature --- The signature of the message.  
+fn verify(remote: &PublicKey, identity: &[u8; 32], data: &[u8], signature: &Signature,) -> Result<(), Error> {
+  constant_time::verify_slices_are_equal(remote.as_bytes(), identity,)
+    .map_err(|_,| Error::UnknownIdentity,)?;
+
+  xeddsa::verify(remote, data, signature,).map_err(|_,| Error::InvalidSignature,)
+}
+
+/// The control message requesting a session reset.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct ResetRequest {
+  /// The identity public key of the requesting Client.
+  identity: [u8; 32],
+  /// The ephemeral public key of the requesting Client.
+  ephemeral: [u8; 32],
+  /// The time in seconds since the UNIX epoch the request was created.
+  timestamp: u64,
+  /// The signature over the request.
+  signature: Signature,
+}
+
+impl ResetRequest {
+  /// The identity public key of the requesting Client.
+  #[inline]
+  pub fn identity(&self,) -> PublicKey { self.identity.into() }
+  /// The time in seconds since the UNIX epoch the request was created.
+  /// 
+  /// The timestamp of an accepted request should be stored and passed as `last` when
+  /// responding to the next request of the same Client.
+  #[inline]
+  pub fn timestamp(&self,) -> u64 { self.timestamp }
+  /// Checks that the request was signed by the expected Client.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The identity public key of the requesting Client.  
+  pub fn verify(&self, remote: &PublicKey,) -> Result<(), Error> {
+    let data = request_data(&self.identity, &self.ephemeral, self.timestamp,);
+
+    verify(remote, &self.identity, &data, &self.signature,)
+  }
+  /// Checks that the request is fresh.
+  /// 
+  /// # Params
+  /// 
+  /// now --- The current time in seconds since the UNIX epoch.  
+  /// last --- The timestamp of the last request accepted from the requesting Client.  
+  pub fn check_fresh(&self, now: u64, last: Option<u64>,) -> Result<(), Error> {
+    if self.timestamp.saturating_add(RESET_WINDOW,) < now
+      || now.saturating_add(RESET_WINDOW,) < self.timestamp { return Err(Error::Expired) }
+    if last.map_or(false, |last,| self.timestamp <= last,) { return Err(Error::Replayed) }
+
+    Ok(())
+  }
+  /// Accepts the request and constructs the new Client of the responding end.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The identity public key of the requesting Client.  
+  /// private_key --- The identity private key of this Client.  
+  /// last --- The timestamp of the last request accepted from the requesting Client.  
+  #[inline]
+  pub fn respond<D, S, A, R, L,>(&self, remote: &PublicKey, private_key: &StaticSecret, last: Option<u64>,) -> Result<(ResetResponse, RemoteClient<D, S, A, R, L,>,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng, {
+    self.respond_with_clock_and_rng(remote, private_key, last, &SystemClock, &mut rand::thread_rng(),)
+  }
+  /// Accepts the request and constructs the new Client of the responding end using the
+  /// passed source of time and randomness.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The identity public key of the requesting Client.  
+  /// private_key --- The identity private key of this Client.  
+  /// last --- The timestamp of the last request accepted from the requesting Client.  
+  /// clock --- The source of the current time.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn respond_with_clock_and_rng<D, S, A, R, L, Rand,>(&self, remote: &PublicKey, private_key: &StaticSecret, last: Option<u64>, clock: &dyn Clock, rand: &mut Rand,) -> Result<(ResetResponse, RemoteClient<D, S, A, R, L,>,), Error>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      Rand: RngCore + CryptoRng, {
+    self.verify(remote,)?;
+    self.check_fresh(clock.now(), last,)?;
+
+    let ephemeral = StaticSecret::new(rand,);
+    let response = {
+      let identity = *PublicKey::from(private_key,).as_bytes();
+      let ephemeral = *PublicKey::from(&ephemeral,).as_bytes();
+      let data = signed_data(RESPONSE_CONTEXT, &[&identity, &ephemeral, &self.ephemeral,],);
+      let signature = xeddsa::sign_with_rng(private_key, &data, rand,);
+
+      ResetResponse { identity, ephemeral, signature, }
+    };
+    let mut seed = seed(private_key, remote, &ephemeral, &self.ephemeral.into(),);
+    let client = RemoteClient::from_seed_with_rng(seed.as_mut_slice(), remote, private_key, rand,);
+
+    Ok((response, client,))
+  }
+}
+
+/// The control message accepting a session reset.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct ResetResponse {
+  /// The identity public key of the responding Client.
+  identity: [u8; 32],
+  /// The ephemeral public key of the responding Client.
+  ephemeral: [u8; 32],
+  /// The signature over the response and the ephemeral key of the request.
+  signature: Signature,
+}
+
+impl ResetResponse {
+  /// The identity public key of the responding Client.
+  #[inline]
+  pub fn identity(&self,) -> PublicKey { self.identity.into() }
+  /// Checks that the response was signed by the expected Client in answer to `request`.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The identity public key of the responding Client.  
+  /// request --- The request being answered.  
+  pub fn verify(&self, remote: &PublicKey, request: &ResetRequest,) -> Result<(), Error> {
+    let data = signed_data(RESPONSE_CONTEXT, &[&self.identity, &self.ephemeral, &request.ephemeral,],);
+
+    verify(remote, &self.identity, &data, &self.signature,)
+  }
+}
+
+/// A reset started by this Client which is waiting for a [ResetResponse].
+pub struct Reset {
+  /// The ephemeral private key of this Client.
+  ephemeral: StaticSecret,
+  /// The request sent to the remote Client.
+  request: ResetRequest,
+}
+
+impl Reset {
+  /// Starts a new reset.
+  /// 
+  /// # Params
+  /// 
+  /// private_key --- The identity private key of this Client.  
+  #[inline]
+  pub fn new(private_key: &StaticSecret,) -> Self {
+    Self::new_with_clock_and_rng(private_key, &SystemClock, &mut rand::thread_rng(),)
+  }
+  /// Starts a new reset using the passed source of time and randomness.
+  /// 
+  /// # Params
+  /// 
+  /// private_key --- The identity private key of this Client.  
+  /// clock --- The source of the current time.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn new_with_clock_and_rng<Rand,>(private_key: &StaticSecret, clock: &dyn Clock, rand: &mut Rand,) -> Self
+    where Rand: RngCore + CryptoRng, {
+    let ephemeral = StaticSecret::new(rand,);
+    let request = {
+      let identity = *PublicKey::from(private_key,).as_bytes();
+      let ephemeral = *PublicKey::from(&ephemeral,).as_bytes();
+      let timestamp = clock.now();
+      let data = request_data(&identity, &ephemeral, timestamp,);
+      let signature = xeddsa::sign_with_rng(private_key, &data, rand,);
+
+      ResetRequest { identity, ephemeral, timestamp, signature, }
+    };
+
+    Self { ephemeral, request, }
+  }
+  /// The request to send to the remote Client.
+  #[inline]
+  pub fn request(&self,) -> &ResetRequest { &self.request }
+  /// Completes the reset and constructs the new Client of the requesting end.
+  /// 
+  /// If the response is rejected the `Reset` can be retried with another response.
+  /// 
+  /// # Params
+  /// 
+  /// response --- The response of the remote Client.  
+  /// remote --- The identity public key of the remote Client.  
+  /// private_key --- The identity private key of this Client.  
+  #[inline]
+  pub fn complete<D, S, A, R, L,>(self, response: &ResetResponse, remote: &PublicKey, private_key: &StaticSecret,) -> Result<LocalClient<D, S, A, R, L,>, (Self, Error,)>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng, {
+    self.complete_with_rng(response, remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Completes the reset and constructs the new Client of the requesting end using the
+  /// passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// response --- The response of the remote Client.  
+  /// remote --- The identity public key of the remote Client.  
+  /// private_key --- The identity private key of this Client.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn complete_with_rng<D, S, A, R, L, Rand,>(self, response: &ResetResponse, remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Result<LocalClient<D, S, A, R, L,>, (Self, Error,)>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      Rand: RngCore + CryptoRng, {
+    if let Err(e) = response.verify(remote, &self.request,) { return Err((self, e,)) }
+
+    let mut seed = seed(private_key, remote, &self.ephemeral, &response.ephemeral.into(),);
+
+    Ok(LocalClient::from_seed_with_rng(seed.as_mut_slice(), remote, private_key, rand,))
+  }
+  /// Completes the reset checking the identity of the responding Client against `store`.
+  /// 
+  /// The response is refused with [Error::Identity] if its identity does not match the
+  /// key recorded for `peer`.
+  /// 
+  /// # Params
+  /// 
+  /// response --- The response of the remote Client.  
+  /// store --- The recorded identities of known peers.  
+  /// peer --- The peer the reset was sent to.  
+  /// private_key --- The identity private key of this Client.  
+  #[inline]
+  pub fn complete_with_store<D, S, A, R, L, P,>(self, response: &ResetResponse, store: &mut IdentityStore<P,>, peer: P, private_key: &StaticSecret,) -> Result<(LocalClient<D, S, A, R, L,>, Trust,), (Self, Error,)>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      P: Eq + Hash, {
+    self.complete_with_store_and_rng(response, store, peer, private_key, &mut rand::thread_rng(),)
+  }
+  /// Completes the reset checking the identity of the responding Client against `store`
+  /// using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// response --- The response of the remote Client.  
+  /// store --- The recorded identities of known peers.  
+  /// peer --- The peer the reset was sent to.  
+  /// private_key --- The identity private key of this Client.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn complete_with_store_and_rng<D, S, A, R, L, P, Rand,>(self, response: &ResetResponse, store: &mut IdentityStore<P,>, peer: P, private_key: &StaticSecret, rand: &mut Rand,) -> Result<(LocalClient<D, S, A, R, L,>, Trust,), (Self, Error,)>
+    where S: 'static + ArrayLength<u8>,
+      A: Algorithm,
+      L: 'static + ArrayLength<u8>,
+      Ratchet<D, S, R,>: RngCore + CryptoRng,
+      P: Eq + Hash,
+      Rand: RngCore + CryptoRng, {
+    let remote = response.identity();
+
+    //The response is authenticated before its identity is recorded.
+    if let Err(e) = response.verify(&remote, &self.request,) { return Err((self, e,)) }
+
+    let trust = match store.check(peer, &remote,) {
+      Ok(trust) => trust,
+      Err(e) => return Err((self, Error::Identity(e,),)),
+    };
+
+    self.complete_with_rng(response, &remote, private_key, rand,).map(|client,| (client, trust,),)
+  }
+}
+
+/// An error returned from checking a reset control message.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Error {
+  /// The control message was sent by a different identity to the expected Client.
+  UnknownIdentity,
+  /// The signature of the control message is invalid.
+  InvalidSignature,
+  /// The request was created outside of the [RESET_WINDOW] of the current time.
+  Expired,
+  /// The request is not newer than the last request accepted from the same Client.
+  Replayed,
+  /// The identity of the control message did not match the [IdentityStore].
+  Identity(identity::Error,),
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{client::{Client, aead::Aes256Gcm,}, typenum::consts,};
+  use sha1::Sha1;
+
+  #[test]
+  fn test_reset() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let other_sec = StaticSecret::from([3; 32],);
+    let local_pub = PublicKey::from(&local_sec,);
+    let remote_pub = PublicKey::from(&remote_sec,);
+
+    let reset = Reset::new(&local_sec,);
+    assert_eq!(reset.request().verify(&PublicKey::from(&other_sec,),), Err(Error::UnknownIdentity), "Accepted an unknown identity",);
+    let forged = Reset::new(&other_sec,);
+    let forged = ResetRequest { identity: *local_pub.as_bytes(), ..forged.request().clone() };
+    assert_eq!(forged.verify(&local_pub,), Err(Error::InvalidSignature), "Accepted a forged request",);
+
+    let (response, mut remote,) = reset.request().respond::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(&local_pub, &remote_sec, None,)
+      .expect("Error responding to the request");
+    let mut local = match reset.complete::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(&response, &remote_pub, &local_sec,) {
+      Ok(v) => v,
+      Err(_) => panic!("Error completing the reset"),
+    };
+
+    let msg = [1; 10];
+    let message = local.lock(&mut msg.clone(),).expect("Error locking message");
+    let mut buffer = Vec::new();
+    remote.open(message, &mut buffer,).expect("Error opening message");
+    assert_eq!(buffer, msg.as_ref(), "Message corrupted",);
+
+    let message = remote.lock(&mut msg.clone(),).expect("Error locking reply");
+    buffer.clear();
+    local.open(message, &mut buffer,).expect("Error opening reply");
+    assert_eq!(buffer, msg.as_ref(), "Reply corrupted",);
+  }
+  #[test]
+  fn test_reset_store() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let local_pub = PublicKey::from(&local_sec,);
+    let mut store = IdentityStore::new();
+
+    //A different key was recorded for the responder.
+    store.check("remote", &PublicKey::from(&StaticSecret::from([3; 32],),),).expect("Error recording key");
+
+    let reset = Reset::new(&local_sec,);
+    let (response, _,) = reset.request().respond::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(&local_pub, &remote_sec, None,)
+      .expect("Error responding to the request");
+    let reset = match reset.complete_with_store::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0, _,>(&response, &mut store, "remote", &local_sec,) {
+      Err((reset, Error::Identity(identity::Error::KeyChanged { .. }),)) => reset,
+      _ => panic!("Completed a reset with a changed identity"),
+    };
+
+    store.accept_change("remote", &PublicKey::from(&remote_sec,),);
+    match reset.complete_with_store::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0, _,>(&response, &mut store, "remote", &local_sec,) {
+      Ok((_, Trust::Known,)) => {},
+      _ => panic!("Error completing the reset"),
+    }
+  }
+  #[test]
+  fn test_reset_replay() {
+    struct TestClock(u64,);
+
+    impl Clock for TestClock {
+      fn now(&self,) -> u64 { self.0 }
+    }
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let local_pub = PublicKey::from(&local_sec,);
+    let respond = |request: &ResetRequest, last, now,| request.respond_with_clock_and_rng::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0, _,>(
+      &local_pub, &remote_sec, last, &TestClock(now,), &mut rand::thread_rng(),
+    ).map(|_,| (),);
+
+    let reset = Reset::new_with_clock_and_rng(&local_sec, &TestClock(1000,), &mut rand::thread_rng(),);
+    let request = reset.request();
+    assert_eq!(request.timestamp(), 1000, "Bad request timestamp",);
+    assert_eq!(respond(request, None, 1000 + RESET_WINDOW,), Ok(()), "Fresh request rejected",);
+
+    //A request recorded by an attacker cannot reset the session again.
+    assert_eq!(respond(request, Some(request.timestamp()), 1000,), Err(Error::Replayed), "Replayed request accepted",);
+    assert_eq!(respond(request, None, 1001 + RESET_WINDOW,), Err(Error::Expired), "Expired request accepted",);
+    assert_eq!(respond(request, None, 999 - RESET_WINDOW,), Err(Error::Expired), "Future request accepted",);
+
+    //The timestamp is signed.
+    let forged = ResetRequest { timestamp: 2000, ..request.clone() };
+    assert_eq!(respond(&forged, Some(request.timestamp()), 2000,), Err(Error::InvalidSignature), "Accepted a forged timestamp",);
+
+    //A newer request is accepted.
+    let reset = Reset::new_with_clock_and_rng(&local_sec, &TestClock(1001,), &mut rand::thread_rng(),);
+    assert_eq!(respond(reset.request(), Some(request.timestamp()), 1001,), Ok(()), "Newer request rejected",);
+  }
+}
@@ -0,0 +1,148 @@
+//! Defines serde for the reset control messages.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer, SerializeTupleStruct,},
+  de::{Deserialize, Deserializer, SeqAccess, Visitor,},
+};
+
+mod request {
+  use super::*;
+
+  static FIELDS: &[&str] = &[
+    "identity",
+    "ephemeral",
+    "timestamp",
+    "signature",
+  ];
+
+  impl Serialize for ResetRequest {
+    fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+      where S: Serializer, {
+      let mut serializer = serializer.serialize_tuple_struct(stringify!(ResetRequest,), FIELDS.len(),)?;
+
+      serializer.serialize_field(&self.identity,)?;
+      serializer.serialize_field(&self.ephemeral,)?;
+      serializer.serialize_field(&self.timestamp,)?;
+      serializer.serialize_field(&self.signature,)?;
+      serializer.end()
+    }
+  }
+
+  impl<'de,> Deserialize<'de> for ResetRequest {
+    fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+      where D: Deserializer<'de>, {
+      use ::serde::de::Error;
+      use std::fmt;
+
+      struct ResetRequestVisitor;
+
+      impl<'de,> Visitor<'de> for ResetRequestVisitor {
+        type Value = ResetRequest;
+
+        fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+          write!(fmt, "a tuple of length {}", FIELDS.len(),)
+        }
+        fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+          where Acc: SeqAccess<'de>, {
+          let identity = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+          let ephemeral = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+          let timestamp = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[2],),)?;
+          let signature = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[3],),)?;
+
+          Ok(ResetRequest { identity, ephemeral, timestamp, signature, })
+        }
+      }
+
+      deserializer.deserialize_tuple_struct(stringify!(ResetRequest,), FIELDS.len(), ResetRequestVisitor,)
+    }
+  }
+}
+
+mod response {
+  use super::*;
+
+  static FIELDS: &[&str] = &[
+    "identity",
+    "ephemeral",
+    "signature",
+  ];
+
+  impl Serialize for ResetResponse {
+    fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+      where S: Serializer, {
+      let mut serializer = serializer.serialize_tuple_struct(stringify!(ResetResponse,), FIELDS.len(),)?;
+
+      serializer.serialize_field(&self.identity,)?;
+      serializer.serialize_field(&self.ephemeral,)?;
+      serializer.serialize_field(&self.signature,)?;
+      serializer.end()
+    }
+  }
+
+  impl<'de,> Deserialize<'de> for ResetResponse {
+    fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+      where D: Deserializer<'de>, {
+      use ::serde::de::Error;
+      use std::fmt;
+
+      struct ResetResponseVisitor;
+
+      impl<'de,> Visitor<'de> for ResetResponseVisitor {
+        type Value = ResetResponse;
+
+        fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+          write!(fmt, "a tuple of length {}", FIELDS.len(),)
+        }
+        fn visit_seq<Acc,>(self, mut seq: Acc,) -> Result<Self::Value, Acc::Error>
+          where Acc: SeqAccess<'de>, {
+          let identity = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[0],),)?;
+          let ephemeral = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[1],),)?;
+          let signature = seq.next_element()?
+            .ok_or(Acc::Error::missing_field(FIELDS[2],),)?;
+
+          Ok(ResetResponse { identity, ephemeral, signature, })
+        }
+      }
+
+      deserializer.deserialize_tuple_struct(stringify!(ResetResponse,), FIELDS.len(), ResetResponseVisitor,)
+    }
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{client::aead::Aes256Gcm, typenum::consts,};
+  use sha1::Sha1;
+
+  #[test]
+  fn test_reset_serde() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let reset = Reset::new(&local_sec,);
+    let (response, _,) = reset.request().respond::<Sha1, consts::U64, Aes256Gcm, consts::U1, consts::U0,>(&(&local_sec).into(), &remote_sec, None,)
+      .expect("Error responding to the request");
+
+    let serialised = serde_cbor::ser::to_vec_packed(reset.request(),)
+      .expect("Error serialising the ResetRequest");
+    let other: ResetRequest = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the ResetRequest");
+    assert_eq!(reset.request(), &other, "ResetRequest deserialised incorrectly",);
+
+    let serialised = serde_cbor::ser::to_vec_packed(&response,)
+      .expect("Error serialising the ResetResponse");
+    let other: ResetResponse = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the ResetResponse");
+    assert_eq!(response, other, "ResetResponse deserialised incorrectly",);
+  }
+}
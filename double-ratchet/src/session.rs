@@ -0,0 +1,378 @@
+//! Defines the [Session] type used to establish communication without agreeing on roles.
+//! 
+//! A [LocalClient] must always be paired with a [RemoteClient]; if both partners call
+//! `LocalClient::connect` at once neither can open the messages of the other.
+//! `Session::establish` can be called by both partners at any time and deterministically
+//! picks the role of each end by comparing their public keys.
+//! 
+//! If the remote Client initiated communication itself by calling `connect` while the
+//! `Session` took the `Local` role, the collision is detected when the first message of
+//! the remote Client arrives: the `Session` with the lower key is demoted, re-established
+//! in the `Remote` role and opens the message.
+//! Messages locked by the `Session` before it was demoted cannot be opened by the remote
+//! Client and must be sent again.
+//! A `Local` end keeps the secret it was established from until it commits its first
+//! ratchet step so that it can be demoted; the secret derives every chain of the session
+//! so the `Local` end cannot be serialised until then.
+//! A partner calling `accept` directly only interoperates if it has the higher key.
+//! Partners with the same identity key cannot be assigned roles and are refused with
+//! [Error::SameIdentity].
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  ratchet::Ratchet,
+  message::{Message, Header, Extensions,},
+  client::{Client, InnerClient, LocalClient, RemoteClient, DelegatedKey, LockHalf, OpenHalf, Error, aead::{Algorithm, Aes256Gcm,},},
+  typenum::consts,
+  generic_array::ArrayLength,
+};
+use rand::{RngCore, CryptoRng,};
+use x25519_dalek::{PublicKey, StaticSecret,};
+
+/// A Double-Ratchet Client whose role was chosen during establishment.
+pub enum Session<Digest, State, Algorithm = Aes256Gcm, Rounds = consts::U1, AadLength = consts::U0,>
+  where State: 'static + ArrayLength<u8>,
+    Algorithm: crate::client::aead::Algorithm,
+    AadLength: 'static + ArrayLength<u8>, {
+  /// This end took the initiating role.
+  Local(LocalClient<Digest, State, Algorithm, Rounds, AadLength,>),
+  /// This end took the partner role.
+  Remote(RemoteClient<Digest, State, Algorithm, Rounds, AadLength,>),
+}
+
+impl<D, S, A, R, L,> Session<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  /// Establishes communication with a remote Client.
+  /// 
+  /// Both Clients may call this function concurrently; the Client with the lowest public
+  /// key becomes the `Local` end and the other becomes the `Remote` end.
+  /// A `Local` end is demoted to the `Remote` end if the first message it receives shows
+  /// that the remote Client also initiated communication.
+  /// The remote Client must have a different identity key, otherwise
+  /// [Error::SameIdentity] is returned.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  #[inline]
+  pub fn establish(remote: &PublicKey, private_key: &StaticSecret,) -> Result<Self, Error> {
+    Self::establish_with_rng(remote, private_key, &mut rand::thread_rng(),)
+  }
+  /// Establishes communication with a remote Client using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// rand --- The source of randomness used to generate keys.  
+  #[inline]
+  pub fn establish_with_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, rand: &mut Rand,) -> Result<Self, Error>
+    where Rand: RngCore + CryptoRng, {
+    Self::establish_with_psk_and_rng(remote, private_key, None, rand,)
+  }
+  /// Establishes communication with a remote Client mixing a pre-shared key into the
+  /// session.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  #[inline]
+  pub fn establish_with_psk(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>,) -> Result<Self, Error> {
+    Self::establish_with_psk_and_rng(remote, private_key, psk, &mut rand::thread_rng(),)
+  }
+  /// Establishes communication with a remote Client mixing a pre-shared key into the
+  /// session using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The public key of the remote Client.  
+  /// private_key --- The private key to connect using.  
+  /// psk --- The secret shared with the remote Client out of band.  
+  /// rand --- The source of randomness used to generate keys.  
+  pub fn establish_with_psk_and_rng<Rand,>(remote: &PublicKey, private_key: &StaticSecret, psk: Option<&[u8]>, rand: &mut Rand,) -> Result<Self, Error>
+    where Rand: RngCore + CryptoRng, {
+    let local = PublicKey::from(private_key,);
+
+    //Equal keys would make both ends take the same role.
+    if local.as_bytes() == remote.as_bytes() { return Err(Error::SameIdentity) }
+
+    Ok(if is_local(&local, remote,) {
+      Session::Local(LocalClient::contend_with_psk_and_rng(remote, private_key, psk, rand,),)
+    } else {
+      Session::Remote(RemoteClient::accept_with_psk_and_rng(remote, private_key, psk, rand,),)
+    })
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client.
+  /// 
+  /// The message is authenticated before its key is handed out, see [LocalClient::delegate].
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  #[inline]
+  pub fn delegate(&mut self, message: &Message,) -> Result<DelegatedKey<A, L,>, Error> {
+    self.delegate_with_rng(message, &mut rand::thread_rng(),)
+  }
+  /// Extracts the key of a message so that it can be opened outside of this Client
+  /// using the passed source of randomness.
+  /// 
+  /// # Params
+  /// 
+  /// message --- The message to delegate.  
+  /// rand --- The source of randomness used to generate keys for a new ratchet step.  
+  #[inline]
+  pub fn delegate_with_rng<Rand,>(&mut self, message: &Message, rand: &mut Rand,) -> Result<DelegatedKey<A, L,>, Error>
+    where Rand: RngCore + CryptoRng, {
+    match self {
+      Session::Local(client) => client.delegate_with_rng(message, rand,),
+      Session::Remote(client) => client.delegate_with_rng(message, rand,),
+    }
+  }
+  /// Extracts the key of a message from its header without authenticating the message.
+  /// 
+  /// The header cannot take a ratchet step, see [LocalClient::delegate_header].
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header of the message to delegate.  
+  #[inline]
+  pub fn delegate_header(&mut self, header: &Header,) -> Result<DelegatedKey<A, L,>, Error> {
+    match self {
+      Session::Local(client) => client.delegate_header(header,),
+      Session::Remote(client) => client.delegate_header(header,),
+    }
+  }
+  /// Splits this Client into a [LockHalf] and an [OpenHalf] which can be used from
+  /// different threads.
+  /// 
+  /// A `Local` end can no longer be demoted once it is split.
+  #[inline]
+  pub fn split(self,) -> (LockHalf<D, S, A, R, L,>, OpenHalf<D, S, A, R, L,>,) {
+    match self {
+      Session::Local(client) => client.split(),
+      Session::Remote(client) => client.split(),
+    }
+  }
+}
+
+impl<D, S, A, R, L,> Session<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// Returns `true` if this end took the initiating role.
+  #[inline]
+  pub fn is_local(&self,) -> bool {
+    match self { Session::Local(_) => true, Session::Remote(_) => false, }
+  }
+  /// The state of this Client.
+  #[inline]
+  pub(crate) fn inner(&self,) -> &InnerClient<D, S, A, R, L,> {
+    match self {
+      Session::Local(client) => client.inner(),
+      Session::Remote(client) => client.inner(),
+    }
+  }
+  /// The state of this Client.
+  #[inline]
+  pub(crate) fn inner_mut(&mut self,) -> &mut InnerClient<D, S, A, R, L,> {
+    match self {
+      Session::Local(client) => client.inner_mut(),
+      Session::Remote(client) => client.inner_mut(),
+    }
+  }
+
+  state_accessors!();
+}
+
+impl<D, S, A, R, L,> Client for Session<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  #[inline]
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    match self {
+      Session::Local(client) => {
+        if !client.contended(&message,) { return client.open_pending_with_rng(message, buffer, rand,) }
+
+        //Both ends initiated communication, this end has the lower key so it is demoted.
+        let (client, data,) = client.demote_with_rng(message, buffer, rand,)?;
+
+        *self = Session::Remote(client,);
+        Ok(data)
+      },
+      Session::Remote(client) => client.open_pending_with_rng(message, buffer, rand,),
+    }
+  }
+  #[inline]
+  fn commit(&mut self,) {
+    match self {
+      Session::Local(client) => client.commit(),
+      Session::Remote(client) => client.commit(),
+    }
+  }
+  #[inline]
+  fn abort(&mut self,) {
+    match self {
+      Session::Local(client) => client.abort(),
+      Session::Remote(client) => client.abort(),
+    }
+  }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    match self {
+      Session::Local(client) => client.lock_with_extensions_and_rng(message, extensions, rand,),
+      Session::Remote(client) => client.lock_with_extensions_and_rng(message, extensions, rand,),
+    }
+  }
+}
+
+impl<D, S, A, R, L,> From<LocalClient<D, S, A, R, L,>> for Session<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  #[inline]
+  fn from(from: LocalClient<D, S, A, R, L,>,) -> Self { Session::Local(from) }
+}
+
+impl<D, S, A, R, L,> From<RemoteClient<D, S, A, R, L,>> for Session<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  #[inline]
+  fn from(from: RemoteClient<D, S, A, R, L,>,) -> Self { Session::Remote(from) }
+}
+
+/// Decides whether this end takes the initiating role.
+/// 
+/// Both ends compare the same pair of keys so exactly one end is chosen as long as the
+/// keys differ; the keys are compared as big endian integers from the final byte.
+/// 
+/// # Params
+/// 
+/// local --- The public key of this Client.  
+/// remote --- The public key of the remote Client.  
+fn is_local(local: &PublicKey, remote: &PublicKey,) -> bool {
+  local.as_bytes().iter().rev().lt(remote.as_bytes().iter().rev(),)
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use sha1::Sha1;
+  use std::{thread, sync::{mpsc, Arc, Barrier,},};
+
+  #[test]
+  fn test_session_roles() {
+    let key1 = PublicKey::from(&StaticSecret::from([1; 32],),);
+    let key2 = PublicKey::from(&StaticSecret::from([2; 32],),);
+
+    assert_ne!(is_local(&key1, &key2,), is_local(&key2, &key1,), "Both ends took the same role",);
+
+    let private_key = StaticSecret::from([1; 32],);
+    match Session::<Sha1, consts::U64,>::establish(&key1, &private_key,) {
+      Err(Error::SameIdentity) => {},
+      _ => panic!("Established a Session with its own identity"),
+    }
+  }
+  #[test]
+  fn test_session_concurrent() {
+    let barrier = Arc::new(Barrier::new(2,),);
+    let (send1, recv1,) = mpsc::channel();
+    let (send2, recv2,) = mpsc::channel();
+    let run = |private_key: [u8; 32], remote: [u8; 32], send: mpsc::Sender<Message>, recv: mpsc::Receiver<Message>, barrier: Arc<Barrier>,| move || {
+      let private_key = StaticSecret::from(private_key,);
+      let remote = StaticSecret::from(remote,);
+      let msg = PublicKey::from(&private_key,).as_bytes().to_vec();
+
+      //Start both ends at the same time.
+      barrier.wait();
+      let mut session = Session::<Sha1, consts::U64,>::establish(&(&remote).into(), &private_key,)
+        .expect("Error establishing Session");
+      //Both ends send before receiving anything.
+      send.send(session.lock(&mut msg.clone(),).expect("Error locking message"),)
+        .expect("Error sending message");
+
+      let mut buffer = Vec::new();
+      session.open(recv.recv().expect("Error receiving message"), &mut buffer,)
+        .expect("Error opening message");
+      assert_eq!(buffer.as_slice(), PublicKey::from(&remote,).as_bytes(), "Message corrupted",);
+
+      session.is_local()
+    };
+    let end1 = thread::spawn(run([1; 32], [2; 32], send1, recv2, barrier.clone(),),);
+    let end2 = thread::spawn(run([2; 32], [1; 32], send2, recv1, barrier,),);
+    let local1 = end1.join().expect("First end failed");
+    let local2 = end2.join().expect("Second end failed");
+
+    assert_ne!(local1, local2, "Both ends took the same role",);
+  }
+  #[test]
+  fn test_session_collision() {
+    let barrier = Arc::new(Barrier::new(2,),);
+    let (send1, recv1,) = mpsc::channel();
+    let (send2, recv2,) = mpsc::channel();
+    //The `Session` must have the lower key to be the end which is demoted.
+    let (key1, key2,) = if is_local(&PublicKey::from(&StaticSecret::from([1; 32],),), &PublicKey::from(&StaticSecret::from([2; 32],),),) {
+      ([1; 32], [2; 32],)
+    } else { ([2; 32], [1; 32],) };
+    let session = {
+      let barrier = barrier.clone();
+
+      thread::spawn(move || {
+        let private_key = StaticSecret::from(key1,);
+        let remote = StaticSecret::from(key2,);
+
+        //Start both ends at the same time.
+        barrier.wait();
+        let mut session = Session::<Sha1, consts::U64,>::establish(&(&remote).into(), &private_key,)
+          .expect("Error establishing Session");
+        assert!(session.is_local(), "Session did not initiate",);
+        send1.send(session.lock(&mut b"lost".to_vec(),).expect("Error locking message"),)
+          .expect("Error sending message");
+
+        let mut buffer = Vec::new();
+        session.open(recv2.recv().expect("Error receiving message"), &mut buffer,)
+          .expect("Error opening message");
+        assert_eq!(buffer.as_slice(), b"connect", "Message corrupted",);
+        assert!(!session.is_local(), "Session was not demoted",);
+
+        send1.send(session.lock(&mut b"session".to_vec(),).expect("Error locking message"),)
+          .expect("Error sending message");
+      })
+    };
+    let client = thread::spawn(move || {
+      let private_key = StaticSecret::from(key2,);
+      let remote = StaticSecret::from(key1,);
+
+      barrier.wait();
+      let mut client = LocalClient::<Sha1, consts::U64,>::connect(&(&remote).into(), &private_key,);
+      send2.send(client.lock(&mut b"connect".to_vec(),).expect("Error locking message"),)
+        .expect("Error sending message");
+
+      //The message locked before the `Session` was demoted is lost.
+      match client.open(recv1.recv().expect("Error receiving message"), &mut Vec::new(),) {
+        Err((_, Error::Decryption,)) => {},
+        _ => panic!("Opened a message locked by the other initiator"),
+      }
+
+      let mut buffer = Vec::new();
+      client.open(recv1.recv().expect("Error receiving message"), &mut buffer,)
+        .expect("Error opening message");
+      assert_eq!(buffer.as_slice(), b"session", "Message corrupted",);
+    });
+
+    session.join().expect("Session end failed");
+    client.join().expect("Connecting end failed");
+  }
+}
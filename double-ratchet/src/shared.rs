@@ -0,0 +1,315 @@
+//! Defines the [SharedClient] type used to share a Client between threads.
+//! 
+//! A `SharedClient` is a [LockHalf] and an [OpenHalf] behind separate mutexes so that
+//! threads locking messages never wait on threads opening messages.
+//! Every clone refers to the same Client; an open left pending by one clone blocks
+//! other opens until that clone commits or aborts it, exactly as for an unshared Client.
+//! Only the clone which started a pending open can commit or abort it; once that clone
+//! is dropped any clone can.
+//! If a thread panics while using a `SharedClient` every clone is poisoned and returns
+//! [Error::Poisoned].
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{
+  ratchet::Ratchet,
+  message::{Message, Extensions,},
+  client::{Client, LocalClient, RemoteClient, LockHalf, OpenHalf, Error, aead::{Algorithm, Aes256Gcm,},},
+  session::Session,
+  franking::Opening,
+  transcript::{self, Checkpoint,},
+  typenum::consts,
+  generic_array::ArrayLength,
+};
+use rand::{RngCore, CryptoRng,};
+use std::sync::{Arc, Weak, Mutex, MutexGuard,};
+
+/// The open half of a `SharedClient` and the handle which owns its pending open.
+struct Open<Digest, State, Algorithm, Rounds, AadLength,>
+  where State: 'static + ArrayLength<u8>,
+    Algorithm: crate::client::aead::Algorithm,
+    AadLength: 'static + ArrayLength<u8>, {
+  /// The half used to open messages.
+  half: OpenHalf<Digest, State, Algorithm, Rounds, AadLength,>,
+  /// The token of the handle which started the pending open.
+  owner: Option<Weak<()>>,
+}
+
+impl<D, S, A, R, L,> Open<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// Returns `true` if the handle with `token` can complete the pending open.
+  /// 
+  /// # Params
+  /// 
+  /// token --- The token of the handle.  
+  fn owned(&self, token: &Arc<()>,) -> bool {
+    match &self.owner {
+      //An open started by a dropped handle can be completed by any clone.
+      Some(owner) => owner.upgrade().map_or(true, |owner,| Arc::ptr_eq(&owner, token,),),
+      None => false,
+    }
+  }
+}
+
+/// A cloneable handle to a Client which can be used from many threads at once.
+pub struct SharedClient<Digest, State, Algorithm = Aes256Gcm, Rounds = consts::U1, AadLength = consts::U0,>
+  where State: 'static + ArrayLength<u8>,
+    Algorithm: crate::client::aead::Algorithm,
+    AadLength: 'static + ArrayLength<u8>, {
+  /// The half used to lock messages.
+  lock: Arc<Mutex<LockHalf<Digest, State, Algorithm, Rounds, AadLength,>>>,
+  /// The half used to open messages.
+  open: Arc<Mutex<Open<Digest, State, Algorithm, Rounds, AadLength,>>>,
+  /// The token identifying this handle.
+  token: Arc<()>,
+}
+
+impl<D, S, A, R, L,> SharedClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  /// Constructs a new `SharedClient` from the halves of a split Client.
+  /// 
+  /// # Params
+  /// 
+  /// lock --- The half used to lock messages.  
+  /// open --- The half used to open messages.  
+  #[inline]
+  pub fn new(lock: LockHalf<D, S, A, R, L,>, open: OpenHalf<D, S, A, R, L,>,) -> Self {
+    Self {
+      lock: Arc::new(Mutex::new(lock,),),
+      open: Arc::new(Mutex::new(Open { half: open, owner: None, },),),
+      token: Arc::new((),),
+    }
+  }
+  /// A [Checkpoint] of the messages this Client has sent and received.
+  pub fn checkpoint(&self,) -> Result<Checkpoint, Error> {
+    Ok(Checkpoint { sent: acquire(&self.lock,)?.sent(), received: acquire(&self.open,)?.half.received(), })
+  }
+  /// Compares the transcript of this Client with a [Checkpoint] from the remote Client.
+  /// 
+  /// Errors with [Error::Poisoned] if the Client is poisoned.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The latest checkpoint of the remote Client.  
+  #[inline]
+  pub fn verify_checkpoint(&self, remote: &Checkpoint,) -> Result<Result<(), transcript::Error>, Error> {
+    Ok(self.checkpoint()?.verify(remote,))
+  }
+  /// The franking [Opening] of the last message opened or `None` if it was not franked.
+  #[inline]
+  pub fn opening(&self,) -> Result<Option<Opening>, Error> { Ok(acquire(&self.open,)?.half.opening().cloned()) }
+  /// Returns `true` if this handle can commit or abort the pending open.
+  /// 
+  /// Returns `false` if the Client is poisoned.
+  #[inline]
+  pub fn owns_pending(&self,) -> bool { acquire(&self.open,).map_or(false, |open,| open.owned(&self.token,),) }
+}
+
+impl<D, S, A, R, L,> Client for SharedClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  #[inline]
+  fn open_pending_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    let mut open = match acquire(&self.open,) {
+      Ok(open) => open,
+      Err(e) => return Err((message, e,)),
+    };
+    let data = open.half.open_pending_with_rng(message, buffer, rand,)?;
+
+    open.owner = Some(Arc::downgrade(&self.token,),);
+    Ok(data)
+  }
+  #[inline]
+  fn open_with_rng<'a, Rand,>(&mut self, message: Message, buffer: &'a mut Vec<u8>, rand: &mut Rand,) -> Result<&'a mut [u8], (Message, Error,)>
+    where Rand: RngCore + CryptoRng, {
+    //The open and commit happen under one lock so no other clone can abort the open.
+    match acquire(&self.open,) {
+      Ok(mut open) => open.half.open_with_rng(message, buffer, rand,),
+      Err(e) => Err((message, e,)),
+    }
+  }
+  /// Completes the pending open started by this handle.
+  /// 
+  /// Does nothing if another live clone started the pending open or the Client is poisoned.
+  fn commit(&mut self,) {
+    let mut open = match acquire(&self.open,) { Ok(open) => open, Err(_) => return, };
+
+    if open.owned(&self.token,) { open.owner = None; open.half.commit() }
+  }
+  /// Cancels the pending open started by this handle.
+  /// 
+  /// Does nothing if another live clone started the pending open or the Client is poisoned.
+  fn abort(&mut self,) {
+    let mut open = match acquire(&self.open,) { Ok(open) => open, Err(_) => return, };
+
+    if open.owned(&self.token,) { open.owner = None; open.half.abort() }
+  }
+  #[inline]
+  fn lock_with_extensions_and_rng<Rand,>(&mut self, message: &mut [u8], extensions: Extensions, rand: &mut Rand,) -> Result<Message, Error>
+    where Rand: RngCore + CryptoRng, {
+    acquire(&self.lock,)?.lock_with_extensions_and_rng(message, extensions, rand,)
+  }
+}
+
+impl<D, S, A, R, L,> Clone for SharedClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>, {
+  #[inline]
+  fn clone(&self,) -> Self {
+    Self { lock: self.lock.clone(), open: self.open.clone(), token: Arc::new((),), }
+  }
+}
+
+impl<D, S, A, R, L,> From<LocalClient<D, S, A, R, L,>> for SharedClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  #[inline]
+  fn from(from: LocalClient<D, S, A, R, L,>,) -> Self {
+    let (lock, open,) = from.split();
+
+    Self::new(lock, open,)
+  }
+}
+
+impl<D, S, A, R, L,> From<RemoteClient<D, S, A, R, L,>> for SharedClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  #[inline]
+  fn from(from: RemoteClient<D, S, A, R, L,>,) -> Self {
+    let (lock, open,) = from.split();
+
+    Self::new(lock, open,)
+  }
+}
+
+impl<D, S, A, R, L,> From<Session<D, S, A, R, L,>> for SharedClient<D, S, A, R, L,>
+  where S: ArrayLength<u8>,
+    A: Algorithm,
+    L: ArrayLength<u8>,
+    Ratchet<D, S, R,>: RngCore + CryptoRng, {
+  #[inline]
+  fn from(from: Session<D, S, A, R, L,>,) -> Self {
+    let (lock, open,) = from.split();
+
+    Self::new(lock, open,)
+  }
+}
+
+/// Locks a mutex.
+/// 
+/// A panic part way through opening or locking a message can leave a half inconsistent so
+/// a poisoned mutex errors with [Error::Poisoned].
+/// 
+/// # Params
+/// 
+/// mutex --- The mutex to lock.  
+fn acquire<T,>(mutex: &Mutex<T>,) -> Result<MutexGuard<T>, Error> {
+  mutex.lock().map_err(|_,| Error::Poisoned,)
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use sha1::Sha1;
+  use x25519_dalek::StaticSecret;
+  use std::{thread, mem, collections::HashSet,};
+
+  #[test]
+  fn test_shared_client() {
+    const THREADS: u8 = 8;
+    const MESSAGES: u8 = 20;
+
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let local = SharedClient::from(LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,),);
+    let mut remote = SharedClient::from(RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,),);
+    //Lock messages from many threads at once.
+    let messages = (0..THREADS).map(|id,| {
+      let mut local = local.clone();
+
+      thread::spawn(move || (0..MESSAGES).map(|index,| {
+        local.lock(&mut [id, index,],).expect("Error locking message")
+      },).collect::<Vec<_>>(),)
+    },).collect::<Vec<_>>().into_iter()
+      .flat_map(|thread,| thread.join().expect("Error joining thread"),)
+      .collect::<Vec<_>>();
+    let mut other = remote.clone();
+
+    //A pending open blocks the other clones until it is committed or aborted.
+    remote.open_pending(messages[0].clone(), &mut Vec::new(),).expect("Error opening pending message");
+    assert!(remote.owns_pending(), "Pending open not owned",);
+    match other.open(messages[1].clone(), &mut Vec::new(),) {
+      Err((_, Error::Pending,)) => {},
+      _ => panic!("Opened while pending"),
+    }
+    //Only the clone which started the open can complete it.
+    assert!(!other.owns_pending(), "Pending open owned by another clone",);
+    other.commit();
+    other.abort();
+    match other.open(messages[1].clone(), &mut Vec::new(),) {
+      Err((_, Error::Pending,)) => {},
+      _ => panic!("Another clone completed the pending open"),
+    }
+
+    //A pending open left by a dropped clone can be aborted by any clone.
+    mem::drop(remote,);
+    let mut remote = other;
+    assert!(remote.owns_pending(), "Pending open of a dropped clone not released",);
+    remote.abort();
+
+    //Open the messages from many threads at once.
+    let opened = messages.chunks(MESSAGES as usize,).map(|messages,| {
+      let mut remote = remote.clone();
+      let messages = messages.to_vec();
+
+      thread::spawn(move || messages.into_iter().map(|message,| {
+        let mut buffer = Vec::new();
+
+        remote.open(message, &mut buffer,).expect("Error opening message");
+        (buffer[0], buffer[1],)
+      },).collect::<Vec<_>>(),)
+    },).collect::<Vec<_>>().into_iter()
+      .flat_map(|thread,| thread.join().expect("Error joining thread"),)
+      .collect::<HashSet<_>>();
+
+    assert_eq!(opened.len(), THREADS as usize * MESSAGES as usize, "Messages lost",);
+    assert_eq!(local.verify_checkpoint(&remote.checkpoint().expect("Error taking checkpoint"),), Ok(Ok(()),), "Transcripts differ",);
+  }
+  #[test]
+  fn test_shared_client_poisoned() {
+    let local_sec = StaticSecret::from([1; 32],);
+    let remote_sec = StaticSecret::from([2; 32],);
+    let mut local = SharedClient::from(LocalClient::<Sha1, consts::U64,>::connect(&(&remote_sec).into(), &local_sec,),);
+    let mut remote = SharedClient::from(RemoteClient::<Sha1, consts::U64,>::accept(&(&local_sec).into(), &remote_sec,),);
+    let message = local.lock(&mut [1, 2, 3,],).expect("Error locking message");
+    let open = remote.open.clone();
+
+    //A thread panics while holding the open half.
+    let _ = thread::spawn(move || {
+      let _open = open.lock();
+
+      panic!("Poisoning the open half");
+    },).join();
+
+    match remote.open(message, &mut Vec::new(),) {
+      Err((_, Error::Poisoned,)) => {},
+      _ => panic!("Opened with a poisoned Client"),
+    }
+    assert_eq!(remote.checkpoint().err(), Some(Error::Poisoned), "Checkpoint of a poisoned Client",);
+    assert_eq!(remote.opening().err(), Some(Error::Poisoned), "Opening of a poisoned Client",);
+    assert!(!remote.owns_pending(), "Pending open owned by a poisoned Client",);
+  }
+}
@@ -0,0 +1,151 @@
+//! Defines the transcripts used to detect messages dropped by the transport.
+//! 
+//! The ratchet tolerates missing and reordered messages so it cannot tell when the last
+//! messages of a conversation were silently dropped.
+//! Every Client accumulates the headers it has sent and received into a [Checkpoint];
+//! the accumulators do not depend on the order messages are processed in so the partners
+//! can exchange checkpoints at any time and compare them with [Checkpoint::verify].
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::message::Header;
+use ring::digest;
+
+mod serde;
+
+/// The context hashed with every header.
+const TRANSCRIPT_CONTEXT: &[u8] = b"DoubleRatchetTranscript";
+
+/// An order independent accumulator of message headers.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default,)]
+pub struct Accumulator {
+  /// The number of headers accumulated.
+  count: u64,
+  /// The sum of the header digests modulo `2^256`.
+  digest: [u8; 32],
+}
+
+impl Accumulator {
+  /// The number of headers accumulated.
+  #[inline]
+  pub fn count(&self,) -> u64 { self.count }
+  /// The sum of the header digests.
+  #[inline]
+  pub fn digest(&self,) -> &[u8; 32] { &self.digest }
+  /// Adds a header to the accumulator.
+  /// 
+  /// # Params
+  /// 
+  /// header --- The header to accumulate.  
+  pub(crate) fn push(&mut self, header: &Header,) {
+    let mut context = digest::Context::new(&digest::SHA256,);
+
+    context.update(TRANSCRIPT_CONTEXT,);
+    context.update(&header.public_key,);
+    context.update(&header.message_index.to_be_bytes(),);
+    context.update(&header.previous_step.to_be_bytes(),);
+
+    //Add the digest as a little endian integer.
+    let mut carry = 0;
+    for (sum, byte,) in self.digest.iter_mut().zip(context.finish().as_ref(),) {
+      let total = *sum as u16 + *byte as u16 + carry;
+
+      *sum = total as u8;
+      carry = total >> 8;
+    }
+
+    self.count += 1;
+  }
+}
+
+/// A snapshot of the headers a Client has sent and received.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default,)]
+pub struct Checkpoint {
+  /// The headers of the messages sent.
+  pub sent: Accumulator,
+  /// The headers of the messages received.
+  pub received: Accumulator,
+}
+
+impl Checkpoint {
+  /// Compares this local `Checkpoint` with a `Checkpoint` from the remote Client.
+  /// 
+  /// Messages which are still in flight are reported as [Error::Incomplete]; if they
+  /// are never received the transport dropped them.
+  /// 
+  /// # Params
+  /// 
+  /// remote --- The latest `Checkpoint` of the remote Client.  
+  pub fn verify(&self, remote: &Checkpoint,) -> Result<(), Error> {
+    //The remote Client cannot have received more than was sent and this Client cannot
+    //have received more than the remote Client sent.
+    if remote.received.count > self.sent.count || self.received.count > remote.sent.count {
+      return Err(Error::Mismatch)
+    }
+    //Transcripts of the same length must be identical.
+    if remote.received.count == self.sent.count && remote.received.digest != self.sent.digest {
+      return Err(Error::Mismatch)
+    }
+    if self.received.count == remote.sent.count && self.received.digest != remote.sent.digest {
+      return Err(Error::Mismatch)
+    }
+
+    match remote.sent.count - self.received.count {
+      0 => Ok(()),
+      missing => Err(Error::Incomplete(missing,)),
+    }
+  }
+}
+
+/// An integrity event raised comparing transcript checkpoints.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Error {
+  /// The contained number of messages sent by the remote Client have not been received.
+  Incomplete(u64,),
+  /// The transcripts disagree; messages were dropped, replayed or came from another session.
+  Mismatch,
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  fn header(message_index: u32,) -> Header {
+    Header { public_key: [1; 32], message_index, ..Header::default() }
+  }
+
+  #[test]
+  fn test_accumulator() {
+    let mut forward = Accumulator::default();
+    let mut backward = Accumulator::default();
+
+    for index in 0..10 { forward.push(&header(index,),); }
+    for index in (0..10).rev() { backward.push(&header(index,),); }
+
+    assert_eq!(forward, backward, "Accumulator depends on order",);
+    assert_eq!(forward.count(), 10, "Bad accumulator count",);
+
+    backward.push(&header(10,),);
+    assert_ne!(forward.digest(), backward.digest(), "Accumulator ignored a header",);
+  }
+  #[test]
+  fn test_checkpoint() {
+    let mut local = Checkpoint::default();
+    let mut remote = Checkpoint::default();
+
+    for index in 0..3 { local.sent.push(&header(index,),); }
+    remote.received.push(&header(0,),);
+    remote.received.push(&header(2,),);
+
+    assert_eq!(remote.verify(&local,), Err(Error::Incomplete(1)), "Missing message not detected",);
+    assert_eq!(local.verify(&remote,), Ok(()), "Local transcript rejected",);
+
+    remote.received.push(&header(1,),);
+    assert_eq!(remote.verify(&local,), Ok(()), "Complete transcript rejected",);
+
+    let mut other = Checkpoint::default();
+    for index in 3..6 { other.received.push(&header(index,),); }
+    assert_eq!(other.verify(&local,), Err(Error::Mismatch), "Different transcript accepted",);
+  }
+}
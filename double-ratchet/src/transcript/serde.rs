@@ -0,0 +1,66 @@
+//! Defines serde for transcript checkpoints.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer,},
+  de::{Deserialize, Deserializer,},
+};
+
+impl Serialize for Accumulator {
+  #[inline]
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    (self.count, &self.digest,).serialize(serializer,)
+  }
+}
+
+impl<'de,> Deserialize<'de> for Accumulator {
+  #[inline]
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    let (count, digest,) = <(u64, [u8; 32],)>::deserialize(deserializer,)?;
+
+    Ok(Accumulator { count, digest, })
+  }
+}
+
+impl Serialize for Checkpoint {
+  #[inline]
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    (&self.sent, &self.received,).serialize(serializer,)
+  }
+}
+
+impl<'de,> Deserialize<'de> for Checkpoint {
+  #[inline]
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    let (sent, received,) = <(Accumulator, Accumulator,)>::deserialize(deserializer,)?;
+
+    Ok(Checkpoint { sent, received, })
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_checkpoint_serde() {
+    let mut checkpoint = Checkpoint::default();
+
+    checkpoint.sent.push(&Header::default(),);
+    checkpoint.received.push(&Header { message_index: 1, ..Header::default() },);
+
+    let serialised = serde_cbor::ser::to_vec_packed(&checkpoint,)
+      .expect("Error serialising the Checkpoint");
+    let other: Checkpoint = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the Checkpoint");
+
+    assert_eq!(checkpoint, other, "Checkpoint deserialised incorrectly",);
+  }
+}
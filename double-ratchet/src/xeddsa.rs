@@ -0,0 +1,290 @@
+//! Defines [XEdDSA](https://signal.org/docs/specifications/xeddsa/) signatures using the
+//! X25519 identity keys of a [Client](crate::Client).
+//! 
+//! Signatures are verified as libsignal verifies them: the top bit of `s` carries the
+//! sign of the signer's Edwards public key. Signatures made here always clear that bit so
+//! they verify with either convention.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use crate::{generic_array::GenericArray, typenum::consts::U64,};
+use curve25519_dalek::{
+  constants::ED25519_BASEPOINT_TABLE,
+  edwards::EdwardsPoint,
+  montgomery::MontgomeryPoint,
+  scalar::Scalar,
+};
+use sha2::{Sha512, Digest,};
+use clear_on_drop::ClearOnDrop;
+use rand::{RngCore, CryptoRng,};
+use x25519_dalek::{PublicKey, StaticSecret,};
+
+mod serde;
+
+/// The domain separation prefix of `hash1` (`2^256 - 2` in little endian).
+const HASH1_PREFIX: [u8; 32] = [
+  0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+  0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+  0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+  0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+/// Checks that the little endian field element in `bytes` is less than `2^255 - 19`.
+/// 
+/// # Params
+/// 
+/// bytes --- The encoded field element to check.  
+fn is_reduced(bytes: &[u8; 32],) -> bool {
+  //The prime encoded in little endian.
+  const PRIME: [u8; 32] = [
+    0xED, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F,
+  ];
+
+  //Compare from the most significant byte.
+  for (a, b,) in bytes.iter().rev().zip(PRIME.iter().rev(),) {
+    if a != b { return a < b }
+  }
+
+  false
+}
+
+/// An XEdDSA signature.
+#[derive(PartialEq, Eq, Clone, Debug,)]
+pub struct Signature(GenericArray<u8, U64>,);
+
+impl Signature {
+  /// Constructs a `Signature` from its encoded bytes.
+  /// 
+  /// Returns `None` if `bytes` is not 64 bytes long.
+  /// 
+  /// # Params
+  /// 
+  /// bytes --- The encoded signature.  
+  #[inline]
+  pub fn from_bytes(bytes: &[u8],) -> Option<Self> {
+    GenericArray::from_exact_iter(bytes.iter().copied(),).map(Signature,)
+  }
+  /// The encoded bytes of the `Signature`.
+  #[inline]
+  pub fn as_bytes(&self,) -> &[u8] { &self.0 }
+}
+
+/// Signs `message` with an X25519 private key.
+/// 
+/// # Params
+/// 
+/// private_key --- The private key to sign with.  
+/// message --- The message to sign.  
+#[inline]
+pub fn sign(private_key: &StaticSecret, message: &[u8],) -> Signature {
+  sign_with_rng(private_key, message, &mut rand::thread_rng(),)
+}
+
+/// Signs `message` with an X25519 private key using the passed source of randomness.
+/// 
+/// # Params
+/// 
+/// private_key --- The private key to sign with.  
+/// message --- The message to sign.  
+/// rand --- The source of randomness used to generate the signing nonce.  
+pub fn sign_with_rng<Rand,>(private_key: &StaticSecret, message: &[u8], rand: &mut Rand,) -> Signature
+  where Rand: RngCore + CryptoRng, {
+  let mut nonce = ClearOnDrop::new(GenericArray::default(),);
+
+  rand.fill_bytes(&mut nonce,);
+  sign_with_nonce(private_key, message, &nonce,)
+}
+
+/// Signs `message` with an X25519 private key using explicit nonce data.
+/// 
+/// `nonce` must be secret random data; reusing a nonce for different messages does not
+/// leak the key but the nonce should never be predictable.
+/// 
+/// # Params
+/// 
+/// private_key --- The private key to sign with.  
+/// message --- The message to sign.  
+/// nonce --- The random data mixed into the signing nonce.  
+pub fn sign_with_nonce(private_key: &StaticSecret, message: &[u8], nonce: &GenericArray<u8, U64>,) -> Signature {
+  //Calculate the Edwards key pair from the Montgomery private key.
+  let (public_key, key,) = {
+    let mut bytes = private_key.to_bytes();
+    let key = Scalar::from_bytes_mod_order(bytes,);
+    ClearOnDrop::new(bytes.as_mut(),);
+    let mut public_key = (&key * &ED25519_BASEPOINT_TABLE).compress().to_bytes();
+    //Force the sign bit of the public key to zero.
+    let negative = public_key[31] & 0x80 != 0;
+    public_key[31] &= 0x7F;
+
+    (public_key, if negative { -key } else { key },)
+  };
+  let r = Scalar::from_hash(Sha512::new()
+    .chain(&HASH1_PREFIX[..],)
+    .chain(key.as_bytes(),)
+    .chain(message,)
+    .chain(nonce.as_slice(),),
+  );
+  let big_r = (&r * &ED25519_BASEPOINT_TABLE).compress();
+  let h = Scalar::from_hash(Sha512::new()
+    .chain(big_r.as_bytes(),)
+    .chain(&public_key[..],)
+    .chain(message,),
+  );
+  let s = r + &h * &key;
+  let mut signature = GenericArray::default();
+
+  signature[..32].copy_from_slice(big_r.as_bytes(),);
+  signature[32..].copy_from_slice(s.as_bytes(),);
+
+  Signature(signature,)
+}
+
+/// Verifies that `signature` is a signature of `message` by the owner of `public_key`.
+/// 
+/// Signatures with a non-canonical `R` or `s` are refused.
+/// 
+/// # Params
+/// 
+/// public_key --- The public key of the signer.  
+/// message --- The signed message.  
+/// signature --- The signature to verify.  
+pub fn verify(public_key: &PublicKey, message: &[u8], signature: &Signature,) -> Result<(), Error> {
+  use ring::constant_time;
+
+  let (big_r, s,) = signature.0.split_at(32,);
+  let mut r_y = [0; 32];
+  let mut s_bytes = [0; 32];
+
+  r_y.copy_from_slice(big_r,);
+  r_y[31] &= 0x7F;
+  s_bytes.copy_from_slice(s,);
+  //The sign of the Edwards public key is sent in the top bit of `s`.
+  let sign = s_bytes[31] >> 7;
+  s_bytes[31] &= 0x7F;
+
+  if !is_reduced(public_key.as_bytes(),) { return Err(Error::InvalidKey) }
+  //Reject signatures with unreduced coordinates or scalars.
+  if !is_reduced(&r_y,) { return Err(Error::InvalidSignature) }
+  let s = Scalar::from_canonical_bytes(s_bytes,).ok_or(Error::InvalidSignature,)?;
+
+  //Convert the Montgomery public key into an Edwards point with the signed sign bit.
+  let public_key = MontgomeryPoint(*public_key.as_bytes(),).to_edwards(sign,)
+    .ok_or(Error::InvalidKey,)?;
+  let h = Scalar::from_hash(Sha512::new()
+    .chain(big_r,)
+    .chain(public_key.compress().as_bytes(),)
+    .chain(message,),
+  );
+  let check = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-h, &public_key, &s,)
+    .compress();
+
+  constant_time::verify_slices_are_equal(check.as_bytes(), big_r,)
+    .map_err(|_,| Error::InvalidSignature,)
+}
+
+/// An error returned from verifying a [Signature].
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum Error {
+  /// The public key is not a valid X25519 public key.
+  InvalidKey,
+  /// The signature does not match the message and public key.
+  InvalidSignature,
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  /// Decodes a hex string.
+  fn hex(hex: &str,) -> Vec<u8> {
+    (0..hex.len()).step_by(2,)
+      .map(|index,| u8::from_str_radix(&hex[index..index + 2], 16,).expect("Bad hex"),)
+      .collect()
+  }
+
+  /// The identity key pair of Alice from the libsignal `Curve25519` tests.
+  const LIBSIGNAL_PRIVATE_KEY: &str = "c097248412e58bf05df487968205132794178e367637f5818f81e0e6ce73e865";
+  const LIBSIGNAL_PUBLIC_KEY: &str = "ab7e717d4a163b7d9a1d8071dfe9dcf8cdcd1cea3339b6356be84d887e322c64";
+  /// The serialised ephemeral public key signed in the libsignal tests.
+  const LIBSIGNAL_MESSAGE: &str = "05edce9d9c415ca78cb7252e72c2c4a554d3eb29485a0e1d503118d1a82d99fb4a";
+  /// The libsignal signature of the ephemeral key; its sign bit is set.
+  const LIBSIGNAL_SIGNATURE: &str = "5de88ca9a89b4a115da79109c67c9c7464a3e4180274f1cb8c63c2984e286dfbede82deb9dcd9fae0bfbb821569b3d9001bd8130cd11d486cef047bd60b86e88";
+
+  #[test]
+  fn test_xeddsa_vectors() {
+    let private_key = {
+      let mut bytes = [0; 32];
+
+      bytes.copy_from_slice(&hex(LIBSIGNAL_PRIVATE_KEY,),);
+      StaticSecret::from(bytes,)
+    };
+    let public_key = PublicKey::from(&private_key,);
+    let message = hex(LIBSIGNAL_MESSAGE,);
+    let signature = Signature::from_bytes(&hex(LIBSIGNAL_SIGNATURE,),).expect("Bad signature length");
+
+    assert_eq!(public_key.as_bytes().as_ref(), hex(LIBSIGNAL_PUBLIC_KEY,).as_slice(), "Bad public key",);
+    assert_eq!(verify(&public_key, &message, &signature,), Ok(()), "Failed to verify the libsignal signature",);
+    assert_eq!(verify(&public_key, &message[1..], &signature,), Err(Error::InvalidSignature),
+      "Verified the libsignal signature for the wrong message",
+    );
+
+    //Signatures made here clear the sign bit.
+    let own = sign_with_nonce(&private_key, &message, &GenericArray::default(),);
+    assert_eq!(own.as_bytes()[63] & 0x80, 0, "Signature has a sign bit",);
+    assert_eq!(verify(&public_key, &message, &own,), Ok(()), "Failed to verify signature",);
+
+    //The libsignal signature with `L` added to `s`.
+    let unreduced_s = Signature::from_bytes(&hex(concat!(
+      "5de88ca9a89b4a115da79109c67c9c7464a3e4180274f1cb8c63c2984e286dfb",
+      "dabc2348b830b206e297b0c434951ca501bd8130cd11d486cef047bd60b86e98",
+    ),),).expect("Bad signature length");
+    assert_eq!(verify(&public_key, &message, &unreduced_s,), Err(Error::InvalidSignature),
+      "Verified a non-canonical s",
+    );
+
+    //The libsignal signature with `R` encoding `y = p`.
+    let mut unreduced_r = signature.as_bytes().to_vec();
+    unreduced_r[..32].copy_from_slice(&hex("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",),);
+    let unreduced_r = Signature::from_bytes(&unreduced_r,).expect("Bad signature length");
+    assert_eq!(verify(&public_key, &message, &unreduced_r,), Err(Error::InvalidSignature),
+      "Verified a non-canonical R",
+    );
+  }
+  #[test]
+  fn test_xeddsa() {
+    let private_key = StaticSecret::new(&mut rand::thread_rng(),);
+    let public_key = PublicKey::from(&private_key,);
+    let other_key = PublicKey::from(&StaticSecret::new(&mut rand::thread_rng(),),);
+    let message = b"a signed message";
+    let signature = sign(&private_key, message,);
+
+    assert_eq!(verify(&public_key, message, &signature,), Ok(()), "Failed to verify signature",);
+    assert_eq!(verify(&public_key, b"another message", &signature,), Err(Error::InvalidSignature),
+      "Verified the wrong message",
+    );
+    assert_eq!(verify(&other_key, message, &signature,), Err(Error::InvalidSignature),
+      "Verified with the wrong key",
+    );
+
+    let mut corrupted = signature.as_bytes().to_vec();
+    corrupted[40] ^= 1;
+    let corrupted = Signature::from_bytes(&corrupted,).expect("Bad signature length");
+    assert_eq!(verify(&public_key, message, &corrupted,), Err(Error::InvalidSignature),
+      "Verified a corrupted signature",
+    );
+
+    let mut oversized = signature.as_bytes().to_vec();
+    oversized[63] |= 0xE0;
+    let oversized = Signature::from_bytes(&oversized,).expect("Bad signature length");
+    assert_eq!(verify(&public_key, message, &oversized,), Err(Error::InvalidSignature),
+      "Verified an oversized scalar",
+    );
+    assert_eq!(verify(&[0xFF; 32].into(), message, &signature,), Err(Error::InvalidKey),
+      "Verified with an unreduced key",
+    );
+  }
+}
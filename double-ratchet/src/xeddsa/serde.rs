@@ -0,0 +1,46 @@
+//! Defines serde for Signature.
+//! 
+//! Author -- daniel.bechaz@gmail.com  
+//! Last Moddified --- 2026-10-18
+
+use super::*;
+use ::serde::{
+  ser::{Serialize, Serializer,},
+  de::{Deserialize, Deserializer,},
+};
+
+impl Serialize for Signature {
+  #[inline]
+  fn serialize<S,>(&self, serializer: S,) -> Result<S::Ok, S::Error>
+    where S: Serializer, {
+    Serialize::serialize(self.as_bytes(), serializer,)
+  }
+}
+
+impl<'de,> Deserialize<'de> for Signature {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    use ::serde::de::{Error, Unexpected,};
+
+    let bytes = Box::<[u8]>::deserialize(deserializer,)?;
+
+    Signature::from_bytes(&bytes,)
+      .ok_or_else(|| Error::invalid_value(Unexpected::Seq, &"a slice of length 64",),)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_signature_serde() {
+    let signature = sign(&StaticSecret::from([1; 32],), b"message",);
+    let serialised = serde_cbor::ser::to_vec_packed(&signature,)
+      .expect("Error serialising the Signature");
+    let other = serde_cbor::from_slice(&serialised,)
+      .expect("Error deserialising the Signature");
+
+    assert_eq!(signature, other, "Signature deserialised incorrectly",);
+  }
+}